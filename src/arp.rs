@@ -0,0 +1,146 @@
+use arrayvec::ArrayVec;
+use rand::{Rng, rngs::SmallRng};
+
+use crate::{
+    consts::KEYS_NR,
+    key::Keys,
+    shared::{ArpMode, ArpSettings, Fox3oscShared},
+};
+
+/// Cycles through the held notes at a tempo-synced rate, turning them into note-ons and note-offs
+/// for the keys.
+pub struct Arpeggiator {
+    /// Notes currently held down along with their velocity, sorted by pitch.
+    held: ArrayVec<(usize, u8), KEYS_NR>,
+    /// The note the arpeggiator is currently sounding.
+    playing: Option<usize>,
+    /// Position in the pattern of the next step.
+    step: usize,
+    /// Samples left until the next step. The next step is due when this reaches 0.
+    countdown: f64,
+}
+
+impl Arpeggiator {
+    pub fn new() -> Self {
+        Self {
+            held: ArrayVec::new(),
+            playing: None,
+            step: 0,
+            countdown: 0.0,
+        }
+    }
+
+    /// Whether there are notes held which the arpeggiator needs to keep cycling through.
+    pub fn is_active(&self) -> bool {
+        !self.held.is_empty()
+    }
+
+    /// Adds a note to the pattern. The pattern starts right away if it's the first note held.
+    pub fn hold(&mut self, note: usize, velocity: u8) {
+        debug_assert!(note < KEYS_NR);
+
+        if self.held.is_empty() {
+            self.step = 0;
+            self.countdown = 0.0;
+        }
+
+        match self.held.binary_search_by_key(&note, |&(note, _)| note) {
+            Ok(idx) => self.held[idx].1 = velocity,
+            // There are only KEYS_NR different notes, so this can never overflow the capacity.
+            Err(idx) => self.held.insert(idx, (note, velocity)),
+        }
+    }
+
+    /// Removes a note from the pattern. Releases the sounding note if no more notes are held.
+    pub fn unhold(&mut self, note: usize, keys: &mut Keys) {
+        self.held.retain(|&mut (held, _)| held != note);
+        if self.held.is_empty() {
+            self.stop(keys);
+        }
+    }
+
+    /// Releases every key and forgets the held notes. Used when the arpeggiator gets toggled so no
+    /// voice is left hanging mid-pattern.
+    pub fn stop(&mut self, keys: &mut Keys) {
+        self.held.clear();
+        self.playing = None;
+        keys.release_all();
+    }
+
+    /// Plays the next step of the pattern if it's due. Returns the number of samples until the step
+    /// after it.
+    pub fn tick(
+        &mut self,
+        tempo: f64,
+        sample_rate: f32,
+        keys: &mut Keys,
+        shared: &Fox3oscShared,
+        rng: &mut SmallRng,
+    ) -> usize {
+        let settings = shared.get_arp_settings();
+        let samples_per_step = settings.rate.beats() * 60.0 / tempo * sample_rate as f64;
+
+        if self.countdown <= 0.0 {
+            self.countdown += samples_per_step;
+
+            if let Some(note) = self.playing.take() {
+                keys.release(note, shared);
+            }
+
+            if let Some((note, velocity)) = self.next_note(settings, shared.n_tet() as usize, rng) {
+                keys.on(note, velocity, shared);
+                self.playing = Some(note);
+            }
+        }
+
+        (self.countdown.ceil() as usize).max(1)
+    }
+
+    /// Advances the arpeggiator's clock by a number of samples.
+    pub fn elapse(&mut self, samples: usize) {
+        if self.is_active() {
+            self.countdown -= samples as f64;
+        }
+    }
+
+    fn next_note(
+        &mut self,
+        settings: ArpSettings,
+        octave_steps: usize,
+        rng: &mut SmallRng,
+    ) -> Option<(usize, u8)> {
+        if self.held.is_empty() {
+            return None;
+        }
+
+        let len = self.held.len() * settings.octaves as usize;
+        let idx = match settings.mode {
+            ArpMode::Up => self.step % len,
+            ArpMode::Down => len - 1 - self.step % len,
+            ArpMode::UpDown if len > 1 => {
+                let period = len * 2 - 2;
+                let position = self.step % period;
+
+                if position < len {
+                    position
+                } else {
+                    period - position
+                }
+            }
+            ArpMode::UpDown => 0,
+            ArpMode::Random => rng.random_range(0..len),
+        };
+
+        self.step += 1;
+
+        let (note, velocity) = self.held[idx % self.held.len()];
+        let note = note + (idx / self.held.len()) * octave_steps;
+
+        // Notes transposed above the keyboard just fall silent for their step.
+        if note < KEYS_NR {
+            Some((note, velocity))
+        } else {
+            None
+        }
+    }
+}
@@ -1,7 +1,14 @@
+use std::ops::Range;
+
 use arrayvec::ArrayVec;
 use clack_extensions::params::PluginAudioProcessorParams;
 use clack_plugin::{
-    events::{io::InputEventsIter, spaces::CoreEventSpace},
+    events::{
+        Match, UnknownEvent,
+        event_types::{NoteExpressionEvent, NoteExpressionType, ParamModEvent},
+        io::InputEventsIter,
+        spaces::CoreEventSpace,
+    },
     host::HostAudioProcessorHandle,
     plugin::{PluginAudioProcessor, PluginError},
     prelude::{InputEvents, OutputEvents},
@@ -10,48 +17,559 @@ use clack_plugin::{
 use rand::{SeedableRng, rngs::SmallRng};
 
 use crate::{
+    arp::Arpeggiator,
+    chorus::Chorus,
     consts::{
-        KEYS_NR, MAX_NOTES_NR, MIDI_CC, MIDI_CC_ALL_NOTES_OFF, MIDI_CC_ALL_SOUNDS_OFF, MIDI_OFF,
-        MIDI_ON, OSC_NR, PARAMETER_LEVEL_1, PARAMETER_LEVEL_3,
+        CHANNEL_NR, KEYS_NR, LFO_PITCH_RANGE, MAX_NOTES_NR, MIDI_CC, MIDI_CC_ALL_NOTES_OFF,
+        MIDI_CC_ALL_SOUNDS_OFF, MIDI_CC_SUSTAIN, MIDI_CHANNEL_AFTERTOUCH, MIDI_OFF, MIDI_ON,
+        MIDI_PITCH_BEND, MIDI_POLY_AFTERTOUCH, OSC_NR, PARAMETER_ARP_ON, PARAMETER_INIT,
+        PARAMETER_LEVEL_1, PARAMETER_LEVEL_3, PARAMETER_RANDOMIZE, PARAMETER_VOICES,
+        PITCH_BEND_CENTER,
     },
-    key::{Key, Keys, NoteData},
+    delay::Delay,
+    filter::{self, FilterCoefficients},
+    key::{Frame, Keys, NoteData, Tuning},
+    lfo::Lfo,
+    limiter::Limiter,
     main_thread::Fox3oscMainThread,
-    shared::Fox3oscShared,
+    math,
+    shared::{Fox3oscShared, LfoDestination, Temperament},
+    smooth::Smoother,
+    transport::Transport,
 };
 
 pub struct Fox3oscAudioProcessor<'a> {
     note_data: ArrayVec<NoteData, MAX_NOTES_NR>,
+    /// The concert pitch the note data was worked out for.
+    concert_pitch: f32,
+    /// The temperament the note data was worked out for.
+    temperament: Temperament,
+    /// The keys are mixed into this before being copied into the output channels.
+    frames: Vec<Frame>,
+    /// Number of channels of the output port. A mono output gets the channels of the frames
+    /// averaged.
+    output_channels: usize,
     keys: Keys,
+    arp: Arpeggiator,
+    chorus: Chorus,
+    delay: Delay,
+    limiter: Limiter,
+    /// The LFO shared by every key.
+    lfo: Lfo,
+    /// The gain the LFO turns the keys' mix down by when it modulates the level.
+    lfo_gain: Smoother,
+    /// How hard the mix is driven into the soft clipper.
+    drive: Smoother,
+    /// The master output gain.
+    gain: Smoother,
+    /// The host's transport, read at the start of every block.
+    transport: Transport,
+    /// The pitch wheel's position, from -1.0 when fully down to almost 1.0 when fully up.
+    pitch_bend: f32,
+    /// Whether the sustain pedal is down.
+    sustain_pedal: bool,
+    /// Notes which were let go of while the sustain pedal was down, to be released once it's up.
+    sustained_notes: [bool; KEYS_NR],
+    sample_rate: f32,
+    /// Random numbers for the patch randomizer and the arpeggiator. The keys draw from their own.
     rng: SmallRng,
     shared: &'a Fox3oscShared,
 }
 
-impl Fox3oscAudioProcessor<'_> {
+impl<'a> Fox3oscAudioProcessor<'a> {
+    /// Creates a processor rendering up to `max_frames` frames at a time. It doesn't need a host,
+    /// so it can be driven headless as well, with [`Self::render_batch`].
+    pub(crate) fn new(
+        shared: &'a Fox3oscShared,
+        sample_rate: f32,
+        max_frames: usize,
+        output_channels: usize,
+    ) -> Self {
+        let concert_pitch = shared.get_concert_pitch();
+        let temperament = shared.get_temperament();
+
+        Self {
+            shared,
+            note_data: self::note_data(shared, sample_rate, concert_pitch, temperament),
+            concert_pitch,
+            temperament,
+            frames: vec![[0.0; CHANNEL_NR]; max_frames],
+            output_channels,
+            sample_rate,
+            arp: Arpeggiator::new(),
+            chorus: Chorus::new(sample_rate),
+            delay: Delay::new(sample_rate),
+            limiter: Limiter::new(sample_rate),
+            lfo: Lfo::new(),
+            lfo_gain: Smoother::new(1.0, sample_rate),
+            drive: Smoother::new(0.0, sample_rate),
+            gain: Smoother::new(1.0, sample_rate),
+            transport: Transport::new(),
+            pitch_bend: 0.0,
+            sustain_pedal: false,
+            sustained_notes: [false; KEYS_NR],
+            rng: SmallRng::seed_from_u64(0xB00B5),
+            keys: Keys::new(sample_rate),
+        }
+    }
+
+    /// Renders the frames in `bounds` into the processor's frames, the keys and every effect
+    /// included. This is everything `process` does past handling events and copying the frames into
+    /// the host's buffers, so it runs without any of the host's wrappers. Returns
+    /// [`ProcessStatus::Continue`] if anything's still sounding.
+    fn render_batch(&mut self, bounds: Range<usize>, tempo: f64) -> ProcessStatus {
+        let mut status = ProcessStatus::Sleep;
+
+        // The note data only gets worked out again when the concert pitch or the temperament
+        // changes, since it takes a `powf` per note.
+        let concert_pitch = self.shared.get_concert_pitch();
+        let temperament = self.shared.get_temperament();
+        if concert_pitch != self.concert_pitch || temperament != self.temperament {
+            self.note_data =
+                self::note_data(self.shared, self.sample_rate, concert_pitch, temperament);
+            self.concert_pitch = concert_pitch;
+            self.temperament = temperament;
+        }
+
+        let levels = self.shared.modulated_levels();
+        let mut oscs: ArrayVec<usize, OSC_NR> = ArrayVec::new();
+        for (osc, level) in levels.into_iter().enumerate() {
+            if (level > 0.0 || self.keys.level_modulated(osc)) && self.shared.is_audible(osc) {
+                oscs.push(osc);
+            }
+        }
+
+        // The LFO's moved on once per batch, modulating every key by where it was at the start of
+        // the batch. At a depth of 0.0 it leaves everything as it was.
+        let lfo_settings = self.shared.get_lfo();
+        let lfo_rate = if lfo_settings.sync && self.transport.host_tempo {
+            // The tempo's only read at the start of every block, so a tempo change in the middle
+            // of a block is picked up from the next block on. While the host is playing, the
+            // phase is lined up with the song position at every batch, so the LFO snaps back in
+            // line with the song right after the change.
+            let beats = lfo_settings.division.beats();
+            if self.transport.playing && self.transport.host_position {
+                let offset = bounds.start as f64 * tempo / 60.0 / self.sample_rate as f64;
+                self.lfo.align(self.transport.position + offset, beats);
+            }
+
+            (tempo / 60.0 / beats) as f32
+        } else {
+            lfo_settings.rate
+        };
+        let lfo = lfo_settings.depth
+            * self
+                .lfo
+                .advance(lfo_settings.shape, lfo_rate, bounds.len(), self.sample_rate);
+        let (lfo_pitch, lfo_gain, lfo_cutoff) = match lfo_settings.destination {
+            LfoDestination::Pitch => (lfo * LFO_PITCH_RANGE / 12.0 * self.shared.n_tet(), 1.0, 0.0),
+            // Tremolo only ever turns the keys down, dipping by the depth at the LFO's trough.
+            LfoDestination::Level => (0.0, 1.0 - (lfo_settings.depth - lfo) / 2.0, 0.0),
+            LfoDestination::Cutoff => (0.0, 1.0, lfo),
+        };
+
+        // Transposing and bending can push an oscillator's pitch past the range of the note data
+        // table, in which case it's clamped to the table's bounds. The bend is picked up by every
+        // sounding key, since it's part of the tuning rather than of the keys.
+        let master_tuning = self.shared.get_master_tuning();
+        let pitch = self.shared.modulated_pitch();
+        let octave_steps = self.shared.octave_steps();
+        let fine = self.shared.modulated_fine();
+        let bend = self.pitch_bend * self.shared.get_pitch_bend_range() as f32 / 12.0
+            * self.shared.n_tet();
+        let tuning = Tuning {
+            pitch: std::array::from_fn(|osc| {
+                (pitch[osc].floor() + octave_steps[osc] + master_tuning.transpose) as f32
+                    + (fine[osc] + master_tuning.tune) / 1200.0 * self.shared.n_tet()
+                    + bend
+                    + lfo_pitch
+            }),
+        };
+        // The filter's left out when it's fully open, so it doesn't color the sound at all. It's
+        // kept in while the LFO sweeps it, so it doesn't cut in and out as the LFO swings.
+        let cutoff = (self.shared.get_cutoff() + lfo_cutoff).clamp(0.0, 1.0);
+        let resonance = self.shared.get_resonance();
+        let filter = (cutoff < 1.0
+            || (lfo_settings.depth > 0.0
+                && matches!(lfo_settings.destination, LfoDestination::Cutoff)))
+        .then(|| {
+            FilterCoefficients::new(
+                filter::cutoff_frequency(cutoff),
+                resonance,
+                self.sample_rate,
+            )
+        });
+        let arp_on = self.shared.get_arp_settings().on;
+
+        self.frames[bounds.clone()].fill([0.0; CHANNEL_NR]);
+
+        // The batch gets split up at every arpeggiator step, so notes are triggered on the exact
+        // sample rather than at the start of the batch.
+        let mut start = bounds.start;
+        while start < bounds.end {
+            let end = if arp_on && self.arp.is_active() {
+                status = ProcessStatus::Continue;
+
+                let samples = self.arp.tick(
+                    tempo,
+                    self.sample_rate,
+                    &mut self.keys,
+                    self.shared,
+                    &mut self.rng,
+                );
+
+                (start + samples).min(bounds.end)
+            } else {
+                bounds.end
+            };
+
+            self.keys.for_each(|key| {
+                status = ProcessStatus::Continue;
+                key.process(
+                    &mut self.frames[start..end],
+                    tuning,
+                    &oscs,
+                    &self.note_data,
+                    filter,
+                );
+            });
+
+            self.arp.elapse(end - start);
+            start = end;
+        }
+
+        // Every key gets the same gain, so it's applied to their mix rather than key by key. It
+        // only moves once per batch, so it's smoothed in between.
+        self.lfo_gain.set(lfo_gain);
+        if !self.lfo_gain.is_settled() || lfo_gain < 1.0 {
+            for frame in &mut self.frames[bounds.clone()] {
+                let gain = self.lfo_gain.process();
+                for sample in frame.iter_mut() {
+                    *sample *= gain;
+                }
+            }
+        }
+
+        self.chorus
+            .process(&mut self.frames[bounds.clone()], self.shared.get_chorus());
+        self.delay.process(
+            &mut self.frames[bounds.clone()],
+            self.shared.get_delay(),
+            tempo,
+        );
+
+        // The mix is saturated before the limiter, so the limiter still catches whatever the
+        // clipper lets through. Without any drive it's left out, keeping the mix clean.
+        let drive = self.shared.get_drive();
+        self.drive.set(drive);
+        if !self.drive.is_settled() || drive > 0.0 {
+            for frame in &mut self.frames[bounds.clone()] {
+                let drive = self.drive.process();
+                for sample in frame.iter_mut() {
+                    *sample = math::saturate(*sample, drive);
+                }
+            }
+        }
+
+        self.limiter
+            .process(&mut self.frames[bounds.clone()], self.shared.get_limit());
+
+        // The master gain comes last, and is smoothed so automating it doesn't click.
+        let gain = 10.0f32.powf(self.shared.get_gain() / 20.0);
+        self.gain.set(gain);
+        if !self.gain.is_settled() || gain < 1.0 {
+            for frame in &mut self.frames[bounds.clone()] {
+                let gain = self.gain.process();
+                for sample in frame.iter_mut() {
+                    *sample *= gain;
+                }
+            }
+        }
+
+        status
+    }
+
+    /// Renders the next `frames_count` frames without a host, as if it sent no events, and returns
+    /// them. There can't be more of them than the processor was created to render at a time.
+    #[cfg(test)]
+    pub(crate) fn render(&mut self, frames_count: usize) -> &[Frame] {
+        self.render_batch(0..frames_count, self.transport.tempo);
+        &self.frames[..frames_count]
+    }
+
+    /// Release a note that was let go of, or hold on to it until the sustain pedal is up.
+    fn release(&mut self, note: usize) {
+        if self.sustain_pedal {
+            self.sustained_notes[note] = true;
+            self.keys.let_go(note);
+        } else {
+            self.keys.release(note, self.shared);
+        }
+    }
+
+    /// Plays a note, or hands it to the arpeggiator if it's on.
+    pub(crate) fn note_on(&mut self, note: usize, velocity: u8, arp_on: bool) {
+        if arp_on {
+            self.arp.hold(note, velocity);
+            return;
+        }
+
+        // A key pressed again is held down by hand, so the pedal going up shouldn't release it
+        // anymore.
+        self.sustained_notes[note] = false;
+        self.keys.on(note, velocity, self.shared);
+    }
+
+    /// Lets go of a note, with its note-off's velocity.
+    fn note_off(&mut self, note: usize, velocity: u8, arp_on: bool) {
+        if arp_on {
+            self.arp.unhold(note, &mut self.keys);
+            return;
+        }
+
+        // Notes held by the sustain pedal are released by the pedal going up rather than by their
+        // note-off, so they release over the envelope's own release time.
+        if self.sustain_pedal {
+            self.release(note);
+            return;
+        }
+
+        let amount = self.shared.get_release_vel_amount();
+        self.keys
+            .release_with_velocity(note, velocity, amount, self.shared);
+    }
+
+    /// Applies a tuning or volume note expression to the key it's aimed at. The other expressions
+    /// aren't supported, and are dropped.
+    fn process_note_expression(&mut self, expression: &NoteExpressionEvent) {
+        let note = match expression.key() {
+            Match::Specific(note) if (note as usize) < KEYS_NR => Some(note as usize),
+            _ => None,
+        };
+        let note_id = match expression.note_id() {
+            Match::Specific(note_id) => Some(note_id),
+            Match::All => None,
+        };
+        let value = expression.value() as f32;
+
+        match expression.expression_type() {
+            // The tuning is in semitones, which get converted into steps of the temperament.
+            Some(NoteExpressionType::Tuning) => {
+                let pitch = value / 12.0 * self.shared.n_tet();
+                self.keys
+                    .expression(note, note_id, |key| key.set_expression_pitch(pitch));
+            }
+            Some(NoteExpressionType::Volume) => {
+                self.keys
+                    .expression(note, note_id, |key| key.set_volume(value));
+            }
+            _ => {}
+        }
+    }
+
+    /// Applies the host's modulation of a parameter to the key it's aimed at, found the same way as
+    /// for note expressions. Modulation aimed at every key is left to the shared parameters.
+    fn process_note_param_mod(&mut self, param_mod: &ParamModEvent) {
+        let note = match param_mod.key() {
+            Match::Specific(note) if (note as usize) < KEYS_NR => Some(note as usize),
+            _ => None,
+        };
+        let note_id = match param_mod.note_id() {
+            Match::Specific(note_id) => Some(note_id),
+            Match::All => None,
+        };
+
+        if (note.is_some() || note_id.is_some())
+            && let Some(param_id) = param_mod.param_id()
+        {
+            self.keys.expression(note, note_id, |key| {
+                key.set_param_mod(param_id.into(), param_mod.amount(), self.shared)
+            });
+        }
+    }
+
+    /// Cuts a note off right away, without it releasing.
+    fn choke(&mut self, note: usize, arp_on: bool) {
+        self.sustained_notes[note] = false;
+        if arp_on {
+            self.arp.unhold(note, &mut self.keys);
+        }
+        self.keys.end(note);
+    }
+
     fn process_cc_event(&mut self, midi_event: [u8; 3]) {
         let cc_nr = midi_event[1];
         match cc_nr {
-            MIDI_CC_ALL_SOUNDS_OFF => self.keys.for_each(Key::end),
-            MIDI_CC_ALL_NOTES_OFF => self.keys.for_each(Key::release),
+            MIDI_CC_SUSTAIN => {
+                self.sustain_pedal = midi_event[2] >= 64;
+                if !self.sustain_pedal {
+                    for (note, sustained) in self.sustained_notes.iter_mut().enumerate() {
+                        if std::mem::take(sustained) {
+                            self.keys.release(note, self.shared);
+                        }
+                    }
+                }
+            }
+            MIDI_CC_ALL_SOUNDS_OFF => {
+                self.sustained_notes = [false; KEYS_NR];
+                self.keys.fade_out_all();
+            }
+            MIDI_CC_ALL_NOTES_OFF => {
+                self.sustained_notes = [false; KEYS_NR];
+                self.keys.release_all();
+            }
             _ => {}
         }
     }
 
-    fn process_events(&mut self, events: InputEventsIter) -> Result<(), PluginError> {
+    /// Catch the keys up with a patch the plugin changed itself, and let the host know about the new
+    /// parameter values.
+    fn patch_changed(&mut self, output: &mut OutputEvents) -> Result<(), PluginError> {
+        if !self.shared.get_arp_settings().on && self.arp.is_active() {
+            self.arp.stop(&mut self.keys);
+        }
+
+        let levels = self.shared.modulated_levels();
+        self.keys.for_each(|key| {
+            for (osc, &level) in levels.iter().enumerate() {
+                key.set_level(level, osc);
+            }
+        });
+
+        self.shared.flush_param_changes(output)
+    }
+
+    /// Let the host know how many voices are sounding, if it changed since it was last reported.
+    fn report_voice_count(&mut self, output: &mut OutputEvents) -> Result<(), PluginError> {
+        let voice_count = self.keys.voice_count();
+        let mut reported = self.shared.get_voice_count_mut();
+
+        if *reported != voice_count {
+            *reported = voice_count;
+            Fox3oscShared::write_param_value(output, PARAMETER_VOICES, voice_count as f64)?;
+        }
+
+        Ok(())
+    }
+
+    /// Handle a parameter change the host sent outside of processing.
+    fn flush_event(
+        &mut self,
+        event: &UnknownEvent,
+        output: &mut OutputEvents,
+    ) -> Result<(), PluginError> {
+        self.shared.process_param_event(event);
+
+        if Fox3oscShared::is_triggered(event, PARAMETER_RANDOMIZE) {
+            self.shared.randomize(&mut self.rng);
+            self.patch_changed(output)?;
+        }
+
+        if Fox3oscShared::is_triggered(event, PARAMETER_INIT) {
+            self.shared.reset_params();
+            self.patch_changed(output)?;
+        }
+
+        Ok(())
+    }
+
+    fn process_events(
+        &mut self,
+        events: InputEventsIter,
+        output: &mut OutputEvents,
+    ) -> Result<(), PluginError> {
         for event in events {
             // Handle a parameter event
-            if let Some(param_id) = self.shared.process_param_event(event)? {
+            if let Some(param_id) = self.shared.process_param_event(event) {
+                if let Some(CoreEventSpace::ParamMod(param_mod)) = event.as_core_event() {
+                    self.process_note_param_mod(param_mod);
+                }
+
                 if matches!(param_id, PARAMETER_LEVEL_1..=PARAMETER_LEVEL_3) {
                     let osc = (param_id - PARAMETER_LEVEL_1) as usize;
-                    let level = self.shared.get_levels()?[osc];
+                    let level = self.shared.modulated_levels()[osc];
 
                     self.keys.for_each(move |key| {
                         key.set_level(level, osc);
                     });
                 }
 
+                if param_id == PARAMETER_ARP_ON {
+                    self.arp.stop(&mut self.keys);
+                }
+
+                if Fox3oscShared::is_triggered(event, PARAMETER_RANDOMIZE) {
+                    self.shared.randomize(&mut self.rng);
+                    self.patch_changed(output)?;
+                }
+
+                if Fox3oscShared::is_triggered(event, PARAMETER_INIT) {
+                    self.shared.reset_params();
+                    self.patch_changed(output)?;
+                }
+
                 continue;
             }
 
+            let arp_on = self.shared.get_arp_settings().on;
+
+            // Handle a CLAP note event. Events matching every key only make sense for note-offs and
+            // chokes, which then go to every key. Keys past the keyboard are dropped like the MIDI
+            // ones below.
+            match event.as_core_event() {
+                Some(CoreEventSpace::NoteOn(note_event)) => {
+                    // A CLAP note-on always plays, even with a velocity of 0.0.
+                    let velocity = (note_event.velocity() * 127.0).round().clamp(1.0, 127.0) as u8;
+                    match note_event.key() {
+                        Match::Specific(note) if (note as usize) < KEYS_NR => {
+                            self.note_on(note as usize, velocity, arp_on);
+
+                            // The arpeggiator plays notes of its own, which note expressions
+                            // aren't aimed at.
+                            if !arp_on && let Match::Specific(note_id) = note_event.note_id() {
+                                self.keys.set_note_id(note as usize, note_id);
+                            }
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+                Some(CoreEventSpace::NoteOff(note_event)) => {
+                    let velocity = (note_event.velocity() * 127.0).round().clamp(0.0, 127.0) as u8;
+                    match note_event.key() {
+                        Match::Specific(note) if (note as usize) < KEYS_NR => {
+                            self.note_off(note as usize, velocity, arp_on);
+                        }
+                        Match::Specific(_) => {}
+                        Match::All => {
+                            for note in 0..KEYS_NR {
+                                self.note_off(note, velocity, arp_on);
+                            }
+                        }
+                    }
+                    continue;
+                }
+                Some(CoreEventSpace::NoteExpression(expression)) => {
+                    self.process_note_expression(expression);
+                    continue;
+                }
+                Some(CoreEventSpace::NoteChoke(note_event)) => {
+                    match note_event.key() {
+                        Match::Specific(note) if (note as usize) < KEYS_NR => {
+                            self.choke(note as usize, arp_on);
+                        }
+                        Match::Specific(_) => {}
+                        Match::All => {
+                            for note in 0..KEYS_NR {
+                                self.choke(note, arp_on);
+                            }
+                        }
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+
             // Handle a MIDI event
             let Some(CoreEventSpace::Midi(midi_event)) = event.as_core_event() else {
                 continue;
@@ -59,18 +577,38 @@ impl Fox3oscAudioProcessor<'_> {
 
             let midi_event = midi_event.data();
             let midi_msg = midi_event[0] & 0xF0;
-            match midi_msg {
-                MIDI_ON => {
-                    let note = midi_event[1] as usize % KEYS_NR;
-                    let velocity = midi_event[2];
 
-                    self.keys.on(note, velocity, self.shared, &mut self.rng)?;
+            // Note numbers past the keyboard can only come from corrupt events, so they're dropped
+            // rather than wrapped into the wrong octave.
+            if matches!(midi_msg, MIDI_ON | MIDI_OFF | MIDI_POLY_AFTERTOUCH)
+                && midi_event[1] as usize >= KEYS_NR
+            {
+                continue;
+            }
+
+            match midi_msg {
+                MIDI_ON if midi_event[2] == 0 => {
+                    self.note_off(midi_event[1] as usize, 0, arp_on);
+                }
+                MIDI_ON => self.note_on(midi_event[1] as usize, midi_event[2], arp_on),
+                MIDI_OFF => self.note_off(midi_event[1] as usize, midi_event[2], arp_on),
+                MIDI_POLY_AFTERTOUCH => {
+                    let note = midi_event[1] as usize;
+                    let amount = self.shared.get_aftertouch_amount();
+                    self.keys.pressure(note, midi_event[2], amount);
                 }
-                MIDI_OFF => {
-                    let note = midi_event[1] as usize % KEYS_NR;
-                    self.keys.release(note);
+                MIDI_CHANNEL_AFTERTOUCH => {
+                    let amount = self.shared.get_aftertouch_amount();
+                    self.keys.channel_pressure(midi_event[1], amount);
                 }
                 MIDI_CC => self.process_cc_event(midi_event),
+                MIDI_PITCH_BEND => {
+                    // The wheel's position is split into 7 bit halves, the least significant one
+                    // coming first. Centered comes out as exactly 0.0.
+                    let position = ((midi_event[2] as u16) << 7) | midi_event[1] as u16;
+                    self.pitch_bend =
+                        (position as f32 - PITCH_BEND_CENTER as f32) / PITCH_BEND_CENTER as f32;
+                }
                 _ => {}
             }
         }
@@ -79,38 +617,54 @@ impl Fox3oscAudioProcessor<'_> {
     }
 }
 
+/// Works out the note data table for every note of `temperament`, with A4 tuned to
+/// `concert_pitch`.
+fn note_data(
+    shared: &Fox3oscShared,
+    sample_rate: f32,
+    concert_pitch: f32,
+    temperament: Temperament,
+) -> ArrayVec<NoteData, MAX_NOTES_NR> {
+    ArrayVec::from_iter((0..shared.notes_nr).map(|note| {
+        NoteData::new(
+            sample_rate,
+            (note as f32) - shared.pitch_amount as f32,
+            temperament.divisions(),
+            concert_pitch,
+        )
+    }))
+}
+
 impl<'a> PluginAudioProcessor<'a, Fox3oscShared, Fox3oscMainThread<'a>>
     for Fox3oscAudioProcessor<'a>
 {
     fn activate(
         _host: HostAudioProcessorHandle<'a>,
-        _main_thread: &mut Fox3oscMainThread,
+        main_thread: &mut Fox3oscMainThread,
         shared: &'a Fox3oscShared,
         audio_config: PluginAudioConfiguration,
     ) -> Result<Self, PluginError> {
-        let sample_rate = audio_config.sample_rate as f32;
-        let note_data = ArrayVec::from_iter((0..shared.notes_nr).map(|note| {
-            NoteData::new(
-                sample_rate,
-                (note as f32) - shared.pitch_amount as f32,
-                shared.n_tet,
-            )
-        }));
-
-        Ok(Self {
+        Ok(Self::new(
             shared,
-            note_data,
-            rng: SmallRng::seed_from_u64(0xB00B5),
-            keys: Keys::new(sample_rate),
-        })
+            audio_config.sample_rate as f32,
+            audio_config.max_frames_count as usize,
+            main_thread.output_channels,
+        ))
     }
 
     fn process(
         &mut self,
-        _process: Process,
+        process: Process,
         mut audio: Audio,
         events: Events,
     ) -> Result<ProcessStatus, PluginError> {
+        self.transport.update(
+            process.transport,
+            audio.frames_count() as usize,
+            self.sample_rate,
+        );
+        let tempo = self.transport.tempo;
+
         let mut output_port = audio
             .output_port(0)
             .ok_or(PluginError::Message("No output port"))?;
@@ -120,41 +674,56 @@ impl<'a> PluginAudioProcessor<'a, Fox3oscShared, Fox3oscMainThread<'a>>
             .into_f32()
             .ok_or(PluginError::Message("Output is not f32"))?;
 
-        let output = output_channels
-            .channel_mut(0)
-            .ok_or(PluginError::Message("Output channel 0 not found"))?;
-
         let mut status = ProcessStatus::Sleep;
         for batch in events.input.batch() {
-            self.process_events(batch.events())?;
+            self.process_events(batch.events(), events.output)?;
 
-            let levels = self.shared.get_levels()?;
-            let oscs: ArrayVec<usize, OSC_NR> = levels
-                .into_iter()
-                .enumerate()
-                .filter_map(|(osc, level)| if level > 0.0 { Some(osc) } else { None })
-                .collect();
+            let bounds = batch.sample_bounds();
+            if let ProcessStatus::Continue = self.render_batch(bounds.clone(), tempo) {
+                status = ProcessStatus::Continue;
+            }
 
-            let pitch = self.shared.get_pitch()?;
+            for channel_index in 0..self.output_channels {
+                let channel = output_channels
+                    .channel_mut(channel_index as u32)
+                    .ok_or(PluginError::Message("Output channel not found"))?;
 
-            output[batch.sample_bounds()].fill(0.0);
-            self.keys.for_each(|key| {
-                status = ProcessStatus::Continue;
-                key.process(
-                    &mut output[batch.sample_bounds()],
-                    pitch.map(|pitch| pitch as usize),
-                    &mut self.rng,
-                    &oscs,
-                    &self.note_data,
-                );
-            });
+                for (sample, frame) in channel[bounds.clone()]
+                    .iter_mut()
+                    .zip(&self.frames[bounds.clone()])
+                {
+                    *sample = if self.output_channels == CHANNEL_NR {
+                        frame[channel_index]
+                    } else {
+                        frame.iter().sum::<f32>() / CHANNEL_NR as f32
+                    };
+                }
+            }
         }
 
+        // Parameters changed from the main thread, like through the context menu, reach the host
+        // from here.
+        self.shared.flush_param_changes(events.output)?;
+        self.report_voice_count(events.output)?;
+
         Ok(status)
     }
 
     fn reset(&mut self) {
-        self.keys.for_each(Key::end);
+        self.arp = Arpeggiator::new();
+        self.chorus.reset();
+        self.delay.reset();
+        self.limiter.reset();
+        self.lfo.reset();
+        self.lfo_gain.reset(1.0);
+        self.transport = Transport::new();
+        self.pitch_bend = 0.0;
+        self.sustain_pedal = false;
+        self.sustained_notes = [false; KEYS_NR];
+        // The audio isn't running while the plugin's reset, so there's nothing for a fade to smooth
+        // over, and keys left fading would otherwise come back when it resumes.
+        self.keys.end_all();
+        self.keys.forget_last_note();
     }
 }
 
@@ -162,10 +731,199 @@ impl PluginAudioProcessorParams for Fox3oscAudioProcessor<'_> {
     fn flush(
         &mut self,
         input_parameter_changes: &InputEvents,
-        _output_parameter_changes: &mut OutputEvents,
+        output_parameter_changes: &mut OutputEvents,
     ) {
+        // Flushing has no way to report an error to the host, and panicking here would unwind across
+        // the FFI boundary, so a change that fails is skipped and the rest still go through.
         for event in input_parameter_changes {
-            self.shared.process_param_event(event).unwrap();
+            let _ = self.flush_event(event, output_parameter_changes);
         }
+
+        let _ = self.shared.flush_param_changes(output_parameter_changes);
+        let _ = self.report_voice_count(output_parameter_changes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clack_plugin::{
+        events::{Event, Pckn},
+        prelude::ClapId,
+        utils::Cookie,
+    };
+
+    use super::*;
+    use crate::{
+        consts::{REFERENCE_FREQUENCY, REFERENCE_NOTE},
+        presets::INIT,
+        state::PatchState,
+    };
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    /// Sends the host's modulation of a parameter, aimed at the notes `pckn` matches, the same way
+    /// `process_events` does.
+    fn param_mod(processor: &mut Fox3oscAudioProcessor, param_id: u32, pckn: Pckn, amount: f64) {
+        let event = ParamModEvent::new(0, ClapId::new(param_id), pckn, amount, Cookie::empty());
+        processor.shared.process_param_event(event.as_unknown());
+        processor.process_note_param_mod(&event);
+    }
+
+    /// The highest of a channel's samples, by their magnitude.
+    fn peak(frames: &[Frame]) -> f32 {
+        frames
+            .iter()
+            .fold(0.0f32, |peak, frame| peak.max(frame[0].abs()))
+    }
+
+    /// The number of times a channel goes from below 0.0 to 0.0 or above.
+    fn rising_crossings(frames: &[Frame]) -> usize {
+        frames
+            .windows(2)
+            .filter(|pair| pair[0][0] < 0.0 && pair[1][0] >= 0.0)
+            .count()
+    }
+
+    #[test]
+    fn sine_plays_at_the_note_frequency() {
+        let shared = Fox3oscShared::new(Temperament::Tet12);
+        let mut processor = Fox3oscAudioProcessor::new(&shared, SAMPLE_RATE, 48000, CHANNEL_NR);
+        processor.note_on(69, 127, false);
+
+        // The attack's left out, and the crossings after it counted over 0.9 seconds.
+        let crossings = self::rising_crossings(&processor.render(48000)[4800..]);
+        assert!(crossings.abs_diff(396) <= 1, "{crossings} crossings");
+    }
+
+    #[test]
+    fn reactivating_at_another_sample_rate_keeps_a4_in_tune() {
+        let shared = Fox3oscShared::new(Temperament::Tet12);
+        let a4 = REFERENCE_NOTE as usize + shared.pitch_amount as usize;
+
+        for sample_rate in [44100.0, 96000.0] {
+            let frames_count = sample_rate as usize;
+            let mut processor =
+                Fox3oscAudioProcessor::new(&shared, sample_rate, frames_count, CHANNEL_NR);
+
+            let increment = processor.note_data[a4].increment;
+            let expected = REFERENCE_FREQUENCY / sample_rate;
+            assert!(
+                (increment - expected).abs() < 1e-9,
+                "{increment} at {sample_rate} Hz"
+            );
+
+            processor.note_on(69, 127, false);
+            let frames = &processor.render(frames_count)[frames_count / 10..];
+            let crossings = self::rising_crossings(frames);
+            assert!(
+                crossings.abs_diff(396) <= 1,
+                "{crossings} crossings at {sample_rate} Hz"
+            );
+        }
+    }
+
+    #[test]
+    fn envelope_settles_at_the_sustain_level() {
+        // The peaks of a note early on into its sustain and later on, with the envelopes sustaining
+        // at `sustain`. A full sustain gives the level every other one is measured against.
+        let sustain_peaks = |sustain| {
+            let shared = Fox3oscShared::new(Temperament::Tet12);
+            for envelope in shared.get_envelope_mut().iter_mut() {
+                envelope.sustain = sustain;
+            }
+
+            let mut processor = Fox3oscAudioProcessor::new(&shared, SAMPLE_RATE, 48000, CHANNEL_NR);
+            processor.note_on(69, 127, false);
+            let frames = processor.render(48000);
+            (peak(&frames[14400..19200]), peak(&frames[38400..43200]))
+        };
+
+        let (full, _) = sustain_peaks(1.0);
+        let (early, late) = sustain_peaks(0.5);
+        assert!((early - late).abs() < 1e-4, "{early} then {late}");
+        assert!((early / full - 0.5).abs() < 1e-4, "{early} of {full}");
+    }
+
+    #[test]
+    fn per_note_modulation_only_moves_its_note() {
+        let shared = Fox3oscShared::new(Temperament::Tet12);
+        let mut unmodulated = Fox3oscAudioProcessor::new(&shared, SAMPLE_RATE, 48000, CHANNEL_NR);
+        unmodulated.note_on(69, 127, false);
+        let unmodulated = unmodulated.render(28800).to_vec();
+
+        let shared = Fox3oscShared::new(Temperament::Tet12);
+        let mut processor = Fox3oscAudioProcessor::new(&shared, SAMPLE_RATE, 48000, CHANNEL_NR);
+        processor.note_on(69, 127, false);
+        processor.keys.set_note_id(69, 7);
+        processor.render(4800);
+
+        // Modulation of other notes leaves the one playing alone.
+        let other_key = Pckn::new(0u16, 0u16, 70u16, Match::All);
+        let other_note_id = Pckn::new(0u16, 0u16, Match::All, 8u32);
+        param_mod(&mut processor, PARAMETER_LEVEL_1, other_key, -1.0);
+        param_mod(&mut processor, PARAMETER_LEVEL_1, other_note_id, -1.0);
+        assert!(processor.render(4800) == &unmodulated[4800..9600]);
+
+        let note_id = Pckn::new(0u16, 0u16, Match::All, 7u32);
+        param_mod(&mut processor, PARAMETER_LEVEL_1, note_id, -1.0);
+        processor.render(4800);
+        assert_eq!(peak(processor.render(4800)), 0.0);
+        assert_eq!(shared.modulated_levels()[0], 1.0);
+
+        // Modulating it by 0.0 brings it back to its level.
+        let key = Pckn::new(0u16, 0u16, 69u16, Match::All);
+        param_mod(&mut processor, PARAMETER_LEVEL_1, key, 0.0);
+        processor.render(4800);
+        let back = peak(processor.render(4800));
+        let full = peak(&unmodulated[24000..]);
+        assert!((back / full - 1.0).abs() < 1e-4, "{back} of {full}");
+    }
+
+    #[test]
+    fn changing_the_temperament_retunes_the_notes() {
+        let shared = Fox3oscShared::new(Temperament::Tet12);
+        let mut processor = Fox3oscAudioProcessor::new(&shared, SAMPLE_RATE, 48000, CHANNEL_NR);
+        let a4 = REFERENCE_NOTE as usize + shared.pitch_amount as usize;
+        // How far above A4 `steps` steps of the temperament are, by frequency.
+        let ratio = |processor: &Fox3oscAudioProcessor, steps: usize| {
+            processor.note_data[a4 + steps].increment / processor.note_data[a4].increment
+        };
+
+        processor.render(1);
+        assert!((ratio(&processor, 12) - 2.0).abs() < 1e-5);
+
+        shared.apply_patch_state(&PatchState {
+            temperament: Temperament::Tet24,
+            ..INIT
+        });
+        processor.render(1);
+        assert!((ratio(&processor, 24) - 2.0).abs() < 1e-5);
+        assert!((ratio(&processor, 12) - 2f32.sqrt()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn coarse_octaves_stay_2_to_1_after_switching_to_53_tet() {
+        let shared = Fox3oscShared::new(Temperament::Tet12);
+        let mut processor = Fox3oscAudioProcessor::new(&shared, SAMPLE_RATE, 48000, CHANNEL_NR);
+        let mut crossings = |pitch: f64| {
+            shared.apply_patch_state(&PatchState {
+                temperament: Temperament::Tet53,
+                pitch: [pitch; OSC_NR],
+                ..INIT
+            });
+            processor.keys.end_all();
+            processor.note_on(69, 127, false);
+            self::rising_crossings(&processor.render(48000)[4800..])
+        };
+
+        // The coarse pitch reaches 2 octaves of 53-TET either way in a 12-TET plugin too.
+        assert!(shared.pitch_amount >= 2.0 * 53.0);
+        let unshifted = crossings(shared.pitch_amount);
+        let octave_up = crossings(shared.pitch_amount + 53.0);
+        assert!(unshifted.abs_diff(396) <= 1, "{unshifted} crossings");
+        assert!(
+            octave_up.abs_diff(2 * unshifted) <= 1,
+            "{octave_up} crossings an octave up"
+        );
     }
 }
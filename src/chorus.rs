@@ -0,0 +1,67 @@
+use std::f32::consts::TAU;
+
+use crate::{consts::CHANNEL_NR, key::Frame, shared::ChorusSettings};
+
+/// Delay of the delay lines while the LFO is centered, in seconds.
+const BASE_DELAY: f32 = 0.007;
+/// How far the LFO sweeps the delay either way at full depth, in seconds.
+const MAX_SWEEP: f32 = 0.005;
+
+/// A stereo chorus after the keys get mixed. Every channel runs through a delay line swept by an
+/// LFO, with the right channel's LFO a quarter of a cycle ahead of the left one's.
+pub struct Chorus {
+    lines: [Vec<f32>; CHANNEL_NR],
+    /// Position in the delay lines the next sample gets written to.
+    write: usize,
+    lfo_phase: f32,
+    sample_rate: f32,
+}
+
+impl Chorus {
+    pub fn new(sample_rate: f32) -> Self {
+        // Some room on top of the longest delay for interpolating between samples.
+        let len = ((BASE_DELAY + MAX_SWEEP) * sample_rate).ceil() as usize + 2;
+
+        Self {
+            lines: std::array::from_fn(|_| vec![0.0; len]),
+            write: 0,
+            lfo_phase: 0.0,
+            sample_rate,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for line in &mut self.lines {
+            line.fill(0.0);
+        }
+
+        self.write = 0;
+        self.lfo_phase = 0.0;
+    }
+
+    /// Runs frames through the chorus in place. Frames are left untouched at a mix of 0.
+    pub fn process(&mut self, frames: &mut [Frame], settings: ChorusSettings) {
+        let len = self.lines[0].len();
+
+        for frame in frames {
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let line = &mut self.lines[channel];
+                line[self.write] = *sample;
+
+                if settings.mix > 0.0 {
+                    let lfo = ((self.lfo_phase + channel as f32 * 0.25) * TAU).sin();
+                    let delay = (BASE_DELAY + lfo * settings.depth * MAX_SWEEP) * self.sample_rate;
+                    let position = (self.write as f32 - delay).rem_euclid(len as f32);
+                    let idx = position as usize % len;
+                    let next = (idx + 1) % len;
+                    let wet = line[idx] + (line[next] - line[idx]) * position.fract();
+
+                    *sample += (wet - *sample) * settings.mix;
+                }
+            }
+
+            self.write = (self.write + 1) % len;
+            self.lfo_phase = (self.lfo_phase + settings.rate / self.sample_rate) % 1.0;
+        }
+    }
+}
@@ -7,7 +7,9 @@ pub const PLUGIN_COUNT: usize = 1
     + if cfg!(feature = "19tet") { 1 } else { 0 }
     + if cfg!(feature = "22tet") { 1 } else { 0 }
     + if cfg!(feature = "23tet") { 1 } else { 0 }
-    + if cfg!(feature = "24tet") { 1 } else { 0 };
+    + if cfg!(feature = "24tet") { 1 } else { 0 }
+    + if cfg!(feature = "31tet") { 1 } else { 0 }
+    + if cfg!(feature = "53tet") { 1 } else { 0 };
 
 /// This parameter represents an enumerated value. If you set this flag, then you must set CLAP_PARAM_IS_STEPPED
 /// too. All values from min to max must not have a blank value_to_text().
@@ -22,38 +24,141 @@ pub const CLAP_PARAM_IS_ENUM: ParamInfoFlags = ParamInfoFlags::from_bits_retain(
 /// Number of MIDI notes and max polyphony of fox3osc
 pub const KEYS_NR: usize = 128;
 
+/// The MIDI note (A4) whose frequency the temperaments are tuned around.
+pub const REFERENCE_NOTE: f32 = 69.0;
+/// Frequency in Hz of [`REFERENCE_NOTE`] (concert pitch).
+pub const REFERENCE_FREQUENCY: f32 = 440.0;
+/// Range of the concert pitch parameter, in Hz.
+pub const CONCERT_PITCH_MIN: f32 = 400.0;
+pub const CONCERT_PITCH_MAX: f32 = 480.0;
+
+/// The MIDI note (C4) around which keyboard tracking is centered. Keys above it are scaled one way,
+/// keys below it the other way.
+pub const KEY_TRACK_CENTER: usize = 60;
+/// The key the keyboard is split at by default, middle C.
+pub const SPLIT_DEFAULT: usize = 60;
+
+/// The amount of steps the pitch parameters can shift an oscillator by either up or down. This is
+/// 2 octaves of the finest temperament (53-TET), so it reaches at least 2 octaves in every
+/// temperament, whichever one the plugin switches to.
+pub const PITCH_AMOUNT: f64 = 53.0 * 2.0;
+
 /// Maximum number of notes that can be heard. This is different from how many keys there are. This
-/// value takes into account pitch shift and temperament. The value is taken from the maximum n-TET
-/// scale supported (24-TET) and adding 2 octaves below and above the 128 MIDI keys.
-pub const MAX_NOTES_NR: usize = (24 * 2) * 2 + KEYS_NR;
+/// value takes into account pitch shift and temperament, adding [`PITCH_AMOUNT`] steps below and
+/// above the 128 MIDI keys.
+pub const MAX_NOTES_NR: usize = PITCH_AMOUNT as usize * 2 + KEYS_NR;
+
+/// Length in seconds of the fade out applied when a key is cut off rather than released, so it
+/// doesn't click.
+pub const FADE_OUT_TIME: f32 = 0.005;
+/// Seed every key's random number generator is derived from, along with the key's note.
+pub const KEY_SEED: u64 = 0xF0C5_3A11;
+/// Shortest release in seconds a hard note-off can cut the envelope's release down to, so it
+/// doesn't click.
+pub const RELEASE_MIN_TIME: f32 = 0.003;
+
+/// Maximum number of harmonics an additive waveform can be built from.
+pub const PARTIALS_MAX: u32 = 32;
+
+/// Lowest cutoff of the filter in Hz.
+pub const CUTOFF_MIN: f32 = 20.0;
+/// Highest cutoff of the filter in Hz. The filter's left out altogether at this cutoff.
+pub const CUTOFF_MAX: f32 = 20000.0;
+
+/// Lowest master output gain in dB.
+pub const GAIN_MIN: f32 = -60.0;
+
+/// Slowest rate of the LFO in Hz.
+pub const LFO_RATE_MIN: f32 = 0.05;
+/// Fastest rate of the LFO in Hz.
+pub const LFO_RATE_MAX: f32 = 20.0;
+/// How far the LFO bends the pitch either way at full depth, in semitones.
+pub const LFO_PITCH_RANGE: f32 = 12.0;
+
+/// Maximum range in semitones the pitch wheel can bend in either direction.
+pub const PITCH_BEND_RANGE_MAX: u32 = 24;
+/// Furthest the octave parameters shift an oscillator either way, in octaves.
+pub const OCTAVE_MAX: i32 = 3;
+
+/// Number of samples in the single-cycle wavetable additive waveforms are read from.
+pub const ADDITIVE_TABLE_SIZE: usize = 512;
+
+/// Widest polyblep correction, as a fraction of a cycle. Past a quarter of a cycle the corrections
+/// around a square wave's rising and falling edges would overlap each other, so notes that high
+/// play polyblep waveforms as a sine instead.
+pub const TRANSITION_SIZE_MAX: f32 = 0.25;
+
+/// Frequency ratios to oscillator 1 which oscillator 3 snaps to when its ratio is locked.
+pub const MOD_RATIOS: [f32; 9] = [0.5, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0];
+
+/// Most detuned copies an oscillator plays in unison.
+pub const UNISON_MAX: usize = 7;
+/// Furthest the outermost unison copies are detuned from the oscillator's pitch in cents.
+pub const UNISON_DETUNE_MAX: f32 = 100.0;
+
+/// Longest time between the delay's repeats in seconds.
+pub const MAX_DELAY_TIME: f32 = 2.0;
+
+/// Maximum number of octaves the arpeggiator can span.
+pub const ARP_OCTAVES_MAX: u32 = 4;
 
 /// The oscillator that modulates oscillator 1.
 pub const OSC_MOD: usize = 2;
 /// Number of oscillators *(The 3 in fox3osc)*
 pub const OSC_NR: usize = 3;
+/// Number of output channels, left and right.
+pub const CHANNEL_NR: usize = 2;
 
-/// The phase for the dry signal during PM synthesis.
-pub const PHASE_DRY: usize = PHASE_NR - 1;
-/// Number of phases we keep track of, which is the number of oscillators + 1.
-pub const PHASE_NR: usize = OSC_NR + 1;
+/// Id of the audio ports configuration with a mono output.
+pub const AUDIO_PORTS_CONFIG_MONO: u32 = 0;
+/// Id of the audio ports configuration with a stereo output.
+pub const AUDIO_PORTS_CONFIG_STEREO: u32 = 1;
+
+/// Action id of the context menu entry which resets the patch.
+pub const CONTEXT_MENU_INIT: u32 = 0;
+/// Action id of the context menu entry which randomizes the patch.
+pub const CONTEXT_MENU_RANDOMIZE: u32 = 1;
+/// Action id of the first context menu entry which copies one oscillator onto another. There's an
+/// entry for every pair of oscillators, with the id `CONTEXT_MENU_COPY_OSC + from * OSC_NR + to`.
+pub const CONTEXT_MENU_COPY_OSC: u32 = 2;
+/// Action id of the first context menu entry which loads a factory preset. There's an entry for
+/// every preset, with the id `CONTEXT_MENU_LOAD_PRESET` plus the preset's index.
+pub const CONTEXT_MENU_LOAD_PRESET: u32 = CONTEXT_MENU_COPY_OSC + (OSC_NR * OSC_NR) as u32;
+
+/// Offset of an oscillator's phase for its dry signal during PM synthesis.
+pub const PHASE_DRY: usize = OSC_NR;
+/// Phase of the sub-oscillator, which plays an octave below oscillator 1.
+pub const PHASE_SUB: usize = OSC_NR * 2;
+/// Number of phases we keep track of, which is a phase and a dry phase per oscillator, and the
+/// sub-oscillator's phase.
+pub const PHASE_NR: usize = OSC_NR * 2 + 1;
 
 pub const MIDI_ON: u8 = 0x90;
 pub const MIDI_OFF: u8 = 0x80;
 pub const MIDI_CC: u8 = 0xB0;
+pub const MIDI_POLY_AFTERTOUCH: u8 = 0xA0;
+pub const MIDI_CHANNEL_AFTERTOUCH: u8 = 0xD0;
+pub const MIDI_PITCH_BEND: u8 = 0xE0;
 
+/// Value of the 14-bit pitch wheel when it's centered.
+pub const PITCH_BEND_CENTER: u16 = 0x2000;
+
+pub const MIDI_CC_SUSTAIN: u8 = 0x40;
 pub const MIDI_CC_ALL_SOUNDS_OFF: u8 = 0x78;
 pub const MIDI_CC_ALL_NOTES_OFF: u8 = 0x7B;
 
-pub const PARAMETER_ATTACK: u32 = 0;
-pub const PARAMETER_DECAY: u32 = 1;
-pub const PARAMETER_SUSTAIN: u32 = 2;
-pub const PARAMETER_RELEASE: u32 = 3;
+pub const PARAMETER_ATTACK_1: u32 = 0;
+pub const PARAMETER_DECAY_1: u32 = 1;
+pub const PARAMETER_SUSTAIN_1: u32 = 2;
+pub const PARAMETER_RELEASE_1: u32 = 3;
 pub const PARAMETER_WAVEFORM_1: u32 = 4;
 pub const PARAMETER_WAVEFORM_2: u32 = 5;
 pub const PARAMETER_WAVEFORM_3: u32 = 6;
 pub const PARAMETER_LEVEL_1: u32 = 7;
 pub const PARAMETER_LEVEL_2: u32 = 8;
 pub const PARAMETER_LEVEL_3: u32 = 9;
+// The HQ switches are superseded by PARAMETER_QUALITY. They're kept hidden so their ids don't get
+// reused.
 pub const PARAMETER_HQ_1: u32 = 10;
 pub const PARAMETER_HQ_2: u32 = 11;
 pub const PARAMETER_HQ_3: u32 = 12;
@@ -61,4 +166,105 @@ pub const PARAMETER_MODULATION: u32 = 13;
 pub const PARAMETER_PITCH_1: u32 = 14;
 pub const PARAMETER_PITCH_2: u32 = 15;
 pub const PARAMETER_PITCH_3: u32 = 16;
-pub const PARAMETER_NR: u32 = 17;
+pub const PARAMETER_VEL_SENS: u32 = 17;
+pub const PARAMETER_VEL_CURVE: u32 = 18;
+pub const PARAMETER_ENV_CURVE: u32 = 19;
+pub const PARAMETER_KEY_TRACK: u32 = 20;
+pub const PARAMETER_MOD_INDEX: u32 = 21;
+pub const PARAMETER_PITCH_ENV_AMOUNT_1: u32 = 22;
+pub const PARAMETER_PITCH_ENV_AMOUNT_2: u32 = 23;
+pub const PARAMETER_PITCH_ENV_AMOUNT_3: u32 = 24;
+pub const PARAMETER_PITCH_ENV_TIME: u32 = 25;
+pub const PARAMETER_ARP_ON: u32 = 26;
+pub const PARAMETER_ARP_MODE: u32 = 27;
+pub const PARAMETER_ARP_RATE: u32 = 28;
+pub const PARAMETER_ARP_OCTAVES: u32 = 29;
+pub const PARAMETER_FINE_1: u32 = 30;
+pub const PARAMETER_FINE_2: u32 = 31;
+pub const PARAMETER_FINE_3: u32 = 32;
+pub const PARAMETER_TRANSPOSE: u32 = 33;
+pub const PARAMETER_MASTER_TUNE: u32 = 34;
+pub const PARAMETER_NOISE_COLOR: u32 = 35;
+pub const PARAMETER_RANDOMIZE: u32 = 36;
+pub const PARAMETER_INIT: u32 = 37;
+pub const PARAMETER_MUTE_1: u32 = 38;
+pub const PARAMETER_MUTE_2: u32 = 39;
+pub const PARAMETER_MUTE_3: u32 = 40;
+pub const PARAMETER_SOLO_1: u32 = 41;
+pub const PARAMETER_SOLO_2: u32 = 42;
+pub const PARAMETER_SOLO_3: u32 = 43;
+pub const PARAMETER_VOICES: u32 = 44;
+pub const PARAMETER_GLIDE_MODE: u32 = 45;
+pub const PARAMETER_GLIDE_TIME: u32 = 46;
+pub const PARAMETER_QUALITY: u32 = 47;
+pub const PARAMETER_PARTIALS: u32 = 48;
+pub const PARAMETER_FOLD_1: u32 = 49;
+pub const PARAMETER_FOLD_2: u32 = 50;
+pub const PARAMETER_FOLD_3: u32 = 51;
+pub const PARAMETER_FM_FEEDBACK: u32 = 52;
+pub const PARAMETER_ALGORITHM: u32 = 53;
+pub const PARAMETER_RATIO_LOCK: u32 = 54;
+pub const PARAMETER_STEREO_SPREAD: u32 = 55;
+pub const PARAMETER_CHORUS_RATE: u32 = 56;
+pub const PARAMETER_CHORUS_DEPTH: u32 = 57;
+pub const PARAMETER_CHORUS_MIX: u32 = 58;
+pub const PARAMETER_DELAY_TIME: u32 = 59;
+pub const PARAMETER_DELAY_FEEDBACK: u32 = 60;
+pub const PARAMETER_DELAY_MIX: u32 = 61;
+pub const PARAMETER_DELAY_SYNC: u32 = 62;
+pub const PARAMETER_LIMIT: u32 = 63;
+pub const PARAMETER_VEL_TO_MOD: u32 = 64;
+pub const PARAMETER_EVIL_DEPTH: u32 = 65;
+pub const PARAMETER_VEL_1: u32 = 66;
+pub const PARAMETER_VEL_2: u32 = 67;
+pub const PARAMETER_VEL_3: u32 = 68;
+pub const PARAMETER_SPLIT: u32 = 69;
+pub const PARAMETER_ZONE_1: u32 = 70;
+pub const PARAMETER_ZONE_2: u32 = 71;
+pub const PARAMETER_ZONE_3: u32 = 72;
+pub const PARAMETER_BLEP_WIDTH: u32 = 73;
+pub const PARAMETER_PAN_1: u32 = 74;
+pub const PARAMETER_PAN_2: u32 = 75;
+pub const PARAMETER_PAN_3: u32 = 76;
+pub const PARAMETER_PITCH_BEND_RANGE: u32 = 77;
+pub const PARAMETER_PWM: u32 = 78;
+pub const PARAMETER_CUTOFF: u32 = 79;
+pub const PARAMETER_RESONANCE: u32 = 80;
+pub const PARAMETER_LFO_SHAPE: u32 = 81;
+pub const PARAMETER_LFO_RATE: u32 = 82;
+pub const PARAMETER_LFO_DEPTH: u32 = 83;
+pub const PARAMETER_LFO_DEST: u32 = 84;
+pub const PARAMETER_LFO_SYNC: u32 = 85;
+pub const PARAMETER_LFO_DIVISION: u32 = 86;
+pub const PARAMETER_GAIN: u32 = 87;
+pub const PARAMETER_ATTACK_2: u32 = 88;
+pub const PARAMETER_DECAY_2: u32 = 89;
+pub const PARAMETER_SUSTAIN_2: u32 = 90;
+pub const PARAMETER_RELEASE_2: u32 = 91;
+pub const PARAMETER_ATTACK_3: u32 = 92;
+pub const PARAMETER_DECAY_3: u32 = 93;
+pub const PARAMETER_SUSTAIN_3: u32 = 94;
+pub const PARAMETER_RELEASE_3: u32 = 95;
+pub const PARAMETER_RELEASE_VEL_AMOUNT: u32 = 96;
+pub const PARAMETER_FM_RATIO: u32 = 97;
+pub const PARAMETER_FM_DEPTH: u32 = 98;
+pub const PARAMETER_SUB_LEVEL: u32 = 99;
+pub const PARAMETER_UNISON_COUNT: u32 = 100;
+pub const PARAMETER_UNISON_DETUNE: u32 = 101;
+pub const PARAMETER_PHASE_MODE: u32 = 102;
+pub const PARAMETER_MAX_VOICES: u32 = 103;
+pub const PARAMETER_POLY_MODE: u32 = 104;
+pub const PARAMETER_AFTERTOUCH_AMOUNT: u32 = 105;
+pub const PARAMETER_TUNING_HZ: u32 = 106;
+pub const PARAMETER_OCTAVE_1: u32 = 107;
+pub const PARAMETER_OCTAVE_2: u32 = 108;
+pub const PARAMETER_OCTAVE_3: u32 = 109;
+pub const PARAMETER_DRIVE: u32 = 110;
+pub const PARAMETER_OVERSAMPLE: u32 = 111;
+pub const PARAMETER_TEMPERAMENT: u32 = 112;
+pub const PARAMETER_NR: u32 = 113;
+
+/// The attack parameter of each oscillator's envelope. Its decay, sustain and release parameters
+/// follow right after it.
+pub const PARAMETER_ENVELOPES: [u32; OSC_NR] =
+    [PARAMETER_ATTACK_1, PARAMETER_ATTACK_2, PARAMETER_ATTACK_3];
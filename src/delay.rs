@@ -0,0 +1,73 @@
+use crate::{
+    consts::{CHANNEL_NR, MAX_DELAY_TIME},
+    key::Frame,
+    math,
+    shared::DelaySettings,
+};
+
+/// Highest feedback the delay runs with. Any higher and the repeats would never die out.
+const MAX_FEEDBACK: f32 = 0.95;
+/// Coefficient of the one-pole low-pass in the feedback path. Every repeat gets a bit darker, so
+/// they don't pile up into something harsh.
+const DAMPING: f32 = 0.3;
+
+/// A stereo feedback delay after the keys get mixed.
+pub struct Delay {
+    lines: [Vec<f32>; CHANNEL_NR],
+    /// Position in the delay lines the next sample gets written to.
+    write: usize,
+    /// State of the low-pass in the feedback path per channel.
+    damping: Frame,
+    sample_rate: f32,
+}
+
+impl Delay {
+    pub fn new(sample_rate: f32) -> Self {
+        let len = (MAX_DELAY_TIME * sample_rate).ceil() as usize + 1;
+
+        Self {
+            lines: std::array::from_fn(|_| vec![0.0; len]),
+            write: 0,
+            damping: [0.0; CHANNEL_NR],
+            sample_rate,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for line in &mut self.lines {
+            line.fill(0.0);
+        }
+
+        self.write = 0;
+        self.damping = [0.0; CHANNEL_NR];
+    }
+
+    /// Runs frames through the delay in place. When synced, the delay time is rounded to the
+    /// nearest sixteenth note at `tempo`.
+    pub fn process(&mut self, frames: &mut [Frame], settings: DelaySettings, tempo: f64) {
+        let len = self.lines[0].len();
+        let time = if settings.sync {
+            let sixteenth = (15.0 / tempo) as f32;
+            ((settings.time / sixteenth).round().max(1.0) * sixteenth).min(MAX_DELAY_TIME)
+        } else {
+            settings.time
+        };
+        let delay = ((time * self.sample_rate) as usize).clamp(1, len - 1);
+        let feedback = settings.feedback.clamp(0.0, MAX_FEEDBACK);
+
+        for frame in frames {
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let line = &mut self.lines[channel];
+                let wet = line[(self.write + len - delay) % len];
+
+                self.damping[channel] = math::flush_denormal(
+                    self.damping[channel] + (wet - self.damping[channel]) * DAMPING,
+                );
+                line[self.write] = *sample + self.damping[channel] * feedback;
+                *sample += (wet - *sample) * settings.mix;
+            }
+
+            self.write = (self.write + 1) % len;
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use std::f32::consts::PI;
+
+use crate::{
+    consts::{CUTOFF_MAX, CUTOFF_MIN},
+    math,
+    smooth::Smoother,
+};
+
+/// Highest cutoff the filter is tuned to, as a share of the sample rate. The cutoff's prewarping
+/// goes off to infinity at Nyquist, so it's kept just under it.
+const CUTOFF_NYQUIST_RATIO: f32 = 0.49;
+/// Damping left at full resonance. Going all the way to 0.0 would let the filter ring on forever, so
+/// this keeps its self-oscillation in check.
+const DAMPING_MIN: f32 = 0.05;
+
+/// Maps the cutoff parameter (0.0..=1.0) to a frequency in Hz. The frequency goes up exponentially
+/// with the parameter, so every octave of the range takes the same share of the knob.
+pub fn cutoff_frequency(value: f32) -> f32 {
+    CUTOFF_MIN * (CUTOFF_MAX / CUTOFF_MIN).powf(value.clamp(0.0, 1.0))
+}
+
+/// The inverse of [`cutoff_frequency`].
+pub fn cutoff_value(frequency: f32) -> f32 {
+    ((frequency / CUTOFF_MIN).ln() / (CUTOFF_MAX / CUTOFF_MIN).ln()).clamp(0.0, 1.0)
+}
+
+/// The coefficients of a [`Filter`], worked out once for a cutoff and resonance and shared by
+/// every sample and every key filtered with them.
+#[derive(Clone, Copy)]
+pub struct FilterCoefficients {
+    /// The integrators' gain, which sets the cutoff.
+    g: f32,
+    /// Damping, which goes down as the resonance goes up.
+    k: f32,
+}
+
+impl FilterCoefficients {
+    /// Coefficients for a cutoff in Hz and a resonance from 0.0 to 1.0. Cutoffs past Nyquist are
+    /// clamped to just under it.
+    pub fn new(cutoff: f32, resonance: f32, sample_rate: f32) -> Self {
+        let cutoff = cutoff.min(sample_rate * CUTOFF_NYQUIST_RATIO);
+
+        Self {
+            g: (PI * cutoff / sample_rate).tan(),
+            k: (2.0 - 2.0 * resonance.clamp(0.0, 1.0)).max(DAMPING_MIN),
+        }
+    }
+}
+
+/// Coefficients which glide to new ones rather than jumping to them, so moving the cutoff or the
+/// resonance doesn't step audibly.
+#[derive(Clone, Copy)]
+pub struct SmoothedCoefficients {
+    g: Smoother,
+    k: Smoother,
+}
+
+impl SmoothedCoefficients {
+    /// Starts right at `coefficients`.
+    pub fn new(coefficients: FilterCoefficients, sample_rate: f32) -> Self {
+        Self {
+            g: Smoother::new(coefficients.g, sample_rate),
+            k: Smoother::new(coefficients.k, sample_rate),
+        }
+    }
+
+    /// Starts gliding towards `coefficients`.
+    pub fn set(&mut self, coefficients: FilterCoefficients) {
+        self.g.set(coefficients.g);
+        self.k.set(coefficients.k);
+    }
+
+    /// Returns the coefficients of the next sample.
+    pub fn process(&mut self) -> FilterCoefficients {
+        FilterCoefficients {
+            g: self.g.process(),
+            k: self.k.process(),
+        }
+    }
+}
+
+/// A 2-pole resonant low-pass state-variable filter, with trapezoidal integrators so it stays
+/// stable while its cutoff changes.
+#[derive(Clone, Copy)]
+pub struct Filter {
+    ic1eq: f32,
+    ic2eq: f32,
+}
+
+impl Filter {
+    pub const fn new() -> Self {
+        Self {
+            ic1eq: 0.0,
+            ic2eq: 0.0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Filters a sample, returning the low-pass output.
+    pub fn process(&mut self, input: f32, coefficients: FilterCoefficients) -> f32 {
+        let FilterCoefficients { g, k } = coefficients;
+        let a1 = 1.0 / (1.0 + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+
+        let v3 = input - self.ic2eq;
+        let v1 = a1 * self.ic1eq + a2 * v3;
+        let v2 = self.ic2eq + a2 * self.ic1eq + a3 * v3;
+        self.ic1eq = math::flush_denormal(2.0 * v1 - self.ic1eq);
+        self.ic2eq = math::flush_denormal(2.0 * v2 - self.ic2eq);
+
+        v2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_settles_at_exactly_zero_after_a_signal() {
+        const SAMPLE_RATE: f32 = 48000.0;
+
+        let coefficients = FilterCoefficients::new(1000.0, 0.8, SAMPLE_RATE);
+        let mut filter = Filter::new();
+        for sample in 0..4800 {
+            filter.process((sample as f32 * 0.05).sin(), coefficients);
+        }
+        assert!(filter.ic1eq != 0.0 || filter.ic2eq != 0.0);
+
+        // The resonance rings on for a while, but two seconds of silence is far longer than it
+        // takes to fall under the threshold.
+        for _ in 0..2 * SAMPLE_RATE as usize {
+            filter.process(0.0, coefficients);
+        }
+        assert_eq!(filter.ic1eq, 0.0);
+        assert_eq!(filter.ic2eq, 0.0);
+    }
+}
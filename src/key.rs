@@ -1,15 +1,43 @@
 use std::f32::consts::TAU;
 
 use arrayvec::ArrayVec;
-use clack_plugin::plugin::PluginError;
-use rand::{Rng, rngs::SmallRng};
+use rand::{Rng, SeedableRng, rngs::SmallRng};
 
 use crate::{
-    consts::{KEYS_NR, OSC_MOD, OSC_NR, PHASE_DRY, PHASE_NR},
+    consts::{
+        ADDITIVE_TABLE_SIZE, CHANNEL_NR, FADE_OUT_TIME, KEY_SEED, KEY_TRACK_CENTER, KEYS_NR,
+        OSC_MOD, OSC_NR, PARAMETER_FINE_1, PARAMETER_FINE_3, PARAMETER_LEVEL_1, PARAMETER_LEVEL_3,
+        PARAMETER_PITCH_1, PARAMETER_PITCH_3, PHASE_DRY, PHASE_NR, PHASE_SUB, REFERENCE_NOTE,
+        RELEASE_MIN_TIME, TRANSITION_SIZE_MAX, UNISON_MAX,
+    },
+    filter::{Filter, FilterCoefficients, SmoothedCoefficients},
     math,
-    shared::{Envelope, Fox3oscShared, Modulation, Waveform},
+    oversample::Decimator,
+    shared::{
+        Algorithm, Envelope, EnvelopeCurve, Fox3oscShared, GlideMode, Modulation, Oversample,
+        PhaseMode, PolyMode, Waveform,
+    },
+    smooth::Smoother,
 };
 
+/// Number of samples an oscillator is rendered at a time. Its ADSR, level and pitch sweep are each
+/// run over the whole block first, so the loop rendering the waveform is left with little else.
+const OSC_BLOCK_SIZE: usize = 64;
+
+/// One of the functions a [`Key`] processes an oscillator's waveform with.
+type WaveFunction = fn(&mut Key, osc: usize, phase: f32, transition_size: f32) -> f32;
+
+/// How far along an envelope segment `samples` long the ADSR is at `sample`, from 0.0 to 1.0. The
+/// segment's last sample can fall past its fractional length, and a segment can be 0 samples long,
+/// so the position is capped at the end of the segment.
+fn segment_position(sample: f32, samples: f32) -> f32 {
+    if samples > 0.0 {
+        (sample / samples).min(1.0)
+    } else {
+        1.0
+    }
+}
+
 #[derive(PartialEq)]
 enum ADSRState {
     Ended,
@@ -25,12 +53,18 @@ struct ADSR {
     decay_samples: f32,
     sustain: f32,
     release_samples: f32,
+    /// The envelope's release time in samples, which a release velocity shortens the release from.
+    envelope_release_samples: f32,
+    curve: EnvelopeCurve,
     /// The current amplitude of the ADSR when it's in the `Attack` or `Decay` states. This is for
     /// smoothly transitioning to the `Release` states from those.
     ad_level: f32,
     /// The current amplitude of the ADSR when it's in the `Decay` or `Release` states. This is for
     /// smoothly transitioning to the `Attack` states from those.
     r_level: f32,
+    /// Whether the ADSR moved on since its key's oscillators were last rendered. Only the ADSRs of
+    /// oscillators which get rendered do, and only those keep their key on.
+    advanced: bool,
 }
 
 impl ADSR {
@@ -42,8 +76,11 @@ impl ADSR {
             decay_samples: 0.0,
             sustain: 0.0,
             release_samples: 0.0,
+            envelope_release_samples: 0.0,
+            curve: EnvelopeCurve::Linear,
             ad_level: 0.0,
             r_level: 0.0,
+            advanced: false,
         }
     }
 
@@ -57,10 +94,43 @@ impl ADSR {
         self.decay_samples = envelope.decay * sample_rate;
         self.sustain = envelope.sustain;
         self.release_samples = envelope.release * sample_rate;
+        self.envelope_release_samples = self.release_samples;
+        self.curve = envelope.curve;
+        // It counts as moving on until the key's first rendered, so a key which was just pressed is
+        // on.
+        self.advanced = true;
+    }
+
+    /// Moves the ADSR to the `Release` state, over the envelope's release time scaled by `scale`.
+    /// The scaled release is never cut shorter than `min_samples`, unless the envelope's release
+    /// already is. An ADSR which is already releasing, or fading out, carries on as it was.
+    pub fn release(&mut self, scale: f32, min_samples: f32) {
+        if matches!(self.state, ADSRState::Ended | ADSRState::Release(_)) {
+            return;
+        }
+
+        let release = self.envelope_release_samples;
+        self.release_samples = (release * scale).max(release.min(min_samples));
+        self.state = ADSRState::Release(0.0);
+    }
+
+    /// Moves the ADSR to the `Release` state, releasing over a number of samples instead of the
+    /// envelope's release time. An ADSR which already ended stays that way.
+    pub fn release_over(&mut self, release_samples: f32) {
+        match self.state {
+            ADSRState::Ended => return,
+            // Release from wherever the current release got to.
+            ADSRState::Release(_) => self.ad_level = self.r_level,
+            _ => {}
+        }
+
+        self.release_samples = release_samples;
+        self.state = ADSRState::Release(0.0);
     }
 
     /// Processes and updates the ADSR state. This will return amplitude (0.0 to 1.0) accordingly.
     pub fn process(&mut self) -> f32 {
+        self.advanced = true;
         match self.state {
             ADSRState::Attack(sample) => {
                 self.state = if sample >= self.attack_samples {
@@ -69,7 +139,9 @@ impl ADSR {
                     ADSRState::Attack(sample + 1.0)
                 };
 
-                self.ad_level = sample / self.attack_samples;
+                self.ad_level = self
+                    .curve
+                    .shape(self::segment_position(sample, self.attack_samples));
                 self.ad_level + self.r_level
             }
             ADSRState::Decay(sample) => {
@@ -79,7 +151,11 @@ impl ADSR {
                     ADSRState::Decay(sample + 1.0)
                 };
 
-                self.ad_level = 1.0 - (1.0 - self.sustain) * (sample / self.decay_samples);
+                // Measured up from the sustain level, so the last sample of the decay lands right on
+                // it whatever the curve.
+                let position = self::segment_position(sample, self.decay_samples);
+                self.ad_level =
+                    self.sustain + (1.0 - self.sustain) * (1.0 - self.curve.shape(position));
                 self.r_level = self.ad_level;
                 self.ad_level
             }
@@ -90,7 +166,8 @@ impl ADSR {
                     0.0
                 } else {
                     self.state = ADSRState::Release(sample + 1.0);
-                    self.r_level = self.ad_level * (1.0 - sample / self.release_samples);
+                    let position = self::segment_position(sample, self.release_samples);
+                    self.r_level = self.ad_level * (1.0 - self.curve.shape(position));
                     self.r_level
                 }
             }
@@ -112,291 +189,1050 @@ impl ADSR {
 struct DCBlocker {
     x: f32,
     y: f32,
+    /// The pole of the filter. It's placed from the sample rate, so the cutoff stays the same at
+    /// every sample rate.
+    r: f32,
 }
 
 impl DCBlocker {
-    /// Resets (or creates) the filter to a non-recursed state.
-    pub fn reset() -> Self {
-        Self { x: 0.0, y: 0.0 }
+    /// Creates the filter in a non-recursed state.
+    pub fn new(sample_rate: f32) -> Self {
+        // Where a pole of 0.995 puts the cutoff at 44.1 kHz.
+        const CUTOFF: f32 = 35.0;
+
+        Self {
+            x: 0.0,
+            y: 0.0,
+            r: (-TAU * CUTOFF / sample_rate).exp(),
+        }
     }
 
-    pub fn process(&mut self, sample: f32) -> f32 {
-        const R: f32 = 0.995;
+    /// Resets the filter to a non-recursed state.
+    pub fn reset(&mut self) {
+        self.x = 0.0;
+        self.y = 0.0;
+    }
 
-        self.y = sample - self.x + R * self.y;
+    pub fn process(&mut self, sample: f32) -> f32 {
+        self.y = math::flush_denormal(sample - self.x + self.r * self.y);
         self.x = sample;
         self.y
     }
 }
 
+/// A one-pole filter which tilts the spectrum of white noise. Negative colors low-pass the noise,
+/// darkening it, and positive colors high-pass it, brightening it.
 #[derive(Clone, Copy)]
-struct LinearFaderF32 {
-    destination: f32,
-    distance: f32,
-    value: f32,
+struct NoiseTilt {
+    y: f32,
 }
 
-impl LinearFaderF32 {
-    pub fn new(value: f32) -> Self {
-        Self {
-            value,
-            destination: value,
-            distance: 0.0,
+impl NoiseTilt {
+    /// Resets (or creates) the filter to a non-recursed state.
+    pub fn reset() -> Self {
+        Self { y: 0.0 }
+    }
+
+    pub fn process(&mut self, sample: f32, color: f32) -> f32 {
+        if color == 0.0 {
+            return sample;
         }
+
+        if color < 0.0 {
+            self.y = math::flush_denormal(self.y + (1.0 + color * 0.98) * (sample - self.y));
+            self.y
+        } else {
+            self.y = math::flush_denormal(self.y + color * 0.9 * (sample - self.y));
+            sample - self.y
+        }
+    }
+}
+
+/// Filters white noise into pink noise, which falls off by about 3 dB per octave. It's Paul Kellet's
+/// economy filter, three leaky integrators which each shape a part of the spectrum. They all leak, so
+/// the output never builds up a DC offset.
+///
+/// # Resources
+///
+/// - https://www.firstpr.com.au/dsp/pink-noise/
+#[derive(Clone, Copy)]
+struct PinkFilter {
+    b: [f32; 3],
+}
+
+impl PinkFilter {
+    /// Brings the filtered noise back down to about the loudness of the white noise going in.
+    const GAIN: f32 = 1.0 / 3.0;
+
+    /// Resets (or creates) the filter to a non-recursed state.
+    pub fn reset() -> Self {
+        Self { b: [0.0; 3] }
     }
 
-    pub fn set(&mut self, destination: f32, distance: f32) {
-        self.destination = destination;
-        self.distance = distance;
-        if distance == 0.0 {
-            self.value = destination;
+    pub fn process(&mut self, white: f32) -> f32 {
+        self.b[0] = math::flush_denormal(0.99765 * self.b[0] + white * 0.099046);
+        self.b[1] = math::flush_denormal(0.963 * self.b[1] + white * 0.2965164);
+        self.b[2] = math::flush_denormal(0.57 * self.b[2] + white * 1.0526913);
+
+        (self.b[0] + self.b[1] + self.b[2] + white * 0.1848) * Self::GAIN
+    }
+}
+
+/// Sweeps the phase increment of an oscillator from an offset in semitones back to its target over a
+/// number of samples.
+#[derive(Clone, Copy)]
+struct PitchSweep {
+    amount: f32,
+    samples: f32,
+    sample: f32,
+}
+
+impl PitchSweep {
+    /// Resets (or creates) the sweep to a finished state.
+    pub fn reset() -> Self {
+        Self {
+            amount: 0.0,
+            samples: 0.0,
+            sample: 0.0,
         }
     }
 
+    /// Starts the sweep from `amount` semitones away from the target pitch.
+    pub fn on(&mut self, amount: f32, time: f32, sample_rate: f32) {
+        self.amount = amount;
+        self.samples = time * sample_rate;
+        self.sample = 0.0;
+    }
+
+    /// Advances the sweep by a sample. Returns the amount by which to multiply the phase increment.
     pub fn process(&mut self) -> f32 {
-        if self.distance == 0.0 {
-            self.value = self.destination;
+        if self.amount == 0.0 || self.sample >= self.samples {
+            1.0
         } else {
-            self.value += (self.destination - self.value) * (1.0 / self.distance);
-            self.distance -= 1.0;
+            let offset = self.amount * (1.0 - self.sample / self.samples);
+            self.sample += 1.0;
+            2.0f32.powf(offset / 12.0)
         }
+    }
+}
 
-        self.value
+/// A sample of every output channel.
+pub type Frame = [f32; CHANNEL_NR];
+
+/// Adds an oscillator's sample to a frame, panned by `pan`.
+fn mix(frame: &mut Frame, pan: Frame, sample: f32) {
+    for (channel, gain) in frame.iter_mut().zip(pan) {
+        *channel += sample * gain;
     }
 }
 
 pub struct Key {
     /* --Per oscillator data-- */
     adsr: [ADSR; OSC_NR],
-    levels: [LinearFaderF32; OSC_NR],
+    levels: [Smoother; OSC_NR],
     /// Amplitude of currently proccessed sample
     phase: [f32; PHASE_NR],
+    /// Whether the key's being cut off with a fade out, after which it's no longer counted as a
+    /// voice.
+    fading_out: bool,
     /// Used when processing sploinky and skloinky waveforms, and when doing phase and evil modulation.
     dc_blocker: [DCBlocker; OSC_NR],
+    pitch_sweep: [PitchSweep; OSC_NR],
+    /// Portamento from the previously played note, applied to every oscillator.
+    glide: [PitchSweep; OSC_NR],
+    noise_tilt: [NoiseTilt; OSC_NR],
+    pink_filter: [PinkFilter; OSC_NR],
+    /// The random value the sample and hold waveform is holding.
+    held_sample: [f32; OSC_NR],
+    /// The phase the sample and hold waveform was last processed at, to tell when it wraps. It's
+    /// 1.0 when the key starts so the first sample draws a value.
+    held_phase: [f32; OSC_NR],
+    /// The low-pass filter the key's output runs through, one per channel.
+    filter: [Filter; CHANNEL_NR],
+    /// The filter's coefficients, gliding to the ones passed in. It's `None` while the filter's
+    /// left out, so the filter starts right at the coefficients it's next used with.
+    filter_coefficients: Option<SmoothedCoefficients>,
+    /// Gain of each oscillator into the left and right channels.
+    pan: [Frame; OSC_NR],
+    /// Wavefolding aliases and can push the signal off center, so the folded output is DC-blocked.
+    fold_dc_blocker: [DCBlocker; OSC_NR],
+    /// Pre-gain into the wavefolder.
+    fold: [f32; OSC_NR],
+    /// Single-cycle tables read by additive waveforms. They're rebuilt on every note-on so their
+    /// harmonics stay below Nyquist at the key's pitch.
+    wavetable: Box<[[f32; ADDITIVE_TABLE_SIZE]; OSC_NR]>,
 
     /// Function pointers per oscillator corresponding to their wave functions.
-    process_waveform:
-        [fn(&mut Self, rng: &mut SmallRng, osc: usize, transition_size: f32) -> f32; OSC_NR],
+    process_waveform: [WaveFunction; OSC_NR],
+    /// Whether each oscillator's waveform is corrected with polyblep, which only works as long as
+    /// its corrections don't overlap.
+    polyblep: [bool; OSC_NR],
 
     /* --Per key data-- */
     modulation: Modulation,
+    /// How the oscillators are connected during phase modulation.
+    algorithm: Algorithm,
+    /// Scale of the modulation depth when oscillator 3 modulates oscillator 1, including the
+    /// velocity's share of it.
+    mod_index: f32,
+    /// Whether each oscillator plays in the keyboard zone this key is in.
+    in_zone: [bool; OSC_NR],
+    /// Scale of the modulation in the evil modulation type.
+    evil_depth: f32,
+    /// Multiplier of the polyblep correction's width in the band-limited waveforms.
+    blep_width: f32,
+    /// Ratio to oscillator 1's frequency oscillator 3 is locked to, if it's locked.
+    mod_ratio: Option<f32>,
+    /// Amount of oscillator 3's output fed back into its own phase during phase modulation.
+    fm_feedback: f32,
+    /// Oscillator 3's frequency ratio to oscillator 1 in the frequency modulation type.
+    fm_ratio: f32,
+    /// How far oscillator 3 swings oscillator 1's frequency in the frequency modulation type.
+    fm_depth: f32,
+    /// Oscillator 3's output from the previous sample, for the feedback.
+    fm_feedback_sample: f32,
+    noise_color: f32,
+    /// Level of the sub-oscillator, a sine an octave below oscillator 1.
+    sub_level: f32,
+    /// Number of detuned copies each oscillator plays in unison. It's always 1 for the noise
+    /// waveforms, which have nothing to detune.
+    unison: [usize; OSC_NR],
+    /// What each unison copy multiplies its oscillator's phase increment by.
+    unison_ratios: [f32; UNISON_MAX],
+    /// Phases of each oscillator's unison copies. They're only used with more than one copy, a
+    /// single one plays on the oscillator's own phase.
+    unison_phase: [[f32; UNISON_MAX]; OSC_NR],
+    /// Share of the cycle the pulse waveform spends high.
+    pulse_width: f32,
     sample_rate: f32,
+    /// How many times the sample rate the oscillators run at. Everything which runs along with them,
+    /// like their envelopes, runs at that rate too.
+    oversample: usize,
+    /// Brings the oversampled oscillators back down to the sample rate.
+    decimator: Decimator,
     note: usize,
-    fader_time: f32,
-    /// MIDI note velocity in amplitude (0.0..=1.0)
-    velocity: f32,
+    /// MIDI note velocity in amplitude (0.0..=1.0) per oscillator, scaled by each oscillator's
+    /// velocity amount.
+    velocity: [f32; OSC_NR],
+    /// Amplitude scale from keyboard tracking (0.0..=2.0)
+    key_track: f32,
+    /// Amplitude scale from the key's aftertouch (1.0..=2.0)
+    pressure: f32,
+    /// Amplitude scale from the key's volume note expression.
+    volume: f32,
+    /// The pressure and volume scales multiplied together, which is what scales the key's output.
+    gain: f32,
+    /// Pitch offset from the key's tuning note expression, in steps of the temperament.
+    expression_pitch: f32,
+    /// The id of the CLAP note the key's playing, for note expressions to find it by.
+    note_id: Option<u32>,
+    /// Offset of each oscillator's level from the host's modulation of this key alone, on top of
+    /// the levels every key plays at.
+    level_mod: [f32; OSC_NR],
+    /// Offset of each oscillator's pitch from the host's modulation of this key's coarse pitch
+    /// alone, in whole steps of the temperament.
+    pitch_mod: [f32; OSC_NR],
+    /// Offset of each oscillator's pitch from the host's modulation of this key's fine tuning
+    /// alone, in steps of the temperament.
+    fine_mod: [f32; OSC_NR],
+    /// The key's own random numbers, for its noise, random waveforms and random phases. Every key
+    /// has its own, so keys playing noise at once aren't correlated, and what they draw doesn't
+    /// depend on the order the keys are played in.
+    rng: SmallRng,
 }
 
 impl Key {
     /// Creates a key in an uninitialized state. The frequency is calculated from `note`, which corresponds
     /// to a MIDI note. The ADSR is also set to an uninitialized state.
     fn new(sample_rate: f32, note: usize) -> Self {
-        let fader_time = sample_rate * 0.01;
-
         Self {
+            rng: Self::seeded_rng(note),
             sample_rate,
+            oversample: 1,
+            decimator: Decimator::new(),
             note,
-            fader_time,
-            levels: std::array::from_fn(|_| LinearFaderF32::new(0.0)),
+            levels: [Smoother::new(0.0, sample_rate); OSC_NR],
             adsr: std::array::from_fn(|_| ADSR::reset()),
-            dc_blocker: std::array::from_fn(|_| DCBlocker::reset()),
+            dc_blocker: [DCBlocker::new(sample_rate); OSC_NR],
+            pitch_sweep: std::array::from_fn(|_| PitchSweep::reset()),
+            glide: std::array::from_fn(|_| PitchSweep::reset()),
+            noise_tilt: std::array::from_fn(|_| NoiseTilt::reset()),
+            pink_filter: std::array::from_fn(|_| PinkFilter::reset()),
+            held_sample: [0.0; OSC_NR],
+            held_phase: [1.0; OSC_NR],
+            filter: [Filter::new(); CHANNEL_NR],
+            filter_coefficients: None,
+            pan: [[1.0; CHANNEL_NR]; OSC_NR],
+            fold_dc_blocker: [DCBlocker::new(sample_rate); OSC_NR],
+            fold: [1.0; OSC_NR],
+            wavetable: Box::new([[0.0; ADDITIVE_TABLE_SIZE]; OSC_NR]),
             phase: [0.0; PHASE_NR],
+            fading_out: false,
             process_waveform: [Self::process_sine; OSC_NR],
+            polyblep: [false; OSC_NR],
             modulation: Modulation::None,
-            velocity: 0.0,
+            algorithm: Algorithm::ThreeToOne,
+            mod_index: 1.0,
+            evil_depth: 1.0,
+            blep_width: 1.0,
+            in_zone: [true; OSC_NR],
+            mod_ratio: None,
+            fm_feedback: 0.0,
+            fm_ratio: 1.0,
+            fm_depth: 0.0,
+            fm_feedback_sample: 0.0,
+            noise_color: 0.0,
+            sub_level: 0.0,
+            unison: [1; OSC_NR],
+            unison_ratios: [1.0; UNISON_MAX],
+            unison_phase: [[0.0; UNISON_MAX]; OSC_NR],
+            pulse_width: 0.5,
+            velocity: [0.0; OSC_NR],
+            key_track: 1.0,
+            pressure: 1.0,
+            volume: 1.0,
+            gain: 1.0,
+            expression_pitch: 0.0,
+            note_id: None,
+            level_mod: [0.0; OSC_NR],
+            pitch_mod: [0.0; OSC_NR],
+            fine_mod: [0.0; OSC_NR],
         }
     }
 
-    /// Initializes a key as pressed.
-    fn on(
-        &mut self,
-        velocity: u8,
-        shared: &Fox3oscShared,
-        rng: &mut SmallRng,
-    ) -> Result<(), PluginError> {
+    /// Initializes a key as pressed. The key glides to its pitch from `glide_from` if it's a note.
+    fn on(&mut self, velocity: u8, glide_from: Option<usize>, shared: &Fox3oscShared) {
+        // A note-on with a velocity of 0 is a note-off.
         if velocity == 0 {
-            self.end();
-            return Ok(());
+            self.release();
+            return;
         }
 
-        let mut waveforms = *shared.get_waveforms()?;
-        let envelope = *shared.get_envelope()?;
-        let hq = *shared.get_hq()?;
-        let levels = *shared.get_levels()?;
+        let mut waveforms = shared.get_waveforms();
+        let envelope = shared.modulated_envelope();
+        let band_limited = shared.render_quality().band_limited();
+        let levels = shared.modulated_levels();
+        let pitch_envelope = shared.get_pitch_envelope();
+        let glide = shared.get_glide();
+        let partials = shared.get_partials();
+        let stereo_spread = shared.get_stereo_spread();
+        let pan = shared.get_pan();
+        let fine = shared.modulated_fine();
+        let pitch = shared.modulated_pitch();
+        let octave_steps = shared.octave_steps();
+        let transpose = shared.get_master_tuning().transpose;
+        let concert_pitch = shared.get_concert_pitch();
+        // The glide is measured in semitones, so the distance in steps gets converted.
+        let glide_amount = glide_from.map_or(0.0, |from| {
+            (from as f32 - self.note as f32) * 12.0 / shared.n_tet()
+        });
+
+        let velocity_sensitivity = shared.get_velocity_sensitivity();
+        let velocity_curve = shared.get_velocity_curve();
+        let vel_to_mod = shared.get_vel_to_mod();
+        let osc_velocity = shared.get_osc_velocity();
+
+        self.modulation = shared.get_modulation();
+        self.algorithm = shared.get_algorithm();
+        self.mod_index = shared.modulated_mod_index();
+        self.evil_depth = shared.modulated_evil_depth();
+        self.blep_width = shared.get_blep_width();
+        self.place(shared);
+        self.mod_ratio = if shared.get_ratio_lock() {
+            Some(math::locked_ratio(
+                pitch[OSC_MOD].floor() + octave_steps[OSC_MOD] - pitch[0].floor() - octave_steps[0],
+                shared.n_tet(),
+            ))
+        } else {
+            None
+        };
+        self.fm_feedback = shared.get_fm_feedback();
+        let fm = shared.get_fm();
+        self.fm_ratio = fm.ratio();
+        self.fm_depth = fm.depth;
+        self.fold = shared.get_fold();
+        self.noise_color = shared.get_noise_color();
+        self.sub_level = shared.get_sub_level();
+        let unison = shared.get_unison();
+        let unison_count = (unison.count as usize).clamp(1, UNISON_MAX);
+        // The copies are spread evenly from the lowest to the highest detune.
+        for (copy, ratio) in self.unison_ratios[..unison_count].iter_mut().enumerate() {
+            let spread = copy as f32 / (unison_count - 1).max(1) as f32 * 2.0 - 1.0;
+            *ratio = 2f32.powf(spread * unison.detune / 1200.0);
+        }
+        self.pulse_width = shared.get_pulse_width();
+        let velocity = velocity_curve.apply(velocity as f32 / 127.0);
+        self.velocity = osc_velocity.map(|amount| {
+            let sensitivity = velocity_sensitivity * amount;
+            1.0 - sensitivity + sensitivity * velocity
+        });
+        // Softer notes get less modulation, the velocity scaling it the same as it scales the level.
+        let key_velocity = 1.0 - velocity_sensitivity + velocity_sensitivity * velocity;
+        self.mod_index *= 1.0 - vel_to_mod + vel_to_mod * key_velocity;
+
+        let was_on = self.is_on();
+        self.fading_out = false;
+        self.reset_expressions();
+        // A key which is still sounding carries on from its phases whatever the mode, so they don't
+        // jump. It also keeps running at its rate, so its envelopes carry on from where they are.
+        if !was_on {
+            self.set_oversample(shared.render_oversample().factor());
+            match shared.get_phase_mode() {
+                PhaseMode::Retrigger => self.phase = [0.0; PHASE_NR],
+                PhaseMode::Free => {}
+                PhaseMode::Random => {
+                    for phase in &mut self.phase {
+                        *phase = self.rng.random_range(0.0..1.0);
+                    }
+                }
+            }
 
-        self.modulation = *shared.get_modulation()?;
-        self.velocity = velocity as f32 / 127.0;
+            // Phase modulation crossfades between an oscillator's dry and modulated phases, so
+            // they start out together like they do from 0.0.
+            self.phase.copy_within(..OSC_NR, PHASE_DRY);
+        }
 
         for osc in 0..OSC_NR {
-            self.adsr[osc].on(envelope, self.sample_rate);
-            self.levels[osc] = LinearFaderF32::new(levels[osc]);
+            // The spread sends the lowest detuned oscillator left and the highest detuned one
+            // right, with the one in between staying centered. Each oscillator's own pan moves it
+            // on top of that.
+            let rank = (0..OSC_NR)
+                .filter(|&other| (fine[other], other) < (fine[osc], osc))
+                .count();
+            let position = (rank as f32 / (OSC_NR - 1) as f32 * 2.0 - 1.0) * stereo_spread;
+            let (left, right) = math::pan(position + pan[osc]);
+            self.pan[osc] = [left, right];
+
+            self.adsr[osc].on(envelope[osc], self.osc_rate());
+            self.pitch_sweep[osc].on(
+                pitch_envelope.amount[osc],
+                pitch_envelope.time,
+                self.osc_rate(),
+            );
+            self.glide[osc].on(glide_amount, glide.time, self.osc_rate());
+            self.levels[osc].reset(levels[osc]);
             self.process_waveform[osc] = loop {
                 match waveforms[osc] {
                     Waveform::Sine => break Self::process_sine,
                     Waveform::Noise => break Self::process_noise,
-                    Waveform::Triangle if hq[osc] => break Self::process_triangle_hq,
+                    Waveform::Triangle if band_limited => break Self::process_triangle_hq,
                     Waveform::Triangle => break Self::process_triangle,
-                    Waveform::Square if hq[osc] => break Self::process_square_hq,
+                    Waveform::Square if band_limited => break Self::process_square_hq,
                     Waveform::Square => break Self::process_square,
-                    Waveform::Saw if hq[osc] => break Self::process_saw_hq,
+                    Waveform::Saw if band_limited => break Self::process_saw_hq,
                     Waveform::Saw => break Self::process_saw,
                     Waveform::Sploinky => break Self::process_sploinky,
                     Waveform::Skloinky => break Self::process_skloinky,
-                    Waveform::Random => {
-                        waveforms[osc] = rng.random_range(0.0..Waveform::Random.into()).into()
-                    }
+                    Waveform::Additive => break Self::process_additive,
+                    Waveform::Pulse if band_limited => break Self::process_pulse_hq,
+                    Waveform::Pulse => break Self::process_pulse,
+                    Waveform::SampleHold => break Self::process_sample_hold,
+                    Waveform::PinkNoise => break Self::process_pink_noise,
+                    Waveform::Random => waveforms[osc] = Waveform::random(&mut self.rng),
+                }
+            };
+
+            self.polyblep[osc] = match waveforms[osc] {
+                Waveform::Triangle | Waveform::Square | Waveform::Saw | Waveform::Pulse => {
+                    band_limited
                 }
+                Waveform::Sploinky | Waveform::Skloinky => true,
+                _ => false,
+            };
+
+            let was_unison = self.unison[osc] > 1;
+            self.unison[osc] = match waveforms[osc] {
+                Waveform::Noise | Waveform::SampleHold | Waveform::PinkNoise => 1,
+                _ => unison_count,
             };
+            // Copies starting in phase would add up to a loud spike at the start of the note, so
+            // they start at random phases instead. A key which is still sounding with copies keeps
+            // their phases, so they don't jump.
+            if self.unison[osc] > 1 && !(was_unison && was_on) {
+                for phase in &mut self.unison_phase[osc] {
+                    *phase = self.rng.random_range(0.0..1.0);
+                }
+            }
+
+            if let Waveform::Additive = waveforms[osc] {
+                let frequency = NoteData::frequency(
+                    self.note as f32
+                        + (pitch[osc].floor() + octave_steps[osc] + transpose - shared.pitch_amount)
+                            as f32,
+                    shared.n_tet(),
+                    concert_pitch,
+                );
+                // Partials above Nyquist would alias, so they're left out of the table.
+                let nyquist_partials = (self.osc_rate() / 2.0 / frequency) as u32;
+                math::additive_wavetable(
+                    &mut self.wavetable[osc],
+                    partials.min(nyquist_partials).max(1) as usize,
+                );
+            }
         }
+    }
+
+    /// Sets how many times the sample rate the oscillators run at. Whatever was placed from the rate
+    /// gets placed again for the new one, and it all starts over, having run at the old one.
+    fn set_oversample(&mut self, oversample: usize) {
+        if oversample == self.oversample {
+            return;
+        }
+
+        self.oversample = oversample;
+        let rate = self.osc_rate();
+        self.levels = [Smoother::new(0.0, rate); OSC_NR];
+        self.dc_blocker = [DCBlocker::new(rate); OSC_NR];
+        self.fold_dc_blocker = [DCBlocker::new(rate); OSC_NR];
+        self.decimator.reset();
+    }
+
+    /// The rate the oscillators run at, in Hz.
+    fn osc_rate(&self) -> f32 {
+        self.sample_rate * self.oversample as f32
+    }
 
-        Ok(())
+    /// The random number generator of the key playing `note`, seeded from the note so every run
+    /// draws the same numbers.
+    fn seeded_rng(note: usize) -> SmallRng {
+        SmallRng::seed_from_u64(KEY_SEED ^ note as u64)
     }
 
+    /// Works out everything that depends on where the key is on the keyboard, which are the zones
+    /// it's in and how much key tracking scales it by.
+    fn place(&mut self, shared: &Fox3oscShared) {
+        let split = shared.get_split();
+        self.in_zone = shared
+            .get_zones()
+            .map(|zone| zone.contains(self.note, split));
+
+        let key_track = shared.get_key_track();
+        self.key_track = (1.0
+            + key_track * (self.note as f32 - KEY_TRACK_CENTER as f32) / (KEYS_NR / 2) as f32)
+            .clamp(0.0, 2.0);
+    }
+
+    /// Moves a sounding key over to `note` without retriggering its envelopes, for legato. It glides
+    /// there from the note it was playing, unless glide is off.
+    fn repitch(&mut self, note: usize, shared: &Fox3oscShared) {
+        let glide = shared.get_glide();
+        if !matches!(glide.mode, GlideMode::Off) {
+            let glide_amount = (self.note as f32 - note as f32) * 12.0 / shared.n_tet();
+            let rate = self.osc_rate();
+            for glide_sweep in &mut self.glide {
+                glide_sweep.on(glide_amount, glide.time, rate);
+            }
+        }
+
+        self.note = note;
+        self.reset_expressions();
+        self.place(shared);
+    }
+
+    /// Whether any of the key's oscillators is still sounding. Only the oscillators which were
+    /// rendered last time count, since the ADSRs of the others stand still and would keep the key on
+    /// forever.
     fn is_on(&self) -> bool {
-        self.adsr.iter().all(|adsr| adsr.state != ADSRState::Ended)
+        self.adsr
+            .iter()
+            .any(|adsr| adsr.advanced && adsr.state != ADSRState::Ended)
     }
 
+    /// Whether the key counts towards the voice limit, which it does as long as it's on and isn't
+    /// already fading out.
+    fn is_voice(&self) -> bool {
+        self.is_on() && !self.fading_out
+    }
+
+    /// Whether the key is on and hasn't been released yet.
+    fn is_held(&self) -> bool {
+        self.is_on()
+            && self
+                .adsr
+                .iter()
+                .any(|adsr| !matches!(adsr.state, ADSRState::Release(_)))
+    }
+
+    /// Silences the key right away, dropping everything it had going. To cut a key off while it can
+    /// still be heard, use [`Key::fade_out`] instead, which doesn't click.
+    ///
+    /// The phases are kept, for the next note to start from in the free phase mode.
     pub fn end(&mut self) {
-        self.phase = [0.0; PHASE_NR];
+        self.fading_out = false;
+        self.fm_feedback_sample = 0.0;
+        self.pressure = 1.0;
+        self.reset_expressions();
         for adsr in &mut self.adsr {
             *adsr = ADSR::reset();
         }
 
         for dc_blocker in &mut self.dc_blocker {
-            *dc_blocker = DCBlocker::reset();
+            dc_blocker.reset();
+        }
+
+        for pitch_sweep in &mut self.pitch_sweep {
+            *pitch_sweep = PitchSweep::reset();
         }
+
+        for glide in &mut self.glide {
+            *glide = PitchSweep::reset();
+        }
+
+        for noise_tilt in &mut self.noise_tilt {
+            *noise_tilt = NoiseTilt::reset();
+        }
+
+        for pink_filter in &mut self.pink_filter {
+            *pink_filter = PinkFilter::reset();
+        }
+
+        self.held_sample = [0.0; OSC_NR];
+        self.held_phase = [1.0; OSC_NR];
+        for filter in &mut self.filter {
+            filter.reset();
+        }
+        self.filter_coefficients = None;
+
+        for fold_dc_blocker in &mut self.fold_dc_blocker {
+            fold_dc_blocker.reset();
+        }
+        self.decimator.reset();
     }
 
     pub fn release(&mut self) {
+        self.release_with_velocity(0, 0.0);
+    }
+
+    /// Releases the key, with a harder note-off shortening the release. `amount` is how much the
+    /// velocity shortens it by, with a full velocity at an amount of 1.0 cutting the release right
+    /// down to [`RELEASE_MIN_TIME`].
+    pub fn release_with_velocity(&mut self, velocity: u8, amount: f32) {
+        let scale = 1.0 - amount * velocity as f32 / 127.0;
+        let min_samples = RELEASE_MIN_TIME * self.osc_rate();
         for adsr in &mut self.adsr {
-            adsr.state = ADSRState::Release(0.0);
+            adsr.release(scale, min_samples);
         }
     }
 
+    /// Sets the key's aftertouch, which swells it up to `1.0 + amount` times its amplitude at full
+    /// pressure.
+    pub fn set_pressure(&mut self, pressure: u8, amount: f32) {
+        self.pressure = 1.0 + amount * pressure as f32 / 127.0;
+        self.gain = self.pressure * self.volume;
+    }
+
+    /// Sets the key's volume note expression, which scales its amplitude.
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume;
+        self.gain = self.pressure * self.volume;
+    }
+
+    /// Sets the key's tuning note expression, an offset from its pitch in steps of the temperament.
+    pub fn set_expression_pitch(&mut self, pitch: f32) {
+        self.expression_pitch = pitch;
+    }
+
+    /// Sets the host's modulation of one of the key's parameters, the ones modulatable per note,
+    /// replacing whatever it was modulated by before. The sum gets clamped into the parameter's
+    /// range the same as it is without it.
+    pub fn set_param_mod(&mut self, param_id: u32, amount: f64, shared: &Fox3oscShared) {
+        match param_id {
+            PARAMETER_LEVEL_1..=PARAMETER_LEVEL_3 => {
+                let osc = (param_id - PARAMETER_LEVEL_1) as usize;
+                self.level_mod[osc] = amount as f32;
+                self.set_level(shared.modulated_levels()[osc], osc);
+            }
+            // The pitch parameters are stepped, so they're modulated by whole steps.
+            PARAMETER_PITCH_1..=PARAMETER_PITCH_3 => {
+                self.pitch_mod[(param_id - PARAMETER_PITCH_1) as usize] = amount.round() as f32;
+            }
+            PARAMETER_FINE_1..=PARAMETER_FINE_3 => {
+                self.fine_mod[(param_id - PARAMETER_FINE_1) as usize] =
+                    amount as f32 / 1200.0 * shared.n_tet();
+            }
+            _ => {}
+        }
+    }
+
+    /// Puts the key's note expressions and per-note modulation back to neutral for a new note, and
+    /// forgets its note id.
+    fn reset_expressions(&mut self) {
+        self.volume = 1.0;
+        self.gain = self.pressure;
+        self.expression_pitch = 0.0;
+        self.note_id = None;
+        self.level_mod = [0.0; OSC_NR];
+        self.pitch_mod = [0.0; OSC_NR];
+        self.fine_mod = [0.0; OSC_NR];
+    }
+
+    /// Cuts the key off with a short fade out instead of its envelope's release. The key stays on
+    /// until the fade finishes.
+    pub fn fade_out(&mut self) {
+        self.fading_out = true;
+        let samples = FADE_OUT_TIME * self.osc_rate();
+        for adsr in &mut self.adsr {
+            adsr.release_over(samples);
+        }
+    }
+
+    /// Adds the key's output to `output`. It runs through the filter on the way, unless `filter` is
+    /// `None` for when the filter's fully open.
     pub fn process(
         &mut self,
-        output: &mut [f32],
-        pitch: [usize; OSC_NR],
-        rng: &mut SmallRng,
+        output: &mut [Frame],
+        tuning: Tuning,
         oscs: &[usize],
         note_data: &[NoteData],
+        filter: Option<FilterCoefficients>,
     ) {
-        // We should never call this function on a key that isn't on
-        debug_assert!(self.is_on());
+        /// Number of frames rendered at a time before they get decimated and filtered.
+        const FILTER_BLOCK_SIZE: usize = 64;
+
+        if filter.is_none() && self.oversample == 1 {
+            self.filter_coefficients = None;
+            self.process_oscs(output, tuning, oscs, note_data);
+            return;
+        }
+
+        let mut smoothed = filter.map(|coefficients| {
+            let mut smoothed = self
+                .filter_coefficients
+                .unwrap_or_else(|| SmoothedCoefficients::new(coefficients, self.sample_rate));
+            smoothed.set(coefficients);
+            smoothed
+        });
+
+        // The oscillators add onto what's already in the output, so the key gets rendered on its
+        // own in blocks first, and only its part gets decimated and filtered.
+        for chunk in output.chunks_mut(FILTER_BLOCK_SIZE) {
+            let mut block = [[0.0; CHANNEL_NR]; FILTER_BLOCK_SIZE * Oversample::X4.factor()];
+            let block = &mut block[..chunk.len() * self.oversample];
+            self.process_oscs(block, tuning, oscs, note_data);
+            let block = self.decimator.process(block, self.oversample);
+
+            for (frame, key_frame) in chunk.iter_mut().zip(block.iter()) {
+                match smoothed.as_mut() {
+                    Some(smoothed) => {
+                        let coefficients = smoothed.process();
+                        for (channel, filter) in self.filter.iter_mut().enumerate() {
+                            frame[channel] += filter.process(key_frame[channel], coefficients);
+                        }
+                    }
+                    None => {
+                        for (channel, sample) in frame.iter_mut().zip(key_frame) {
+                            *channel += sample;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.filter_coefficients = smoothed;
+    }
+
+    /// Adds the output of the key's oscillators to `output`.
+    fn process_oscs(
+        &mut self,
+        output: &mut [Frame],
+        tuning: Tuning,
+        oscs: &[usize],
+        note_data: &[NoteData],
+    ) {
+        for adsr in &mut self.adsr {
+            adsr.advanced = false;
+        }
+
+        // Oscillators outside of the key's zone are left out as if they were muted, and so are
+        // oscillators whose ADSR already ended, which have nothing left to play. A key which ends
+        // partway through rendering renders nothing from then on.
+        let oscs: ArrayVec<usize, OSC_NR> = oscs
+            .iter()
+            .copied()
+            .filter(|&osc| self.in_zone[osc] && self.adsr[osc].state != ADSRState::Ended)
+            .collect();
+        let oscs = oscs.as_slice();
 
         match self.modulation {
-            Modulation::None => self.process_3sub(output, pitch, rng, oscs, note_data),
-            Modulation::Phase => self.process_1pm_1sub(output, pitch, rng, oscs, note_data),
-            Modulation::Evil => self.process_1evil_1sub(output, pitch, rng, oscs, note_data),
+            Modulation::None => self.process_3sub(output, tuning, oscs, note_data),
+            Modulation::Phase => self.process_pm_algorithm(output, tuning, oscs, note_data),
+            Modulation::Evil => self.process_1evil_1sub(output, tuning, oscs, note_data),
+            Modulation::Cross => self.process_2cross_1sub(output, tuning, oscs, note_data),
+            Modulation::Sync => self.process_1sync_1sub(output, tuning, oscs, note_data),
+            Modulation::Ring => self.process_1ring_1sub(output, tuning, oscs, note_data),
+            Modulation::Frequency => self.process_1fm_1sub(output, tuning, oscs, note_data),
         }
     }
 
+    /// Sets the level an oscillator plays at, on top of which this key's own modulation goes.
     pub fn set_level(&mut self, level: f32, osc: usize) {
-        self.levels[osc].set(level, self.fader_time);
+        self.levels[osc].set((level + self.level_mod[osc]).clamp(0.0, 1.0));
     }
 
-    /// Regular subtractive synthesis.
+    /// Advances an oscillator's ADSR over a block of samples.
+    fn adsr_block(&mut self, osc: usize, adsr: &mut [f32]) {
+        for sample in adsr {
+            *sample = self.adsr[osc].process();
+        }
+    }
+
+    /// Advances the smoothing of an oscillator's level over a block of samples.
+    fn level_block(&mut self, osc: usize, levels: &mut [f32]) {
+        for sample in levels {
+            *sample = self.levels[osc].process();
+        }
+    }
+
+    /// Works out an oscillator's phase increment over a block of samples, advancing its pitch
+    /// envelope and glide along the way.
+    fn increment_block(&mut self, osc: usize, increment: f32, increments: &mut [f32]) {
+        for sample in increments {
+            *sample = increment * self.sweep(osc);
+        }
+    }
+
+    /// Regular subtractive synthesis. The sub-oscillator plays along with oscillator 1, following its
+    /// pitch an octave down, and shaped by its velocity and ADSR. Oscillators with unison play all of
+    /// their detuned copies.
     fn process_3sub(
         &mut self,
-        output: &mut [f32],
-        pitch: [usize; OSC_NR],
-        rng: &mut SmallRng,
+        output: &mut [Frame],
+        tuning: Tuning,
         oscs: &[usize],
         note_data: &[NoteData],
     ) {
         for &osc in oscs {
-            let note_data = note_data[self.note + pitch[osc]];
+            let note_data = self.osc_note_data(note_data, tuning, osc);
+            let (process, transition_size) = self.wave_function(osc, note_data.transition_size);
+
+            // The sub-oscillator is left out altogether when it's off, so it doesn't change a thing.
+            let sub = osc == 0 && self.sub_level > 0.0;
+            let unison = self.unison[osc] > 1;
+
+            for chunk in output.chunks_mut(OSC_BLOCK_SIZE) {
+                let mut adsr = [0.0; OSC_BLOCK_SIZE];
+                let mut levels = [0.0; OSC_BLOCK_SIZE];
+                let mut increments = [0.0; OSC_BLOCK_SIZE];
+                self.adsr_block(osc, &mut adsr[..chunk.len()]);
+                self.level_block(osc, &mut levels[..chunk.len()]);
+                self.increment_block(osc, note_data.increment, &mut increments[..chunk.len()]);
+
+                for (sample, frame) in chunk.iter_mut().enumerate() {
+                    let wave = if unison {
+                        self.unison_waveform(osc, note_data.transition_size)
+                    } else {
+                        let phase = self.phase[osc];
+                        process(self, osc, phase, transition_size)
+                    };
+                    let adsr = adsr[sample];
+                    self::mix(
+                        frame,
+                        self.pan[osc],
+                        self.fold(wave, osc)
+                            * self.velocity[osc]
+                            * self.key_track
+                            * self.gain
+                            * levels[sample]
+                            * adsr,
+                    );
+
+                    let increment = increments[sample];
+                    if unison {
+                        let count = self.unison[osc];
+                        for (phase, ratio) in self.unison_phase[osc][..count]
+                            .iter_mut()
+                            .zip(self.unison_ratios)
+                        {
+                            *phase = math::wrap_phase(*phase + increment * ratio);
+                        }
+                    }
 
-            for sample in output.iter_mut() {
-                *sample += (self.process_waveform[osc])(self, rng, osc, note_data.transition_size)
-                    * self.velocity
-                    * self.levels[osc].process()
-                    * self.adsr[osc].process();
+                    if sub {
+                        self::mix(
+                            frame,
+                            self.pan[osc],
+                            (self.phase[PHASE_SUB] * TAU).sin()
+                                * self.velocity[osc]
+                                * self.key_track
+                                * self.gain
+                                * self.sub_level
+                                * adsr,
+                        );
+                        self.phase[PHASE_SUB] =
+                            math::wrap_phase(self.phase[PHASE_SUB] + increment / 2.0);
+                    }
 
-                self.phase[osc] = (self.phase[osc] + note_data.increment) % 1.0;
+                    self.phase[osc] = math::wrap_phase(self.phase[osc] + increment);
+                }
             }
         }
     }
 
-    /// Oscillator 3's signal is used to modulate Oscillattor 1's phase. Adjusting Oscillator 3's level
-    /// adjusts the mix of dry un-modulated signal and wet modulated signal that's output.
-    fn process_1pm_1sub(
+    /// The sum of an oscillator's unison copies at their own phases. Detuned copies drift in and out
+    /// of phase with each other, so they're summed at equal power to keep the oscillator about as loud
+    /// as a single copy.
+    fn unison_waveform(&mut self, osc: usize, transition_size: f32) -> f32 {
+        let count = self.unison[osc];
+        let phases = self.unison_phase[osc];
+        let ratios = self.unison_ratios;
+
+        let mut sum = 0.0;
+        for (phase, ratio) in phases[..count].iter().zip(ratios) {
+            sum += self.waveform(osc, *phase, transition_size * ratio);
+        }
+
+        sum / (count as f32).sqrt()
+    }
+
+    /// Phase modulation with the oscillators connected by the key's algorithm. A modulator's level
+    /// crossfades between the dry un-modulated signal and wet modulated signal of the oscillator it
+    /// modulates, and modulators aren't heard themselves. The crossfade is equal-power, so turning
+    /// a modulator up doesn't duck the oscillator it modulates.
+    fn process_pm_algorithm(
         &mut self,
-        output: &mut [f32],
-        pitch: [usize; OSC_NR],
-        rng: &mut SmallRng,
+        output: &mut [Frame],
+        tuning: Tuning,
         oscs: &[usize],
         note_data: &[NoteData],
     ) {
-        for &osc in oscs {
-            if osc == 0 {
-                let osc1_note_data = note_data[self.note + pitch[osc]];
-                let mod_osc_note_data = note_data[self.note + pitch[OSC_MOD]];
-
-                for sample in output.iter_mut() {
-                    /// Amount by which to scale down the PM signal's amplitude.
-                    ///
-                    /// I want the PM signal to be scaled down to 48% of the maximum amplitude because
-                    /// modulating the Osc 1 signal with a higher amplitude than that creates very
-                    /// nasty aliasing.
-                    const OSC_MOD_LEVEL_MODIFIER: f32 = 100.0 / 48.0;
-
-                    // We are using the ADSR signal in multiple points here so we're processing it
-                    // only once here and reusing it where needed.
-                    let osc1_adsr = self.adsr[osc].process();
-                    let osc1_level = self.levels[osc].process();
-                    let let_osc_mod = self.levels[OSC_MOD].process();
-                    let sample_dc = (self.process_waveform[osc])(
-                        self,
-                        rng,
-                        osc,
-                        osc1_note_data.transition_size,
-                    ) * self.velocity
-                        * osc1_level
-                        * osc1_adsr;
-
-                    *sample += self.dc_blocker[osc].process(sample_dc);
-                    self.phase[osc] = ((self.phase[osc]
-                        + (self.process_waveform[OSC_MOD])(
-                            self,
-                            rng,
-                            OSC_MOD,
-                            mod_osc_note_data.transition_size,
-                        ))
-                        * (let_osc_mod / OSC_MOD_LEVEL_MODIFIER))
-                        % 1.0;
-
-                    self.phase[OSC_MOD] = (self.phase[OSC_MOD] + mod_osc_note_data.increment) % 1.0;
-                    self.phase[PHASE_DRY] =
-                        (self.phase[PHASE_DRY] + osc1_note_data.increment) % 1.0;
-
-                    self.phase.swap(0, PHASE_DRY);
-                    *sample += (self.process_waveform[osc])(
-                        self,
-                        rng,
-                        osc,
-                        osc1_note_data.transition_size,
-                    ) * self.velocity
-                        * (osc1_level - (osc1_level * let_osc_mod) / OSC_MOD_LEVEL_MODIFIER)
-                        * osc1_adsr;
-
-                    self.phase.swap(0, PHASE_DRY);
+        /// Amount by which to scale down the PM signal's amplitude.
+        ///
+        /// I want the PM signal to be scaled down to 48% of the maximum amplitude because modulating
+        /// the Osc 1 signal with a higher amplitude than that creates very nasty aliasing. This is
+        /// the cap at a modulation index of 1.0, higher indices push past it and alias accordingly.
+        /// Oversampling keeps most of that aliasing out, but the cap stays so patches sound the same
+        /// however much they're oversampled.
+        const OSC_MOD_LEVEL_MODIFIER: f32 = 100.0 / 48.0;
+
+        let routing = self.algorithm.routing();
+        let note_data: [NoteData; OSC_NR] =
+            std::array::from_fn(|osc| self.osc_note_data(note_data, tuning, osc));
+        let wave_functions: [(WaveFunction, f32); OSC_NR] =
+            std::array::from_fn(|osc| self.wave_function(osc, note_data[osc].transition_size));
+        // Oscillators only sound or modulate when they're meant to be heard.
+        let active: [bool; OSC_NR] = std::array::from_fn(|osc| oscs.contains(&osc));
+        let is_modulator: [bool; OSC_NR] =
+            std::array::from_fn(|osc| routing.iter().any(|modulators| modulators[osc]));
+        let modulated: [bool; OSC_NR] = std::array::from_fn(|osc| {
+            (0..OSC_NR).any(|modulator| routing[osc][modulator] && active[modulator])
+        });
+
+        for chunk in output.chunks_mut(OSC_BLOCK_SIZE) {
+            let mut adsr = [[0.0; OSC_BLOCK_SIZE]; OSC_NR];
+            let mut levels = [[0.0; OSC_BLOCK_SIZE]; OSC_NR];
+            let mut increments = [[0.0; OSC_BLOCK_SIZE]; OSC_NR];
+            for osc in (0..OSC_NR).filter(|&osc| active[osc]) {
+                self.level_block(osc, &mut levels[osc][..chunk.len()]);
+
+                // Modulators aren't heard, so their ADSRs are left alone, and a modulated
+                // modulator's phase is set by its modulation instead of being advanced.
+                if !is_modulator[osc] {
+                    self.adsr_block(osc, &mut adsr[osc][..chunk.len()]);
                 }
-            } else if osc == 1 {
-                let note_data = note_data[self.note + pitch[osc]];
 
-                for sample in output.iter_mut() {
-                    *sample +=
-                        (self.process_waveform[osc])(self, rng, osc, note_data.transition_size)
-                            * self.velocity
-                            * self.levels[osc].process()
-                            * self.adsr[osc].process();
-                    self.phase[osc] = (self.phase[osc] + note_data.increment) % 1.0;
+                if !(is_modulator[osc] && modulated[osc]) {
+                    let increment = note_data[osc].increment;
+                    self.increment_block(osc, increment, &mut increments[osc][..chunk.len()]);
+                }
+            }
+
+            for (sample, frame) in chunk.iter_mut().enumerate() {
+                // The signal each modulator passes on, and the level it modulates with.
+                let mut signals = [0.0; OSC_NR];
+                let mut depths = [0.0; OSC_NR];
+
+                // Going backwards, a modulator's signal is always ready by the time the
+                // oscillators it modulates need it.
+                for osc in (0..OSC_NR).rev() {
+                    if !active[osc] {
+                        continue;
+                    }
+
+                    let (modulation, depth) = (0..OSC_NR)
+                        .filter(|&modulator| routing[osc][modulator])
+                        .fold((0.0, 0.0), |(modulation, depth), modulator| {
+                            (modulation + signals[modulator], depth + depths[modulator])
+                        });
+                    let (process, transition_size) = wave_functions[osc];
+                    let level = levels[osc][sample];
+                    let phase = self.phase[osc];
+
+                    if is_modulator[osc] {
+                        signals[osc] = if osc == OSC_MOD {
+                            self.process_feedback_modulator(note_data[osc].transition_size)
+                        } else {
+                            process(self, osc, phase, transition_size)
+                        };
+                        depths[osc] = level;
+
+                        self.phase[osc] = math::wrap_phase(if modulated[osc] {
+                            (phase + modulation) * (depth * self.mod_index / OSC_MOD_LEVEL_MODIFIER)
+                        } else {
+                            phase + increments[osc][sample]
+                        });
+                    } else if modulated[osc] {
+                        // The wet and dry signals are folded as one, since together they're what's
+                        // heard of the oscillator.
+                        let (dry, wet) = math::crossfade(depth / OSC_MOD_LEVEL_MODIFIER);
+                        let wet_sample = process(self, osc, phase, transition_size) * wet;
+                        let wet_sample = self.dc_blocker[osc].process(wet_sample);
+
+                        self.phase[osc] = math::wrap_phase(
+                            (phase + modulation)
+                                * (depth * self.mod_index / OSC_MOD_LEVEL_MODIFIER),
+                        );
+                        self.phase[PHASE_DRY + osc] =
+                            math::wrap_phase(self.phase[PHASE_DRY + osc] + increments[osc][sample]);
+
+                        let dry_phase = self.phase[PHASE_DRY + osc];
+                        let dry_sample = process(self, osc, dry_phase, transition_size) * dry;
+                        let wave = self.fold(wet_sample + dry_sample, osc);
+                        self::mix(
+                            frame,
+                            self.pan[osc],
+                            wave * self.velocity[osc]
+                                * self.key_track
+                                * self.gain
+                                * level
+                                * adsr[osc][sample],
+                        );
+                    } else {
+                        let wave = process(self, osc, phase, transition_size);
+                        self::mix(
+                            frame,
+                            self.pan[osc],
+                            self.fold(wave, osc)
+                                * self.velocity[osc]
+                                * self.key_track
+                                * self.gain
+                                * level
+                                * adsr[osc][sample],
+                        );
+                        self.phase[osc] = math::wrap_phase(phase + increments[osc][sample]);
+                    }
                 }
             }
         }
     }
 
+    /// Oscillator 3's waveform with its previous output fed back into its phase. Feedback turns a sine
+    /// modulator into something closer to a saw, the classic way of getting brighter FM tones.
+    fn process_feedback_modulator(&mut self, transition_size: f32) -> f32 {
+        /// Furthest the feedback can push oscillator 3's phase, in cycles. Pushing it further than
+        /// this makes the feedback loop unstable and just turns it into noise.
+        const FEEDBACK_DEPTH: f32 = 0.25;
+
+        let phase = (self.phase[OSC_MOD]
+            + self.fm_feedback_sample * self.fm_feedback * FEEDBACK_DEPTH)
+            .rem_euclid(1.0);
+        let sample = self
+            .waveform(OSC_MOD, phase, transition_size)
+            .clamp(-1.0, 1.0);
+        self.fm_feedback_sample = sample;
+
+        sample
+    }
+
     /// Oscillator 3's signal is filtered with its velocity and ADSR like in subtractive synthesis,
     /// but we're modulating oscillators 1's signal with it. Along with this, we don't increment the
     /// phase of oscillator 3's signal. It's set to a constant value of the sample increment (frequency /
@@ -406,82 +1242,548 @@ impl Key {
     /// This type of synthesis, along with sploinky and skloinky waveforms are the result of incorrect
     /// implementations, in this case, of just regular phase modulation.
     ///
-    /// Unlike phase modulation, we don't mix any dry signal.
+    /// Unlike phase modulation, we don't mix any dry signal. The modulation index and the evil depth
+    /// scale the modulating signal directly, so high values alias hard. Since oscillator 3's ADSR
+    /// shapes the modulating signal too, the depth is only reached while the envelope is fully open.
     fn process_1evil_1sub(
         &mut self,
-        output: &mut [f32],
-        pitch: [usize; OSC_NR],
-        rng: &mut SmallRng,
+        output: &mut [Frame],
+        tuning: Tuning,
         oscs: &[usize],
         note_data: &[NoteData],
     ) {
         for &osc in oscs {
             if osc == 0 {
-                let osc1_note_data = note_data[self.note + pitch[osc]];
-                let mod_note_data = note_data[self.note + pitch[OSC_MOD]];
-
-                for sample in output.iter_mut() {
-                    let sample_dc = (self.process_waveform[osc])(
-                        self,
-                        rng,
-                        osc,
-                        osc1_note_data.transition_size,
-                    ) * self.velocity
-                        * self.levels[osc].process()
-                        * self.adsr[osc].process();
-
-                    *sample += self.dc_blocker[osc].process(sample_dc);
-
-                    self.phase[OSC_MOD] = osc1_note_data.increment;
-                    self.phase[osc] = (self.phase[osc]
-                        + ((self.process_waveform[OSC_MOD])(
-                            self,
-                            rng,
+                let osc1_note_data = self.osc_note_data(note_data, tuning, osc);
+                let mod_note_data = self.osc_note_data(note_data, tuning, OSC_MOD);
+                let (process, transition_size) =
+                    self.wave_function(osc, osc1_note_data.transition_size);
+                let (process_mod, mod_transition_size) =
+                    self.wave_function(OSC_MOD, mod_note_data.transition_size);
+                // Oscillator 3 only modulates when it's meant to be heard.
+                let mod_gate = oscs.contains(&OSC_MOD) as u8 as f32;
+
+                for chunk in output.chunks_mut(OSC_BLOCK_SIZE) {
+                    let mut adsr = [0.0; OSC_BLOCK_SIZE];
+                    let mut levels = [0.0; OSC_BLOCK_SIZE];
+                    let mut mod_adsr = [0.0; OSC_BLOCK_SIZE];
+                    let mut mod_levels = [0.0; OSC_BLOCK_SIZE];
+                    let mut mod_phases = [0.0; OSC_BLOCK_SIZE];
+                    self.adsr_block(osc, &mut adsr[..chunk.len()]);
+                    self.level_block(osc, &mut levels[..chunk.len()]);
+                    self.adsr_block(OSC_MOD, &mut mod_adsr[..chunk.len()]);
+                    self.level_block(OSC_MOD, &mut mod_levels[..chunk.len()]);
+                    // Oscillator 3's phase is its increment, not advanced by it.
+                    let increment = osc1_note_data.increment;
+                    self.increment_block(OSC_MOD, increment, &mut mod_phases[..chunk.len()]);
+
+                    for (sample, frame) in chunk.iter_mut().enumerate() {
+                        let phase = self.phase[osc];
+                        let wave = process(self, osc, phase, transition_size);
+                        let sample_dc = self.fold(wave, osc)
+                            * self.velocity[osc]
+                            * self.key_track
+                            * self.gain
+                            * levels[sample]
+                            * adsr[sample];
+
+                        self::mix(
+                            frame,
+                            self.pan[osc],
+                            self.dc_blocker[osc].process(sample_dc),
+                        );
+
+                        self.phase[OSC_MOD] = mod_phases[sample];
+                        let modulator =
+                            process_mod(self, OSC_MOD, mod_phases[sample], mod_transition_size);
+                        self.phase[osc] = math::wrap_phase(
+                            phase
+                                + modulator
+                                    * self.velocity[OSC_MOD]
+                                    * self.mod_index
+                                    * self.evil_depth
+                                    * mod_levels[sample]
+                                    * mod_gate
+                                    * mod_adsr[sample],
+                        );
+                    }
+                }
+            } else if osc == 1 {
+                let note_data = self.osc_note_data(note_data, tuning, osc);
+                let (process, transition_size) = self.wave_function(osc, note_data.transition_size);
+
+                for chunk in output.chunks_mut(OSC_BLOCK_SIZE) {
+                    let mut adsr = [0.0; OSC_BLOCK_SIZE];
+                    let mut levels = [0.0; OSC_BLOCK_SIZE];
+                    let mut increments = [0.0; OSC_BLOCK_SIZE];
+                    self.adsr_block(osc, &mut adsr[..chunk.len()]);
+                    self.level_block(osc, &mut levels[..chunk.len()]);
+                    self.increment_block(osc, note_data.increment, &mut increments[..chunk.len()]);
+
+                    for (sample, frame) in chunk.iter_mut().enumerate() {
+                        let phase = self.phase[osc];
+                        let wave = process(self, osc, phase, transition_size);
+                        self::mix(
+                            frame,
+                            self.pan[osc],
+                            self.fold(wave, osc)
+                                * self.velocity[osc]
+                                * self.key_track
+                                * self.gain
+                                * levels[sample]
+                                * adsr[sample],
+                        );
+                        self.phase[osc] = math::wrap_phase(phase + increments[sample]);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Oscillators 1 and 3 modulate each other's phase, each one using the other's output from the
+    /// previous sample. Both are heard at their own levels, and their levels and ADSRs also scale how
+    /// much they modulate the other. Muted oscillators neither sound nor modulate.
+    ///
+    /// The feedback loop between the two can run away at high modulation indices, so the amount
+    /// either phase gets pushed by in a single sample is clamped. Past that point the tone just turns
+    /// into noise instead of blowing up.
+    fn process_2cross_1sub(
+        &mut self,
+        output: &mut [Frame],
+        tuning: Tuning,
+        oscs: &[usize],
+        note_data: &[NoteData],
+    ) {
+        /// Furthest the modulation can push an oscillator's phase in a single sample.
+        const MAX_PHASE_OFFSET: f32 = 0.5;
+
+        const OSC_CARRIER: usize = 0;
+
+        let carrier_gate = oscs.contains(&OSC_CARRIER) as u8 as f32;
+        let mod_gate = oscs.contains(&OSC_MOD) as u8 as f32;
+
+        if carrier_gate > 0.0 || mod_gate > 0.0 {
+            let carrier_note_data = self.osc_note_data(note_data, tuning, OSC_CARRIER);
+            let mod_note_data = self.osc_note_data(note_data, tuning, OSC_MOD);
+
+            for frame in output.iter_mut() {
+                let carrier = self.waveform(
+                    OSC_CARRIER,
+                    self.phase[OSC_CARRIER],
+                    carrier_note_data.transition_size,
+                );
+                let carrier = self.fold(carrier, OSC_CARRIER)
+                    * self.velocity[OSC_CARRIER]
+                    * self.key_track
+                    * self.gain
+                    * self.levels[OSC_CARRIER].process()
+                    * self.adsr[OSC_CARRIER].process()
+                    * carrier_gate;
+                let modulator =
+                    self.waveform(OSC_MOD, self.phase[OSC_MOD], mod_note_data.transition_size);
+                let modulator = self.fold(modulator, OSC_MOD)
+                    * self.velocity[OSC_MOD]
+                    * self.key_track
+                    * self.gain
+                    * self.levels[OSC_MOD].process()
+                    * self.adsr[OSC_MOD].process()
+                    * mod_gate;
+
+                let carrier = self.dc_blocker[OSC_CARRIER].process(carrier);
+                let modulator = self.dc_blocker[OSC_MOD].process(modulator);
+                self::mix(frame, self.pan[OSC_CARRIER], carrier);
+                self::mix(frame, self.pan[OSC_MOD], modulator);
+
+                let carrier_offset =
+                    (modulator * self.mod_index).clamp(-MAX_PHASE_OFFSET, MAX_PHASE_OFFSET);
+                let mod_offset =
+                    (carrier * self.mod_index).clamp(-MAX_PHASE_OFFSET, MAX_PHASE_OFFSET);
+
+                self.phase[OSC_CARRIER] = (self.phase[OSC_CARRIER]
+                    + carrier_note_data.increment * self.sweep(OSC_CARRIER)
+                    + carrier_offset)
+                    .rem_euclid(1.0);
+                self.phase[OSC_MOD] = (self.phase[OSC_MOD]
+                    + mod_note_data.increment * self.sweep(OSC_MOD)
+                    + mod_offset)
+                    .rem_euclid(1.0);
+            }
+        }
+
+        if oscs.contains(&1) {
+            let note_data = self.osc_note_data(note_data, tuning, 1);
+
+            for frame in output.iter_mut() {
+                let wave = self.waveform(1, self.phase[1], note_data.transition_size);
+                self::mix(
+                    frame,
+                    self.pan[1],
+                    self.fold(wave, 1)
+                        * self.velocity[1]
+                        * self.key_track
+                        * self.gain
+                        * self.levels[1].process()
+                        * self.adsr[1].process(),
+                );
+                self.phase[1] = (self.phase[1] + note_data.increment * self.sweep(1)) % 1.0;
+            }
+        }
+    }
+
+    /// Hard sync. Oscillator 3 runs as the master, restarting oscillator 1's cycle every time its own
+    /// cycle starts over, so oscillator 1 plays at oscillator 3's pitch while its own pitch shapes the
+    /// timbre. Oscillator 3 isn't heard itself, and only syncs oscillator 1 while it's meant to be
+    /// heard.
+    ///
+    /// Oscillator 1 restarts from wherever it would have got to since oscillator 3 wrapped, rather
+    /// than from the start of its cycle, which keeps the synced pitch steady between samples. The jump
+    /// restarting leaves in its waveform isn't band-limited though, so high oscillator 1 pitches
+    /// alias.
+    fn process_1sync_1sub(
+        &mut self,
+        output: &mut [Frame],
+        tuning: Tuning,
+        oscs: &[usize],
+        note_data: &[NoteData],
+    ) {
+        const OSC_SLAVE: usize = 0;
+
+        for &osc in oscs {
+            if osc == OSC_SLAVE {
+                let slave_note_data = self.osc_note_data(note_data, tuning, osc);
+                let master_note_data = self.osc_note_data(note_data, tuning, OSC_MOD);
+                // Oscillator 3 only syncs when it's meant to be heard.
+                let synced = oscs.contains(&OSC_MOD);
+
+                for frame in output.iter_mut() {
+                    let wave = self.waveform(osc, self.phase[osc], slave_note_data.transition_size);
+                    self::mix(
+                        frame,
+                        self.pan[osc],
+                        self.fold(wave, osc)
+                            * self.velocity[osc]
+                            * self.key_track
+                            * self.gain
+                            * self.levels[osc].process()
+                            * self.adsr[osc].process(),
+                    );
+
+                    let slave_increment = slave_note_data.increment * self.sweep(osc);
+                    self.phase[osc] = (self.phase[osc] + slave_increment) % 1.0;
+
+                    if synced {
+                        let master_increment = master_note_data.increment * self.sweep(OSC_MOD);
+                        let master_phase = self.phase[OSC_MOD] + master_increment;
+
+                        if master_phase >= 1.0 {
+                            // How far into the sample oscillator 3 wrapped, in samples.
+                            let since_wrap = (master_phase - 1.0) / master_increment;
+                            self.phase[osc] = (since_wrap * slave_increment) % 1.0;
+                        }
+
+                        self.phase[OSC_MOD] = master_phase % 1.0;
+                    }
+                }
+            } else if osc == 1 {
+                let note_data = self.osc_note_data(note_data, tuning, osc);
+
+                for frame in output.iter_mut() {
+                    let wave = self.waveform(osc, self.phase[osc], note_data.transition_size);
+                    self::mix(
+                        frame,
+                        self.pan[osc],
+                        self.fold(wave, osc)
+                            * self.velocity[osc]
+                            * self.key_track
+                            * self.gain
+                            * self.levels[osc].process()
+                            * self.adsr[osc].process(),
+                    );
+                    self.phase[osc] =
+                        (self.phase[osc] + note_data.increment * self.sweep(osc)) % 1.0;
+                }
+            }
+        }
+    }
+
+    /// Ring modulation. Oscillator 1's signal is multiplied by oscillator 3's, which swaps its partials
+    /// for the sums and differences of the two oscillators' frequencies. Oscillator 3's level, scaled
+    /// by its velocity, ADSR and the modulation index, crossfades between oscillator 1's dry signal and
+    /// the ring modulated one at equal power, so at a level of 0.0 oscillator 1 plays as it does in
+    /// subtractive synthesis. Oscillator 3 isn't heard itself.
+    fn process_1ring_1sub(
+        &mut self,
+        output: &mut [Frame],
+        tuning: Tuning,
+        oscs: &[usize],
+        note_data: &[NoteData],
+    ) {
+        const OSC_CARRIER: usize = 0;
+
+        for &osc in oscs {
+            if osc == OSC_CARRIER {
+                let carrier_note_data = self.osc_note_data(note_data, tuning, osc);
+                let mod_note_data = self.osc_note_data(note_data, tuning, OSC_MOD);
+                // Oscillator 3 only modulates when it's meant to be heard.
+                let modulated = oscs.contains(&OSC_MOD);
+
+                for frame in output.iter_mut() {
+                    let wave =
+                        self.waveform(osc, self.phase[osc], carrier_note_data.transition_size);
+                    let carrier = self.fold(wave, osc);
+
+                    let sample = if modulated {
+                        let modulator = self.waveform(
                             OSC_MOD,
+                            self.phase[OSC_MOD],
                             mod_note_data.transition_size,
-                        )) * self.velocity
+                        );
+                        let depth = self.velocity[OSC_MOD]
+                            * self.mod_index
                             * self.levels[OSC_MOD].process()
-                            * self.adsr[OSC_MOD].process())
-                        % 1.0;
+                            * self.adsr[OSC_MOD].process();
+                        let (dry, wet) = math::crossfade(depth);
+
+                        self.phase[OSC_MOD] = (self.phase[OSC_MOD]
+                            + mod_note_data.increment * self.sweep(OSC_MOD))
+                            % 1.0;
+
+                        carrier * (dry + wet * modulator)
+                    } else {
+                        carrier
+                    };
+
+                    self::mix(
+                        frame,
+                        self.pan[osc],
+                        sample
+                            * self.velocity[osc]
+                            * self.key_track
+                            * self.gain
+                            * self.levels[osc].process()
+                            * self.adsr[osc].process(),
+                    );
+                    self.phase[osc] =
+                        (self.phase[osc] + carrier_note_data.increment * self.sweep(osc)) % 1.0;
                 }
             } else if osc == 1 {
-                let note_data = note_data[self.note + pitch[osc]];
+                let note_data = self.osc_note_data(note_data, tuning, osc);
+
+                for frame in output.iter_mut() {
+                    let wave = self.waveform(osc, self.phase[osc], note_data.transition_size);
+                    self::mix(
+                        frame,
+                        self.pan[osc],
+                        self.fold(wave, osc)
+                            * self.velocity[osc]
+                            * self.key_track
+                            * self.gain
+                            * self.levels[osc].process()
+                            * self.adsr[osc].process(),
+                    );
+                    self.phase[osc] =
+                        (self.phase[osc] + note_data.increment * self.sweep(osc)) % 1.0;
+                }
+            }
+        }
+    }
+
+    /// Frequency modulation. Oscillator 3 runs at oscillator 1's frequency times the FM ratio, and its
+    /// output, scaled by its velocity, level and ADSR, swings oscillator 1's phase increment up and
+    /// down by the FM depth. Since oscillator 3 follows oscillator 1 at a clean ratio, the sidebands
+    /// land on harmonics of the note instead of drifting with oscillator 3's own pitch. Oscillator 3
+    /// isn't heard itself.
+    ///
+    /// Unlike phase modulation, the modulator bends the frequency rather than offsetting the phase,
+    /// so there's no dry signal to crossfade with.
+    fn process_1fm_1sub(
+        &mut self,
+        output: &mut [Frame],
+        tuning: Tuning,
+        oscs: &[usize],
+        note_data: &[NoteData],
+    ) {
+        const OSC_CARRIER: usize = 0;
 
-                for sample in output.iter_mut() {
-                    *sample +=
-                        (self.process_waveform[osc])(self, rng, osc, note_data.transition_size)
-                            * self.velocity
+        for &osc in oscs {
+            if osc == OSC_CARRIER {
+                let carrier_note_data = self.osc_note_data(note_data, tuning, osc);
+                let mod_transition_size = carrier_note_data.transition_size * self.fm_ratio;
+                // Oscillator 3 only modulates when it's meant to be heard.
+                let mod_gate = oscs.contains(&OSC_MOD) as u8 as f32;
+
+                for frame in output.iter_mut() {
+                    let wave =
+                        self.waveform(osc, self.phase[osc], carrier_note_data.transition_size);
+                    self::mix(
+                        frame,
+                        self.pan[osc],
+                        self.fold(wave, osc)
+                            * self.velocity[osc]
+                            * self.key_track
+                            * self.gain
                             * self.levels[osc].process()
-                            * self.adsr[osc].process();
-                    self.phase[osc] = (self.phase[osc] + note_data.increment) % 1.0;
+                            * self.adsr[osc].process(),
+                    );
+
+                    let modulator =
+                        self.waveform(OSC_MOD, self.phase[OSC_MOD], mod_transition_size)
+                            * self.velocity[OSC_MOD]
+                            * self.levels[OSC_MOD].process()
+                            * self.adsr[OSC_MOD].process()
+                            * mod_gate;
+
+                    // Both oscillators follow oscillator 1's pitch envelope and glide, so the ratio
+                    // holds while they sweep.
+                    let increment = carrier_note_data.increment * self.sweep(osc);
+                    self.phase[OSC_MOD] = (self.phase[OSC_MOD] + increment * self.fm_ratio) % 1.0;
+                    self.phase[osc] = (self.phase[osc]
+                        + increment * (1.0 + self.fm_depth * modulator))
+                        .rem_euclid(1.0);
+                }
+            } else if osc == 1 {
+                let note_data = self.osc_note_data(note_data, tuning, osc);
+
+                for frame in output.iter_mut() {
+                    let wave = self.waveform(osc, self.phase[osc], note_data.transition_size);
+                    self::mix(
+                        frame,
+                        self.pan[osc],
+                        self.fold(wave, osc)
+                            * self.velocity[osc]
+                            * self.key_track
+                            * self.gain
+                            * self.levels[osc].process()
+                            * self.adsr[osc].process(),
+                    );
+                    self.phase[osc] =
+                        (self.phase[osc] + note_data.increment * self.sweep(osc)) % 1.0;
                 }
             }
         }
     }
 
+    /// Runs an oscillator's sample through its wavefolder. A fold of 1.0 passes the sample through as
+    /// is.
+    fn fold(&mut self, sample: f32, osc: usize) -> f32 {
+        if self.fold[osc] > 1.0 {
+            self.fold_dc_blocker[osc].process(math::wavefold(sample * self.fold[osc]))
+        } else {
+            sample
+        }
+    }
+
+    /// Advances the pitch envelope and glide of an oscillator by a sample. Returns the amount by
+    /// which to multiply the phase increment.
+    fn sweep(&mut self, osc: usize) -> f32 {
+        self.pitch_sweep[osc].process() * self.glide[osc].process()
+    }
+
+    /// Looks up the note data of an oscillator for this key, detuned by the oscillator's tuning.
+    /// Oscillator 3 follows oscillator 1 at its ratio instead while its ratio is locked.
+    fn osc_note_data(&self, note_data: &[NoteData], tuning: Tuning, osc: usize) -> NoteData {
+        if let (OSC_MOD, Some(ratio)) = (osc, self.mod_ratio) {
+            let carrier_note_data = self.osc_note_data(note_data, tuning, 0);
+
+            return NoteData {
+                increment: carrier_note_data.increment * ratio,
+                transition_size: carrier_note_data.transition_size * ratio,
+            };
+        }
+
+        let note_data = NoteData::interpolate(
+            note_data,
+            self.note as f32
+                + tuning.pitch[osc]
+                + self.expression_pitch
+                + self.pitch_mod[osc]
+                + self.fine_mod[osc],
+        );
+
+        // Oversampled, the oscillator takes smaller steps through its cycle, which are each a
+        // smaller share of it.
+        let oversample = self.oversample as f32;
+        NoteData {
+            increment: note_data.increment / oversample,
+            transition_size: note_data.transition_size / oversample,
+        }
+    }
+
+    /// Processes an oscillator's waveform at `phase`, which doesn't have to be the phase of the
+    /// oscillator itself. The transition size is widened by the key's polyblep width, up to
+    /// [`TRANSITION_SIZE_MAX`].
+    ///
+    /// Past [`TRANSITION_SIZE_MAX`] the polyblep corrections of a waveform would overlap each other,
+    /// which only happens this close to Nyquist. There the fundamental is all that could be heard of
+    /// the waveform without aliasing anyway, so those waveforms fall back to a sine.
+    fn waveform(&mut self, osc: usize, phase: f32, transition_size: f32) -> f32 {
+        let (process, transition_size) = self.wave_function(osc, transition_size);
+        process(self, osc, phase, transition_size)
+    }
+
+    /// The wave function [`Self::waveform`] processes an oscillator with at a transition size, and
+    /// the transition size it's called with. It stays the same for as long as the transition size
+    /// does, so blocks of samples look it up once ahead of their loop.
+    fn wave_function(&self, osc: usize, transition_size: f32) -> (WaveFunction, f32) {
+        if self.polyblep[osc] && transition_size > TRANSITION_SIZE_MAX {
+            return (Self::process_sine, transition_size);
+        }
+
+        (
+            self.process_waveform[osc],
+            (transition_size * self.blep_width).min(TRANSITION_SIZE_MAX),
+        )
+    }
+
     /// A sine waveform.
-    fn process_sine(&mut self, _rng: &mut SmallRng, osc: usize, _transition_size: f32) -> f32 {
-        (self.phase[osc] * TAU).sin()
+    fn process_sine(&mut self, _osc: usize, phase: f32, _transition_size: f32) -> f32 {
+        (phase * TAU).sin()
     }
 
-    /// A noise waveform tsssssssssssshh.
-    fn process_noise(&mut self, rng: &mut SmallRng, _osc: usize, _transition_size: f32) -> f32 {
-        rng.random_range(-1.0..1.0)
+    /// A sum of harmonics read from the oscillator's wavetable, linearly interpolated between its
+    /// samples.
+    fn process_additive(&mut self, osc: usize, phase: f32, _transition_size: f32) -> f32 {
+        let table = &self.wavetable[osc];
+        let position = phase * ADDITIVE_TABLE_SIZE as f32;
+        let idx = position as usize % ADDITIVE_TABLE_SIZE;
+        let next = (idx + 1) % ADDITIVE_TABLE_SIZE;
+        let fraction = position.fract();
+
+        table[idx] + (table[next] - table[idx]) * fraction
     }
 
-    fn process_triangle_hq(
-        &mut self,
-        _rng: &mut SmallRng,
-        osc: usize,
-        transition_size: f32,
-    ) -> f32 {
-        4.0 * math::integrate_square_wave((self.phase[osc] + 0.25).rem_euclid(1.0), transition_size)
-            - 1.0
+    /// A noise waveform tsssssssssssshh. Its color is tilted by the noise color parameter.
+    fn process_noise(&mut self, osc: usize, _phase: f32, _transition_size: f32) -> f32 {
+        let white = self.rng.random_range(-1.0..1.0);
+        self.noise_tilt[osc].process(white, self.noise_color)
+    }
+
+    /// Pink noise, white noise with its highs rolled off by about 3 dB per octave. The noise color
+    /// tilts it just the same as it does white noise.
+    fn process_pink_noise(&mut self, osc: usize, _phase: f32, _transition_size: f32) -> f32 {
+        let white = self.rng.random_range(-1.0..1.0);
+        let pink = self.pink_filter[osc].process(white);
+        self.noise_tilt[osc].process(pink, self.noise_color)
+    }
+
+    /// Stepped noise. A new random value is drawn every time the phase wraps, and held for the rest
+    /// of the cycle, so the steps follow the pitch of the note.
+    fn process_sample_hold(&mut self, osc: usize, phase: f32, _transition_size: f32) -> f32 {
+        if phase < self.held_phase[osc] {
+            self.held_sample[osc] = self.rng.random_range(-1.0..1.0);
+        }
+
+        self.held_phase[osc] = phase;
+        self.held_sample[osc]
+    }
+
+    fn process_triangle_hq(&mut self, _osc: usize, phase: f32, transition_size: f32) -> f32 {
+        4.0 * math::integrate_square_wave((phase + 0.25).rem_euclid(1.0), transition_size) - 1.0
     }
 
     /// An naive aliasing triangle waveform.
-    fn process_triangle(&mut self, _rng: &mut SmallRng, osc: usize, _transition_size: f32) -> f32 {
-        let p = self.phase[osc] % 1.0;
+    fn process_triangle(&mut self, _osc: usize, phase: f32, _transition_size: f32) -> f32 {
+        let p = phase % 1.0;
 
         if p < 0.25 {
             4.0 * p
@@ -493,30 +1795,61 @@ impl Key {
     }
 
     /// A polyblep square waveform.
-    fn process_square_hq(&mut self, _rng: &mut SmallRng, osc: usize, transition_size: f32) -> f32 {
-        let p = self.phase[osc] % 1.0;
+    fn process_square_hq(&mut self, _osc: usize, phase: f32, transition_size: f32) -> f32 {
+        let p = phase % 1.0;
 
         (if p < 0.5 { 1.0 } else { -1.0 })
-            + math::polyblep((((self.phase[osc] + 0.5) % 1.0) - 0.5) / transition_size)
+            + math::polyblep((((phase + 0.5) % 1.0) - 0.5) / transition_size)
             - math::polyblep((p - 0.5) / transition_size)
     }
 
     /// An naive aliasing square waveform.
-    fn process_square(&mut self, _rng: &mut SmallRng, osc: usize, _transition_size: f32) -> f32 {
-        let p = self.phase[osc] % 1.0;
+    fn process_square(&mut self, _osc: usize, phase: f32, _transition_size: f32) -> f32 {
+        let p = phase % 1.0;
         if p < 0.5 { 1.0 } else { -1.0 }
     }
 
+    /// A polyblep pulse waveform. When the falling transition is close enough to the end of the
+    /// cycle, its distance is wrapped around so the correction carries over into the next cycle.
+    ///
+    /// The pulse's DC offset gets taken out, which is 0.0 at a width of 0.5. Nothing gets wrapped at
+    /// that width either, so it comes out exactly the same as [`Key::process_square_hq`] there.
+    fn process_pulse_hq(&mut self, _osc: usize, phase: f32, transition_size: f32) -> f32 {
+        let p = phase % 1.0;
+        let width = self.pulse_width;
+        let fall = p - width;
+        let fall = if width + transition_size > 1.0 && fall < -0.5 {
+            fall + 1.0
+        } else if width < transition_size && fall >= 0.5 {
+            fall - 1.0
+        } else {
+            fall
+        };
+
+        (if p < width { 1.0 } else { -1.0 })
+            + math::polyblep((((phase + 0.5) % 1.0) - 0.5) / transition_size)
+            - math::polyblep(fall / transition_size)
+            - (2.0 * width - 1.0)
+    }
+
+    /// An naive aliasing pulse waveform, without its DC offset like [`Key::process_pulse_hq`].
+    fn process_pulse(&mut self, _osc: usize, phase: f32, _transition_size: f32) -> f32 {
+        let p = phase % 1.0;
+        let width = self.pulse_width;
+
+        (if p < width { 1.0 } else { -1.0 }) - (2.0 * width - 1.0)
+    }
+
     /// A polyblep saw waveform.
-    fn process_saw_hq(&mut self, _rng: &mut SmallRng, osc: usize, transition_size: f32) -> f32 {
-        let p = self.phase[osc] % 1.0;
+    fn process_saw_hq(&mut self, _osc: usize, phase: f32, transition_size: f32) -> f32 {
+        let p = phase % 1.0;
 
-        2.0 * p - 1.0 - math::polyblep((((self.phase[osc] + 0.5) % 1.0) - 0.5) / transition_size)
+        2.0 * p - 1.0 - math::polyblep((((phase + 0.5) % 1.0) - 0.5) / transition_size)
     }
 
     /// An naive aliasing saw waveform.
-    fn process_saw(&mut self, _rng: &mut SmallRng, osc: usize, _transition_size: f32) -> f32 {
-        let p = self.phase[osc] % 1.0;
+    fn process_saw(&mut self, _osc: usize, phase: f32, _transition_size: f32) -> f32 {
+        let p = phase % 1.0;
 
         2.0 * p - 1.0
     }
@@ -526,12 +1859,12 @@ impl Key {
     /// at the wrong point in the wave. Name was chosen arbitrarilly because it sounds cute.
     ///
     /// Since the waveform generated by this is so incorrect, we apply a DC blocking filter.
-    fn process_sploinky(&mut self, _rng: &mut SmallRng, osc: usize, transition_size: f32) -> f32 {
-        let p = self.phase[osc] % 1.0;
+    fn process_sploinky(&mut self, osc: usize, phase: f32, transition_size: f32) -> f32 {
+        let p = phase % 1.0;
 
         self.dc_blocker[osc].process(
             (if p < 0.5 { 1.0 } else { -1.0 }
-                + math::polyblep(((self.phase[osc] + 0.5) % 0.5) - transition_size)
+                + math::polyblep(((phase + 0.5) % 0.5) - transition_size)
                 - math::polyblep((p - 0.5) / transition_size))
                 / 2.0,
         )
@@ -542,11 +1875,10 @@ impl Key {
     /// at the wrong point in the wave. Name was chosen arbitrarilly because it sounds cute.
     ///
     /// Since the waveform generated by this is so incorrect, we apply a DC blocking filter.
-    fn process_skloinky(&mut self, _rng: &mut SmallRng, osc: usize, transition_size: f32) -> f32 {
-        let p = self.phase[osc] % 1.0;
+    fn process_skloinky(&mut self, osc: usize, phase: f32, transition_size: f32) -> f32 {
+        let p = phase % 1.0;
         self.dc_blocker[osc].process(
-            (2.0 * p - 1.0 - math::polyblep(((self.phase[osc] + 0.5) % 0.5) - transition_size))
-                / 2.0,
+            (2.0 * p - 1.0 - math::polyblep(((phase + 0.5) % 0.5) - transition_size)) / 2.0,
         )
     }
 }
@@ -558,8 +1890,8 @@ pub struct NoteData {
 }
 
 impl NoteData {
-    pub fn new(sample_rate: f32, note: f32, n_tet: f32) -> Self {
-        let frequency = 2.0f32.powf((note - 69.0) / n_tet) * 440.0;
+    pub fn new(sample_rate: f32, note: f32, n_tet: f32, concert_pitch: f32) -> Self {
+        let frequency = Self::frequency(note, n_tet, concert_pitch);
         let increment = frequency / sample_rate;
         let transition_size = 2.0 / (sample_rate / frequency);
 
@@ -568,43 +1900,315 @@ impl NoteData {
             transition_size,
         }
     }
+
+    /// Note data for a fractional position in the note data table. Positions between two entries are
+    /// interpolated exponentially, which is exact for an equal temperament, and positions past the
+    /// table are clamped to its bounds.
+    ///
+    /// This is what every pitch finer than a step goes through. It costs a `powf` per oscillator per
+    /// block, rather than per sample.
+    pub fn interpolate(note_data: &[NoteData], note: f32) -> Self {
+        let note = note.clamp(0.0, (note_data.len() - 1) as f32);
+        let below = note_data[note as usize];
+        let Some(above) = note_data.get(note as usize + 1) else {
+            return below;
+        };
+
+        let ratio = (above.increment / below.increment).powf(note.fract());
+        Self {
+            increment: below.increment * ratio,
+            transition_size: below.transition_size * ratio,
+        }
+    }
+
+    /// Frequency in Hz of a note in an n-TET temperament, with A4 tuned to `concert_pitch`.
+    pub fn frequency(note: f32, n_tet: f32, concert_pitch: f32) -> f32 {
+        2.0f32.powf((note - REFERENCE_NOTE) / n_tet) * concert_pitch
+    }
+}
+
+/// The pitch of every oscillator during a block, as set by the pitch parameters.
+#[derive(Clone, Copy)]
+pub struct Tuning {
+    /// Offset into the note data table per oscillator, in steps of the temperament. The fractional
+    /// part is for pitch changes finer than a step.
+    pub pitch: [f32; OSC_NR],
 }
 
 pub struct Keys {
     alive_keys: ArrayVec<usize, KEYS_NR>,
+    /// The notes held down and the velocities they were played with, the most recently pressed one
+    /// last. Mono goes back to the note before when the one playing is let go of.
+    held_notes: ArrayVec<(usize, u8), KEYS_NR>,
+    /// The note the single voice is playing in mono.
+    mono_note: Option<usize>,
     keys: [Key; KEYS_NR],
+    /// The most recently pressed note, which new notes glide from.
+    last_note: Option<usize>,
 }
 
 impl Keys {
     pub fn new(sample_rate: f32) -> Self {
         Self {
             alive_keys: ArrayVec::new(),
+            held_notes: ArrayVec::new(),
+            mono_note: None,
             keys: std::array::from_fn(move |note| Key::new(sample_rate, note)),
+            last_note: None,
+        }
+    }
+
+    pub fn on(&mut self, note: usize, velocity: u8, shared: &Fox3oscShared) {
+        debug_assert!(note < KEYS_NR);
+
+        if velocity == 0 {
+            return self.release(note, shared);
+        }
+
+        // Pressing a note which is still held moves it to the top.
+        self.held_notes.retain(|&mut (held, _)| held != note);
+        self.held_notes.push((note, velocity));
+
+        let poly_mode = shared.get_poly_mode();
+        if let (PolyMode::Legato, Some(playing)) = (poly_mode, self.mono_note)
+            && playing != note
+            && self.keys[playing].is_held()
+        {
+            return self.move_voice(playing, note, shared);
+        }
+
+        self.play(note, velocity, poly_mode, shared)
+    }
+
+    /// Starts the key playing `note`, making room for it first if it needs any.
+    fn play(&mut self, note: usize, velocity: u8, poly_mode: PolyMode, shared: &Fox3oscShared) {
+        let glide_from = match shared.get_glide().mode {
+            GlideMode::Off => None,
+            GlideMode::Always => self.last_note,
+            GlideMode::Legato => self.last_note.filter(|_| {
+                self.alive_keys
+                    .iter()
+                    .any(|&other| other != note && self.keys[other].is_held())
+            }),
+        };
+
+        if !matches!(poly_mode, PolyMode::Poly) {
+            // Only the one voice sounds in mono.
+            for &other in &self.alive_keys {
+                if other != note && self.keys[other].is_voice() {
+                    self.keys[other].fade_out();
+                }
+            }
+
+            self.mono_note = Some(note);
+        } else if !self.keys[note].is_voice() {
+            // A note which is already a voice plays on its own key again, so it never steals
+            // another.
+            let max_voices = shared.get_max_voices() as usize;
+            while self.voices_besides(note) >= max_voices {
+                self.steal(note);
+            }
+        }
+
+        self.keys[note].on(velocity, glide_from, shared);
+        self.last_note = Some(note);
+
+        // The keys are kept in the order they were last played in, so the oldest comes first.
+        if let Some(index) = self.alive_keys.iter().position(|&other| other == note) {
+            self.alive_keys.remove(index);
+        }
+
+        // SAFETY:
+        // We check whether note is less than KEYS_NR, and take note out of the vector if it was
+        // already in it. Therefore, this will never push note if the capacity isn't sufficient.
+        unsafe { self.alive_keys.push_unchecked(note) };
+    }
+
+    /// Hands the mono voice sounding `from` over to `to` without retriggering it.
+    fn move_voice(&mut self, from: usize, to: usize, shared: &Fox3oscShared) {
+        // Whatever's left of the key playing `to` from before gets cut, for the voice to take its
+        // place.
+        if let Some(index) = self.alive_keys.iter().position(|&other| other == to) {
+            self.keys[to].end();
+            self.alive_keys.remove(index);
+        }
+
+        // The keys' frequencies come from their notes, so both get swapped back after the keys are.
+        self.keys.swap(from, to);
+        self.keys[from].note = from;
+        self.keys[to].repitch(to, shared);
+        for alive in &mut self.alive_keys {
+            if *alive == from {
+                *alive = to;
+            }
+        }
+
+        self.mono_note = Some(to);
+        self.last_note = Some(to);
+    }
+
+    pub fn release(&mut self, note: usize, shared: &Fox3oscShared) {
+        self.release_with_velocity(note, 0, 0.0, shared)
+    }
+
+    /// Takes `note` off the held notes without releasing it, for when the sustain pedal keeps it
+    /// sounding.
+    pub fn let_go(&mut self, note: usize) {
+        self.held_notes.retain(|&mut (held, _)| held != note);
+    }
+
+    /// Number of keys counting towards the voice limit, leaving out the key playing `note`.
+    fn voices_besides(&self, note: usize) -> usize {
+        self.alive_keys
+            .iter()
+            .filter(|&&other| other != note && self.keys[other].is_voice())
+            .count()
+    }
+
+    /// Fades out a voice to make room for `note`. Released keys are quieter than held ones and about
+    /// to end anyway, so they go first, and the oldest one goes first among those.
+    fn steal(&mut self, note: usize) {
+        let victim = self
+            .alive_keys
+            .iter()
+            .copied()
+            .filter(|&other| other != note && self.keys[other].is_voice())
+            .min_by_key(|&other| self.keys[other].is_held());
+
+        if let Some(victim) = victim {
+            self.keys[victim].fade_out();
         }
     }
 
-    pub fn on(
+    /// Releases the key playing `note`, with the note-off's velocity shortening its release by
+    /// `amount`. In mono, letting go of the note the voice is playing goes back to the most recently
+    /// pressed note still held, if there is one. Notes the voice isn't playing only come off the
+    /// held notes.
+    pub fn release_with_velocity(
         &mut self,
         note: usize,
         velocity: u8,
+        amount: f32,
         shared: &Fox3oscShared,
-        rng: &mut SmallRng,
-    ) -> Result<(), PluginError> {
-        debug_assert!(note < KEYS_NR);
+    ) {
+        self.let_go(note);
+
+        let poly_mode = shared.get_poly_mode();
+        if !matches!(poly_mode, PolyMode::Poly)
+            && self.mono_note == Some(note)
+            && let Some(&(previous, previous_velocity)) = self.held_notes.last()
+        {
+            return match poly_mode {
+                PolyMode::Legato if self.keys[note].is_held() => {
+                    self.move_voice(note, previous, shared)
+                }
+                _ => self.play(previous, previous_velocity, poly_mode, shared),
+            };
+        }
+
+        // Releasing a key which isn't sounding doesn't do anything, so this is safe for the notes
+        // the mono voice isn't playing too.
+        self.keys[note].release_with_velocity(velocity, amount);
+    }
+
+    /// Ends the key playing `note` right away and forgets it was held.
+    pub fn end(&mut self, note: usize) {
+        self.let_go(note);
+        if let Some(index) = self.alive_keys.iter().position(|&other| other == note) {
+            self.keys[note].end();
+            self.alive_keys.remove(index);
+        }
+    }
+
+    /// Releases every key and forgets every held note.
+    pub fn release_all(&mut self) {
+        self.held_notes.clear();
+        self.for_each(Key::release);
+    }
+
+    /// Fades out every key and forgets every held note.
+    pub fn fade_out_all(&mut self) {
+        self.held_notes.clear();
+        self.for_each(Key::fade_out);
+    }
+
+    /// Forget the most recently pressed note, so the next note starts right at its pitch instead of
+    /// gliding from a note played before the keys went silent.
+    pub fn forget_last_note(&mut self) {
+        self.last_note = None;
+    }
 
-        self.keys[note].on(velocity, shared, rng)?;
-        if !self.alive_keys.contains(&note) {
-            // SAFETY:
-            // We check both whether note is less than KEYS_NR as well as whether note is already in
-            // the vector. Therefore, this will never push note if the capacity isn't sufficient.
-            unsafe { self.alive_keys.push_unchecked(note) };
+    /// Applies polyphonic aftertouch to the key playing `note`, if it's sounding.
+    pub fn pressure(&mut self, note: usize, pressure: u8, amount: f32) {
+        let key = &mut self.keys[note];
+        if key.is_on() {
+            key.set_pressure(pressure, amount);
         }
+    }
 
-        Ok(())
+    /// Sets the id of the CLAP note `note` is playing, for note expressions aimed at the id to find
+    /// its key.
+    pub fn set_note_id(&mut self, note: usize, note_id: u32) {
+        self.keys[note].note_id = Some(note_id);
     }
 
-    pub fn release(&mut self, note: usize) {
-        self.keys[note].release();
+    /// Applies a note expression to the sounding key it's aimed at, by its note id if the host
+    /// gave one, or by its note otherwise. Expressions aimed at keys which aren't sounding are
+    /// dropped.
+    pub fn expression<F>(&mut self, note: Option<usize>, note_id: Option<u32>, f: F)
+    where
+        F: FnOnce(&mut Key),
+    {
+        let note = match note_id {
+            Some(note_id) => self
+                .alive_keys
+                .iter()
+                .copied()
+                .find(|&alive| self.keys[alive].note_id == Some(note_id)),
+            None => note,
+        };
+
+        if let Some(note) = note
+            && self.keys[note].is_on()
+        {
+            f(&mut self.keys[note]);
+        }
+    }
+
+    /// Whether any sounding key's own modulation brings an oscillator's level up.
+    pub fn level_modulated(&self, osc: usize) -> bool {
+        self.alive_keys
+            .iter()
+            .any(|&note| self.keys[note].is_on() && self.keys[note].level_mod[osc] > 0.0)
+    }
+
+    /// Applies channel aftertouch to every sounding key.
+    pub fn channel_pressure(&mut self, pressure: u8, amount: f32) {
+        self.for_each(|key| key.set_pressure(pressure, amount));
+    }
+
+    /// Number of keys currently sounding.
+    pub fn voice_count(&self) -> usize {
+        self.alive_keys
+            .iter()
+            .filter(|&&note| self.keys[note].is_on())
+            .count()
+    }
+
+    /// Silences every key at once, without fading them out, and reseeds their random number
+    /// generators so playing starts over from the same random numbers. Only for when the audio has
+    /// stopped and a cut can't be heard.
+    pub fn end_all(&mut self) {
+        for &note in &self.alive_keys {
+            self.keys[note].end();
+        }
+        self.alive_keys.clear();
+        self.held_notes.clear();
+        self.mono_note = None;
+
+        for (note, key) in self.keys.iter_mut().enumerate() {
+            key.rng = Key::seeded_rng(note);
+        }
     }
 
     pub fn for_each<F>(&mut self, mut f: F)
@@ -625,3 +2229,529 @@ impl Keys {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        consts::{PITCH_AMOUNT, REFERENCE_FREQUENCY},
+        presets::INIT,
+        shared::{PitchEnvelope, Temperament, Unison},
+        state::PatchState,
+    };
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    /// The note data of every note of a plugin, as the audio processor works it out.
+    fn note_data(shared: &Fox3oscShared) -> Vec<NoteData> {
+        (0..shared.notes_nr)
+            .map(|note| {
+                NoteData::new(
+                    SAMPLE_RATE,
+                    note as f32 - shared.pitch_amount as f32,
+                    shared.n_tet(),
+                    REFERENCE_FREQUENCY,
+                )
+            })
+            .collect()
+    }
+
+    /// The tuning of a plugin's pitch parameters, with none of the master tuning or bend.
+    fn tuning(shared: &Fox3oscShared) -> Tuning {
+        Tuning {
+            pitch: shared.get_pitch().map(|pitch| pitch as f32),
+        }
+    }
+
+    /// A plugin playing `patch`, and a key it's playing `note` on at full velocity.
+    fn played_key(patch: PatchState, note: usize) -> (Fox3oscShared, Key) {
+        let shared = Fox3oscShared::new(Temperament::Tet12);
+        shared.apply_patch_state(&patch);
+
+        let mut key = Key::new(SAMPLE_RATE, note);
+        key.on(127, None, &shared);
+        (shared, key)
+    }
+
+    /// What the oscillators were rendered with before they were rendered in blocks, one whole
+    /// sample at a time and wrapping their phases with a modulo. The block rendering has to come
+    /// out the same.
+    impl Key {
+        fn process_oscs_per_sample(
+            &mut self,
+            output: &mut [Frame],
+            tuning: Tuning,
+            oscs: &[usize],
+            note_data: &[NoteData],
+        ) {
+            match self.modulation {
+                Modulation::None => self.process_3sub_per_sample(output, tuning, oscs, note_data),
+                Modulation::Phase => self.process_pm_per_sample(output, tuning, oscs, note_data),
+                Modulation::Evil => self.process_evil_per_sample(output, tuning, oscs, note_data),
+                // The other modulations render a sample at a time already, so they have nothing to
+                // be compared against.
+                _ => unreachable!("sync, ring, cross and FM modulation render per sample"),
+            }
+        }
+
+        fn process_3sub_per_sample(
+            &mut self,
+            output: &mut [Frame],
+            tuning: Tuning,
+            oscs: &[usize],
+            note_data: &[NoteData],
+        ) {
+            for &osc in oscs {
+                let note_data = self.osc_note_data(note_data, tuning, osc);
+                let sub = osc == 0 && self.sub_level > 0.0;
+                let unison = self.unison[osc] > 1;
+
+                for frame in output.iter_mut() {
+                    let wave = if unison {
+                        self.unison_waveform(osc, note_data.transition_size)
+                    } else {
+                        self.waveform(osc, self.phase[osc], note_data.transition_size)
+                    };
+                    let adsr = self.adsr[osc].process();
+                    self::mix(
+                        frame,
+                        self.pan[osc],
+                        self.fold(wave, osc)
+                            * self.velocity[osc]
+                            * self.key_track
+                            * self.gain
+                            * self.levels[osc].process()
+                            * adsr,
+                    );
+
+                    let increment = note_data.increment * self.sweep(osc);
+                    if unison {
+                        let count = self.unison[osc];
+                        for (phase, ratio) in self.unison_phase[osc][..count]
+                            .iter_mut()
+                            .zip(self.unison_ratios)
+                        {
+                            *phase = (*phase + increment * ratio) % 1.0;
+                        }
+                    }
+
+                    if sub {
+                        self::mix(
+                            frame,
+                            self.pan[osc],
+                            (self.phase[PHASE_SUB] * TAU).sin()
+                                * self.velocity[osc]
+                                * self.key_track
+                                * self.gain
+                                * self.sub_level
+                                * adsr,
+                        );
+                        self.phase[PHASE_SUB] = (self.phase[PHASE_SUB] + increment / 2.0) % 1.0;
+                    }
+
+                    self.phase[osc] = (self.phase[osc] + increment) % 1.0;
+                }
+            }
+        }
+
+        fn process_pm_per_sample(
+            &mut self,
+            output: &mut [Frame],
+            tuning: Tuning,
+            oscs: &[usize],
+            note_data: &[NoteData],
+        ) {
+            const OSC_MOD_LEVEL_MODIFIER: f32 = 100.0 / 48.0;
+
+            let routing = self.algorithm.routing();
+            let note_data: [NoteData; OSC_NR] =
+                std::array::from_fn(|osc| self.osc_note_data(note_data, tuning, osc));
+            let active: [bool; OSC_NR] = std::array::from_fn(|osc| oscs.contains(&osc));
+            let is_modulator: [bool; OSC_NR] =
+                std::array::from_fn(|osc| routing.iter().any(|modulators| modulators[osc]));
+
+            for frame in output.iter_mut() {
+                let mut signals = [0.0; OSC_NR];
+                let mut depths = [0.0; OSC_NR];
+
+                for osc in (0..OSC_NR).rev() {
+                    if !active[osc] {
+                        continue;
+                    }
+
+                    let modulated =
+                        (0..OSC_NR).any(|modulator| routing[osc][modulator] && active[modulator]);
+                    let (modulation, depth) = (0..OSC_NR)
+                        .filter(|&modulator| routing[osc][modulator])
+                        .fold((0.0, 0.0), |(modulation, depth), modulator| {
+                            (modulation + signals[modulator], depth + depths[modulator])
+                        });
+                    let transition_size = note_data[osc].transition_size;
+                    let level = self.levels[osc].process();
+
+                    if is_modulator[osc] {
+                        signals[osc] = if osc == OSC_MOD {
+                            self.process_feedback_modulator(transition_size)
+                        } else {
+                            self.waveform(osc, self.phase[osc], transition_size)
+                        };
+                        depths[osc] = level;
+
+                        self.phase[osc] = if modulated {
+                            ((self.phase[osc] + modulation)
+                                * (depth * self.mod_index / OSC_MOD_LEVEL_MODIFIER))
+                                % 1.0
+                        } else {
+                            (self.phase[osc] + note_data[osc].increment * self.sweep(osc)) % 1.0
+                        };
+                    } else if modulated {
+                        let adsr = self.adsr[osc].process();
+                        let (dry, wet) = math::crossfade(depth / OSC_MOD_LEVEL_MODIFIER);
+                        let wet_sample = self.waveform(osc, self.phase[osc], transition_size) * wet;
+                        let wet_sample = self.dc_blocker[osc].process(wet_sample);
+
+                        self.phase[osc] = ((self.phase[osc] + modulation)
+                            * (depth * self.mod_index / OSC_MOD_LEVEL_MODIFIER))
+                            % 1.0;
+                        self.phase[PHASE_DRY + osc] = (self.phase[PHASE_DRY + osc]
+                            + note_data[osc].increment * self.sweep(osc))
+                            % 1.0;
+
+                        let dry_sample =
+                            self.waveform(osc, self.phase[PHASE_DRY + osc], transition_size) * dry;
+                        let wave = self.fold(wet_sample + dry_sample, osc);
+                        self::mix(
+                            frame,
+                            self.pan[osc],
+                            wave * self.velocity[osc] * self.key_track * self.gain * level * adsr,
+                        );
+                    } else {
+                        let wave = self.waveform(osc, self.phase[osc], transition_size);
+                        self::mix(
+                            frame,
+                            self.pan[osc],
+                            self.fold(wave, osc)
+                                * self.velocity[osc]
+                                * self.key_track
+                                * self.gain
+                                * level
+                                * self.adsr[osc].process(),
+                        );
+                        self.phase[osc] =
+                            (self.phase[osc] + note_data[osc].increment * self.sweep(osc)) % 1.0;
+                    }
+                }
+            }
+        }
+
+        fn process_evil_per_sample(
+            &mut self,
+            output: &mut [Frame],
+            tuning: Tuning,
+            oscs: &[usize],
+            note_data: &[NoteData],
+        ) {
+            for &osc in oscs {
+                if osc == 0 {
+                    let osc1_note_data = self.osc_note_data(note_data, tuning, osc);
+                    let mod_note_data = self.osc_note_data(note_data, tuning, OSC_MOD);
+                    let mod_gate = oscs.contains(&OSC_MOD) as u8 as f32;
+
+                    for frame in output.iter_mut() {
+                        let wave =
+                            self.waveform(osc, self.phase[osc], osc1_note_data.transition_size);
+                        let sample_dc = self.fold(wave, osc)
+                            * self.velocity[osc]
+                            * self.key_track
+                            * self.gain
+                            * self.levels[osc].process()
+                            * self.adsr[osc].process();
+
+                        self::mix(
+                            frame,
+                            self.pan[osc],
+                            self.dc_blocker[osc].process(sample_dc),
+                        );
+
+                        self.phase[OSC_MOD] = osc1_note_data.increment * self.sweep(OSC_MOD);
+                        self.phase[osc] = (self.phase[osc]
+                            + self.waveform(
+                                OSC_MOD,
+                                self.phase[OSC_MOD],
+                                mod_note_data.transition_size,
+                            ) * self.velocity[OSC_MOD]
+                                * self.mod_index
+                                * self.evil_depth
+                                * self.levels[OSC_MOD].process()
+                                * mod_gate
+                                * self.adsr[OSC_MOD].process())
+                            % 1.0;
+                    }
+                } else if osc == 1 {
+                    let note_data = self.osc_note_data(note_data, tuning, osc);
+
+                    for frame in output.iter_mut() {
+                        let wave = self.waveform(osc, self.phase[osc], note_data.transition_size);
+                        self::mix(
+                            frame,
+                            self.pan[osc],
+                            self.fold(wave, osc)
+                                * self.velocity[osc]
+                                * self.key_track
+                                * self.gain
+                                * self.levels[osc].process()
+                                * self.adsr[osc].process(),
+                        );
+                        self.phase[osc] =
+                            (self.phase[osc] + note_data.increment * self.sweep(osc)) % 1.0;
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dc_blocker_settles_at_exactly_zero_after_a_signal() {
+        let mut dc_blocker = DCBlocker::new(SAMPLE_RATE);
+        for sample in 0..4800 {
+            dc_blocker.process((sample as f32 * 0.05).sin() + 0.3);
+        }
+        assert_ne!(dc_blocker.y, 0.0);
+
+        // Two seconds of silence is far longer than the state takes to fall under the threshold.
+        for _ in 0..2 * SAMPLE_RATE as usize {
+            dc_blocker.process(0.0);
+        }
+        assert_eq!(dc_blocker.y, 0.0);
+        assert_eq!(dc_blocker.x, 0.0);
+    }
+
+    #[test]
+    fn note_on_with_velocity_zero_releases_rather_than_ends() {
+        let (shared, mut key) = self::played_key(INIT, 60);
+        key.on(0, None, &shared);
+        assert!(
+            key.adsr
+                .iter()
+                .all(|adsr| matches!(adsr.state, ADSRState::Release(_)))
+        );
+
+        // Through the keyboard, the same as a note-off.
+        let mut keys = Keys::new(SAMPLE_RATE);
+        keys.on(60, 127, &shared);
+        keys.on(60, 0, &shared);
+        assert!(
+            keys.keys[60]
+                .adsr
+                .iter()
+                .all(|adsr| matches!(adsr.state, ADSRState::Release(_)))
+        );
+    }
+
+    #[test]
+    fn octaves_of_53_tet_are_exactly_2_to_1() {
+        assert_eq!(
+            NoteData::frequency(REFERENCE_NOTE + 53.0, 53.0, REFERENCE_FREQUENCY),
+            2.0 * REFERENCE_FREQUENCY
+        );
+
+        // Every octave across the whole note data table of a 53-TET build, which spans a lot more
+        // notes than a 12-TET one does.
+        let shared = Fox3oscShared::new(Temperament::Tet53);
+        let note_data = self::note_data(&shared);
+        for (below, above) in note_data.iter().zip(&note_data[53..]) {
+            let ratio = above.increment / below.increment;
+            assert!((ratio - 2.0).abs() < 1e-5, "{ratio}");
+        }
+    }
+
+    #[test]
+    fn longer_releases_ring_on_after_shorter_ones_end() {
+        let patch = PatchState {
+            envelope: [
+                Envelope::new(0.01, 0.1, 0.8, 0.05),
+                Envelope::new(0.01, 0.1, 0.8, 0.5),
+                Envelope::new(0.01, 0.1, 0.8, 0.05),
+            ],
+            levels: [1.0, 1.0, 0.0],
+            ..INIT
+        };
+        let (shared, mut key) = self::played_key(patch, 57);
+        let note_data = self::note_data(&shared);
+        let tuning = self::tuning(&shared);
+
+        let mut output = vec![[0.0; CHANNEL_NR]; 4800];
+        key.process_oscs(&mut output, tuning, &[0, 1], &note_data);
+        key.release();
+
+        // A fifth of a second in, oscillator 1's release is long over, and oscillator 2's is still
+        // going.
+        let mut output = vec![[0.0; CHANNEL_NR]; 9600];
+        key.process_oscs(&mut output, tuning, &[0, 1], &note_data);
+        assert!(key.adsr[0].state == ADSRState::Ended);
+        assert!(key.is_on());
+        let tail = &output[output.len() - 480..];
+        assert!(tail.iter().flatten().any(|&sample| sample != 0.0));
+
+        // Once oscillator 2's release is over too, the key ends.
+        let mut output = vec![[0.0; CHANNEL_NR]; 24000];
+        key.process_oscs(&mut output, tuning, &[0, 1], &note_data);
+        assert!(!key.is_on());
+    }
+
+    #[test]
+    fn keys_with_nothing_rendered_end() {
+        let (shared, mut key) = self::played_key(INIT, 57);
+        let note_data = self::note_data(&shared);
+
+        let mut output = vec![[0.0; CHANNEL_NR]; 480];
+        key.process_oscs(&mut output, self::tuning(&shared), &[], &note_data);
+        assert!(!key.is_on());
+    }
+
+    #[test]
+    fn block_rendering_matches_rendering_per_sample() {
+        for modulation in [Modulation::None, Modulation::Phase, Modulation::Evil] {
+            for waveform in [Waveform::Sine, Waveform::Saw, Waveform::Square] {
+                let patch = PatchState {
+                    waveforms: [waveform; OSC_NR],
+                    levels: [1.0, 0.6, 0.8],
+                    modulation,
+                    pitch: [PITCH_AMOUNT, PITCH_AMOUNT + 7.0, PITCH_AMOUNT + 12.0],
+                    pitch_envelope: PitchEnvelope {
+                        amount: [0.5, -0.3, 0.2],
+                        time: 0.01,
+                    },
+                    fold: [1.0, 1.5, 2.0],
+                    sub_level: 0.5,
+                    unison: Unison {
+                        count: 3,
+                        detune: 0.2,
+                    },
+                    ..INIT
+                };
+                let (shared, mut blocks) = self::played_key(patch, 57);
+                let (_, mut per_sample) = self::played_key(patch, 57);
+                let note_data = self::note_data(&shared);
+                let tuning = self::tuning(&shared);
+
+                // Not a whole number of blocks, so the last one's cut short.
+                let mut expected = vec![[0.0; CHANNEL_NR]; 1000];
+                let mut output = expected.clone();
+                per_sample.process_oscs_per_sample(&mut expected, tuning, &[0, 1, 2], &note_data);
+                blocks.process_oscs(&mut output, tuning, &[0, 1, 2], &note_data);
+
+                for (frame, (sample, expected)) in output.iter().zip(&expected).enumerate() {
+                    for channel in 0..CHANNEL_NR {
+                        assert!(
+                            (sample[channel] - expected[channel]).abs() < 1e-6,
+                            "{} {} frame {frame}: {sample:?} != {expected:?}",
+                            modulation.as_str(),
+                            waveform.as_str(),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn phase_modulation_renders_bit_identical_to_rendering_per_sample() {
+        // Every carrier's dry and modulated phases get passed into its waveform one after the
+        // other, which has to come out exactly as rendering them one sample at a time does.
+        for algorithm in [
+            Algorithm::ThreeToOne,
+            Algorithm::Series,
+            Algorithm::Stack,
+            Algorithm::Branch,
+        ] {
+            let patch = PatchState {
+                waveforms: [Waveform::Sine, Waveform::Saw, Waveform::Square],
+                levels: [1.0, 0.6, 0.8],
+                modulation: Modulation::Phase,
+                algorithm,
+                pitch: [PITCH_AMOUNT, PITCH_AMOUNT + 7.0, PITCH_AMOUNT + 12.0],
+                fold: [1.5, 1.0, 1.0],
+                ..INIT
+            };
+            let (shared, mut blocks) = self::played_key(patch, 57);
+            let (_, mut per_sample) = self::played_key(patch, 57);
+            let note_data = self::note_data(&shared);
+            let tuning = self::tuning(&shared);
+
+            let mut expected = vec![[0.0; CHANNEL_NR]; 1000];
+            let mut output = expected.clone();
+            per_sample.process_oscs_per_sample(&mut expected, tuning, &[0, 1, 2], &note_data);
+            blocks.process_oscs(&mut output, tuning, &[0, 1, 2], &note_data);
+            assert_eq!(output, expected, "{}", algorithm.as_str());
+        }
+    }
+
+    #[test]
+    fn phase_modulation_keeps_its_loudness_as_the_modulator_turns_up() {
+        let rms = |modulator_level: f32| {
+            let patch = PatchState {
+                waveforms: [Waveform::Sine; OSC_NR],
+                levels: [1.0, 0.0, modulator_level],
+                modulation: Modulation::Phase,
+                algorithm: Algorithm::ThreeToOne,
+                ..INIT
+            };
+            let (shared, mut key) = self::played_key(patch, 57);
+            let note_data = self::note_data(&shared);
+
+            // A second in, the envelope has long settled at its sustain level.
+            let mut output = vec![[0.0; CHANNEL_NR]; SAMPLE_RATE as usize];
+            key.process_oscs(&mut output, self::tuning(&shared), &[0, 2], &note_data);
+            let settled = &output[output.len() / 2..];
+            (settled
+                .iter()
+                .flatten()
+                .map(|sample| sample * sample)
+                .sum::<f32>()
+                / (settled.len() * CHANNEL_NR) as f32)
+                .sqrt()
+        };
+
+        // The modulated signal follows the dry one closely at first and drifts away from it as the
+        // modulation deepens, so the sum swings a little either way. It has to stay within about
+        // 4 dB of the carrier on its own though, where mixing them linearly nearly doubled it.
+        let dry = rms(0.0);
+        for modulator_level in (1..=20).map(|step| step as f32 / 20.0) {
+            let ratio = rms(modulator_level) / dry;
+            assert!(
+                (0.6..=1.4).contains(&ratio),
+                "{ratio} at a modulator level of {modulator_level}"
+            );
+        }
+    }
+
+    #[test]
+    fn band_limited_waveforms_stay_bounded_at_the_top_note() {
+        for waveform in [Waveform::Square, Waveform::Saw] {
+            // The top key, as it is and shifted as far up as the coarse pitch goes.
+            for pitch in [PITCH_AMOUNT, PITCH_AMOUNT * 2.0] {
+                let patch = PatchState {
+                    waveforms: [waveform; OSC_NR],
+                    levels: [1.0, 0.0, 0.0],
+                    pitch: [pitch; OSC_NR],
+                    ..INIT
+                };
+                let (shared, mut key) = self::played_key(patch, KEYS_NR - 1);
+                let note_data = self::note_data(&shared);
+
+                let mut output = vec![[0.0; CHANNEL_NR]; 4800];
+                key.process_oscs(&mut output, self::tuning(&shared), &[0], &note_data);
+                let peak = output
+                    .iter()
+                    .flatten()
+                    .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+                assert!(
+                    peak > 0.5 && peak <= 1.0,
+                    "{} at {pitch}: {peak}",
+                    waveform.as_str()
+                );
+            }
+        }
+    }
+}
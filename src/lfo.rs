@@ -0,0 +1,44 @@
+use std::f32::consts::TAU;
+
+use crate::shared::LfoShape;
+
+/// The global LFO. Every key gets modulated by the same LFO, so its phase is kept by the audio
+/// processor rather than by the keys.
+pub struct Lfo {
+    phase: f32,
+}
+
+impl Lfo {
+    pub const fn new() -> Self {
+        Self { phase: 0.0 }
+    }
+
+    pub fn reset(&mut self) {
+        self.phase = 0.0;
+    }
+
+    /// Moves the LFO to where it should be at a song position, for an LFO doing a cycle every
+    /// `beats`. The LFO then lines up with the song, wherever playback starts from.
+    pub fn align(&mut self, position: f64, beats: f64) {
+        self.phase = (position / beats).rem_euclid(1.0) as f32;
+    }
+
+    /// Returns the LFO's value (-1.0..=1.0) at the start of a block of `samples`, then moves it
+    /// to the end of the block.
+    pub fn advance(&mut self, shape: LfoShape, rate: f32, samples: usize, sample_rate: f32) -> f32 {
+        let value = match shape {
+            LfoShape::Sine => (self.phase * TAU).sin(),
+            LfoShape::Triangle => 1.0 - 4.0 * (self.phase - 0.5).abs(),
+            LfoShape::Square => {
+                if self.phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+        };
+
+        self.phase = (self.phase + rate * samples as f32 / sample_rate) % 1.0;
+        value
+    }
+}
@@ -4,8 +4,10 @@
 use std::ffi::CStr;
 
 use clack_extensions::{
-    audio_ports::PluginAudioPorts, note_ports::PluginNotePorts, params::PluginParams,
-    state::PluginState,
+    audio_ports::PluginAudioPorts, audio_ports_config::PluginAudioPortsConfig,
+    context_menu::PluginContextMenu, note_name::PluginNoteName, note_ports::PluginNotePorts,
+    params::PluginParams, preset_load::PluginPresetLoad, render::PluginRender, state::PluginState,
+    state_context::PluginStateContext,
 };
 use clack_plugin::entry::prelude::*;
 use clack_plugin::prelude::*;
@@ -14,15 +16,27 @@ use crate::{
     audio_processor::Fox3oscAudioProcessor,
     consts::{AUTHOR, PLUGIN_COUNT},
     main_thread::Fox3oscMainThread,
-    shared::Fox3oscShared,
+    shared::{Fox3oscShared, Temperament},
 };
 
+mod arp;
 mod audio_processor;
+mod chorus;
 mod consts;
+mod delay;
+mod filter;
 mod key;
+mod lfo;
+mod limiter;
 mod main_thread;
 mod math;
+mod oversample;
+mod param;
+mod presets;
 mod shared;
+mod smooth;
+mod state;
+mod transport;
 
 struct Fox3oscDescriptor {
     name: &'static str,
@@ -84,9 +98,15 @@ impl Plugin for Fox3osc {
     ) {
         builder
             .register::<PluginAudioPorts>()
+            .register::<PluginAudioPortsConfig>()
+            .register::<PluginContextMenu>()
+            .register::<PluginNoteName>()
             .register::<PluginNotePorts>()
             .register::<PluginParams>()
-            .register::<PluginState>();
+            .register::<PluginRender>()
+            .register::<PluginState>()
+            .register::<PluginStateContext>()
+            .register::<PluginPresetLoad>();
     }
 }
 
@@ -108,7 +128,7 @@ impl Entry for Fox3oscEntry {
 
 struct Fox3oscPlugin {
     pub descriptor: PluginDescriptor,
-    pub n_tet: f32,
+    pub temperament: Temperament,
 }
 
 struct Fox3oscFactory {
@@ -133,36 +153,44 @@ impl Fox3oscFactory {
             fox3osc_descriptor!("fox3osc (23-tet)"),
             #[cfg(feature = "24tet")]
             fox3osc_descriptor!("fox3osc (24-tet)"),
+            #[cfg(feature = "31tet")]
+            fox3osc_descriptor!("fox3osc (31-tet)"),
+            #[cfg(feature = "53tet")]
+            fox3osc_descriptor!("fox3osc (53-tet)"),
         ];
 
-        const PLUGIN_N_TETS: [f32; PLUGIN_COUNT] = [
-            12.0,
+        const PLUGIN_TEMPERAMENTS: [Temperament; PLUGIN_COUNT] = [
+            Temperament::Tet12,
             #[cfg(feature = "15tet")]
-            15.0,
+            Temperament::Tet15,
             #[cfg(feature = "17tet")]
-            17.0,
+            Temperament::Tet17,
             #[cfg(feature = "19tet")]
-            19.0,
+            Temperament::Tet19,
             #[cfg(feature = "22tet")]
-            22.0,
+            Temperament::Tet22,
             #[cfg(feature = "23tet")]
-            23.0,
+            Temperament::Tet23,
             #[cfg(feature = "24tet")]
-            24.0,
+            Temperament::Tet24,
+            #[cfg(feature = "31tet")]
+            Temperament::Tet31,
+            #[cfg(feature = "53tet")]
+            Temperament::Tet53,
         ];
 
         let plugin_descriptors = std::array::from_fn(|i| {
             let descriptor = &PLUGIN_DESCRIPTORS[i];
-            let n_tet = PLUGIN_N_TETS[i];
+            let temperament = PLUGIN_TEMPERAMENTS[i];
 
             Fox3oscPlugin {
-                n_tet,
+                temperament,
                 descriptor: PluginDescriptor::new(descriptor.id(), descriptor.name())
                     .with_vendor(descriptor.author())
                     .with_version(descriptor.version())
                     .with_description(descriptor.description())
                     .with_url(descriptor.url())
-                    .with_features([INSTRUMENT, SYNTHESIZER, MONO]),
+                    .with_features([INSTRUMENT, SYNTHESIZER, STEREO]),
             }
         });
 
@@ -191,8 +219,8 @@ impl PluginFactory for Fox3oscFactory {
                 let instance = PluginInstance::new::<Fox3osc>(
                     host_info,
                     &plugin.descriptor,
-                    move |_host| Ok(Fox3oscShared::new(plugin.n_tet)),
-                    |_host, shared| Ok(Fox3oscMainThread::new(shared)),
+                    move |_host| Ok(Fox3oscShared::new(plugin.temperament)),
+                    |host, shared| Ok(Fox3oscMainThread::new(host, shared)),
                 );
 
                 Some(instance)
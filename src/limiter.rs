@@ -0,0 +1,52 @@
+use crate::key::Frame;
+
+/// Time it takes for the gain reduction to recover by about two thirds, in seconds.
+const RELEASE_TIME: f32 = 0.05;
+
+/// A peak limiter on the final output, which keeps the summed keys from clipping. The gain drops
+/// right away to catch a peak, and recovers over the release. Both channels share the same gain
+/// so the stereo image doesn't shift.
+pub struct Limiter {
+    /// Gain currently applied to the output.
+    gain: f32,
+    release: f32,
+}
+
+impl Limiter {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            gain: 1.0,
+            release: 1.0 - (-1.0 / (RELEASE_TIME * sample_rate)).exp(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.gain = 1.0;
+    }
+
+    /// Limits frames in place so their peaks stay under `threshold`, given in dBFS.
+    pub fn process(&mut self, frames: &mut [Frame], threshold: f32) {
+        let threshold = 10.0f32.powf(threshold / 20.0);
+
+        for frame in frames {
+            let peak = frame
+                .iter()
+                .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+            let target = if peak > threshold {
+                threshold / peak
+            } else {
+                1.0
+            };
+
+            if target < self.gain {
+                self.gain = target;
+            } else {
+                self.gain += (target - self.gain) * self.release;
+            }
+
+            for sample in frame.iter_mut() {
+                *sample *= self.gain;
+            }
+        }
+    }
+}
@@ -1,116 +1,1333 @@
 use std::{
-    ffi::CStr,
-    io::{Read, Write},
+    ffi::{CStr, CString},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use clack_extensions::{
     audio_ports::{AudioPortFlags, AudioPortInfo, AudioPortType, PluginAudioPortsImpl},
+    audio_ports_config::{
+        AudioPortConfigWriter, AudioPortsConfiguration, MainPortInfo, PluginAudioPortsConfigImpl,
+    },
+    context_menu::{
+        ContextMenuBuilder, ContextMenuEntry, ContextMenuItem, ContextMenuTarget,
+        PluginContextMenuImpl,
+    },
+    note_name::{NoteName, NoteNameWriter, PluginNoteNameImpl},
     note_ports::{NoteDialect, NoteDialects, NotePortInfo, PluginNotePortsImpl},
     params::{
-        ParamDisplayWriter, ParamInfo, ParamInfoFlags, ParamInfoWriter, PluginMainThreadParams,
+        HostParams, ParamDisplayWriter, ParamInfo, ParamInfoFlags, ParamInfoWriter,
+        ParamRescanFlags, PluginMainThreadParams,
     },
+    preset_load::{PluginPresetLoadImpl, PresetLocation},
+    render::{PluginRenderImpl, RenderMode},
     state::PluginStateImpl,
+    state_context::{PluginStateContextImpl, StateContextType},
 };
 use clack_plugin::{
+    events::{Match, UnknownEvent},
     prelude::*,
     stream::{InputStream, OutputStream},
 };
+use rand::{SeedableRng, rngs::SmallRng};
 
 use crate::{
     consts::{
-        CLAP_PARAM_IS_ENUM, PARAMETER_ATTACK, PARAMETER_DECAY, PARAMETER_HQ_1, PARAMETER_HQ_2,
-        PARAMETER_HQ_3, PARAMETER_LEVEL_1, PARAMETER_LEVEL_2, PARAMETER_LEVEL_3,
-        PARAMETER_MODULATION, PARAMETER_NR, PARAMETER_PITCH_1, PARAMETER_PITCH_2,
-        PARAMETER_PITCH_3, PARAMETER_RELEASE, PARAMETER_SUSTAIN, PARAMETER_WAVEFORM_1,
-        PARAMETER_WAVEFORM_2, PARAMETER_WAVEFORM_3,
+        ARP_OCTAVES_MAX, AUDIO_PORTS_CONFIG_MONO, AUDIO_PORTS_CONFIG_STEREO, CHANNEL_NR,
+        CLAP_PARAM_IS_ENUM, CONCERT_PITCH_MAX, CONCERT_PITCH_MIN, CONTEXT_MENU_COPY_OSC,
+        CONTEXT_MENU_INIT, CONTEXT_MENU_LOAD_PRESET, CONTEXT_MENU_RANDOMIZE, GAIN_MIN, KEYS_NR,
+        LFO_RATE_MAX, LFO_RATE_MIN, MAX_DELAY_TIME, MOD_RATIOS, OCTAVE_MAX, OSC_MOD, OSC_NR,
+        PARAMETER_AFTERTOUCH_AMOUNT, PARAMETER_ALGORITHM, PARAMETER_ARP_MODE,
+        PARAMETER_ARP_OCTAVES, PARAMETER_ARP_ON, PARAMETER_ARP_RATE, PARAMETER_ATTACK_1,
+        PARAMETER_ATTACK_2, PARAMETER_ATTACK_3, PARAMETER_BLEP_WIDTH, PARAMETER_CHORUS_DEPTH,
+        PARAMETER_CHORUS_MIX, PARAMETER_CHORUS_RATE, PARAMETER_CUTOFF, PARAMETER_DECAY_1,
+        PARAMETER_DECAY_2, PARAMETER_DECAY_3, PARAMETER_DELAY_FEEDBACK, PARAMETER_DELAY_MIX,
+        PARAMETER_DELAY_SYNC, PARAMETER_DELAY_TIME, PARAMETER_DRIVE, PARAMETER_ENV_CURVE,
+        PARAMETER_EVIL_DEPTH, PARAMETER_FINE_1, PARAMETER_FINE_2, PARAMETER_FINE_3,
+        PARAMETER_FM_DEPTH, PARAMETER_FM_FEEDBACK, PARAMETER_FM_RATIO, PARAMETER_FOLD_1,
+        PARAMETER_FOLD_2, PARAMETER_FOLD_3, PARAMETER_GAIN, PARAMETER_GLIDE_MODE,
+        PARAMETER_GLIDE_TIME, PARAMETER_HQ_1, PARAMETER_HQ_2, PARAMETER_HQ_3, PARAMETER_INIT,
+        PARAMETER_KEY_TRACK, PARAMETER_LEVEL_1, PARAMETER_LEVEL_2, PARAMETER_LEVEL_3,
+        PARAMETER_LFO_DEPTH, PARAMETER_LFO_DEST, PARAMETER_LFO_DIVISION, PARAMETER_LFO_RATE,
+        PARAMETER_LFO_SHAPE, PARAMETER_LFO_SYNC, PARAMETER_LIMIT, PARAMETER_MASTER_TUNE,
+        PARAMETER_MAX_VOICES, PARAMETER_MOD_INDEX, PARAMETER_MODULATION, PARAMETER_MUTE_1,
+        PARAMETER_MUTE_2, PARAMETER_MUTE_3, PARAMETER_NOISE_COLOR, PARAMETER_NR,
+        PARAMETER_OCTAVE_1, PARAMETER_OCTAVE_2, PARAMETER_OCTAVE_3, PARAMETER_OVERSAMPLE,
+        PARAMETER_PAN_1, PARAMETER_PAN_2, PARAMETER_PAN_3, PARAMETER_PARTIALS,
+        PARAMETER_PHASE_MODE, PARAMETER_PITCH_1, PARAMETER_PITCH_2, PARAMETER_PITCH_3,
+        PARAMETER_PITCH_BEND_RANGE, PARAMETER_PITCH_ENV_AMOUNT_1, PARAMETER_PITCH_ENV_AMOUNT_2,
+        PARAMETER_PITCH_ENV_AMOUNT_3, PARAMETER_PITCH_ENV_TIME, PARAMETER_POLY_MODE, PARAMETER_PWM,
+        PARAMETER_QUALITY, PARAMETER_RANDOMIZE, PARAMETER_RATIO_LOCK, PARAMETER_RELEASE_1,
+        PARAMETER_RELEASE_2, PARAMETER_RELEASE_3, PARAMETER_RELEASE_VEL_AMOUNT,
+        PARAMETER_RESONANCE, PARAMETER_SOLO_1, PARAMETER_SOLO_2, PARAMETER_SOLO_3, PARAMETER_SPLIT,
+        PARAMETER_STEREO_SPREAD, PARAMETER_SUB_LEVEL, PARAMETER_SUSTAIN_1, PARAMETER_SUSTAIN_2,
+        PARAMETER_SUSTAIN_3, PARAMETER_TEMPERAMENT, PARAMETER_TRANSPOSE, PARAMETER_TUNING_HZ,
+        PARAMETER_UNISON_COUNT, PARAMETER_UNISON_DETUNE, PARAMETER_VEL_1, PARAMETER_VEL_2,
+        PARAMETER_VEL_3, PARAMETER_VEL_CURVE, PARAMETER_VEL_SENS, PARAMETER_VEL_TO_MOD,
+        PARAMETER_VOICES, PARAMETER_WAVEFORM_1, PARAMETER_WAVEFORM_2, PARAMETER_WAVEFORM_3,
+        PARAMETER_ZONE_1, PARAMETER_ZONE_2, PARAMETER_ZONE_3, PARTIALS_MAX, PITCH_BEND_RANGE_MAX,
+        REFERENCE_FREQUENCY, REFERENCE_NOTE, SPLIT_DEFAULT, UNISON_DETUNE_MAX, UNISON_MAX,
     },
-    shared::{Envelope, Fox3oscShared, Modulation, Waveform},
+    filter,
+    key::NoteData,
+    math,
+    presets::{FACTORY_PITCH_AMOUNT, FACTORY_PRESETS},
+    shared::{
+        self, Algorithm, ArpMode, ArpRate, ArpSettings, ChorusSettings, DelaySettings, Envelope,
+        EnvelopeCurve, FmSettings, Fox3oscShared, GlideMode, LfoDestination, LfoDivision,
+        LfoSettings, LfoShape, Modulation, Oversample, PhaseMode, PitchEnvelope, PolyMode, Quality,
+        Temperament, Unison, VelocityCurve, Waveform, Zone,
+    },
+    state::PatchState,
 };
 
 pub struct Fox3oscMainThread<'a> {
+    host: HostMainThreadHandle<'a>,
     shared: &'a Fox3oscShared,
+    /// Used for randomizing the patch while the plugin is deactivated.
+    rng: SmallRng,
+    /// Number of channels of the output port, as picked by the host's audio ports configuration.
+    pub output_channels: usize,
+}
+
+impl<'a> PluginMainThread<'a, Fox3oscShared> for Fox3oscMainThread<'a> {}
+
+impl<'a> Fox3oscMainThread<'a> {
+    pub fn new(host: HostMainThreadHandle<'a>, shared: &'a Fox3oscShared) -> Self {
+        // Seeded from the time the plugin was created so each instance gets different patches.
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+
+        Self {
+            host,
+            shared,
+            rng: SmallRng::seed_from_u64(seed),
+            output_channels: CHANNEL_NR,
+        }
+    }
+
+    /// Let the host know the plugin changed its parameter values by itself.
+    fn rescan_param_values(&mut self) {
+        if let Some(params) = self.host.get_extension::<HostParams>() {
+            params.rescan(&mut self.host, ParamRescanFlags::VALUES);
+        }
+    }
+
+    /// Load one of the [`FACTORY_PRESETS`] by its load key.
+    pub fn load_factory_preset(&mut self, load_key: &str) -> Result<(), PluginError> {
+        let preset = FACTORY_PRESETS
+            .iter()
+            .find(|preset| preset.load_key == load_key)
+            .ok_or(PluginError::Message("No factory preset with this load key"))?;
+
+        // Factory presets are written for 12-TET, so their pitch shifts get scaled to cover the
+        // same intervals in the temperament being played in, as far as the pitch parameters reach.
+        let mut state = preset.state;
+        state.temperament = self.shared.get_temperament();
+        for pitch in state.pitch.iter_mut() {
+            *pitch = (self.shared.pitch_amount
+                + (*pitch - FACTORY_PITCH_AMOUNT) / 12.0 * self.shared.n_tet() as f64)
+                .clamp(0.0, self.shared.pitch_amount * 2.0);
+        }
+
+        self.shared.apply_patch_state(&state);
+
+        Ok(())
+    }
+
+    /// Handle a parameter change the host sent while the plugin isn't processing.
+    fn flush_event(
+        &mut self,
+        event: &UnknownEvent,
+        output: &mut OutputEvents,
+    ) -> Result<(), PluginError> {
+        self.shared.process_param_event(event);
+
+        if Fox3oscShared::is_triggered(event, PARAMETER_RANDOMIZE) {
+            self.shared.randomize(&mut self.rng);
+            self.shared.flush_param_changes(output)?;
+        }
+
+        if Fox3oscShared::is_triggered(event, PARAMETER_INIT) {
+            self.shared.reset_params();
+            self.shared.flush_param_changes(output)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl PluginAudioPortsImpl for Fox3oscMainThread<'_> {
+    fn count(&mut self, is_input: bool) -> u32 {
+        if !is_input { 1 } else { 0 }
+    }
+
+    fn get(
+        &mut self,
+        index: u32,
+        is_input: bool,
+        writer: &mut clack_extensions::audio_ports::AudioPortInfoWriter,
+    ) {
+        if !is_input && index == 0 {
+            writer.set(&AudioPortInfo {
+                id: ClapId::new(1),
+                name: b"main",
+                channel_count: self.output_channels as u32,
+                flags: AudioPortFlags::IS_MAIN,
+                port_type: Some(if self.output_channels == 1 {
+                    AudioPortType::MONO
+                } else {
+                    AudioPortType::STEREO
+                }),
+                in_place_pair: None,
+            });
+        }
+    }
+}
+
+impl PluginAudioPortsConfigImpl for Fox3oscMainThread<'_> {
+    fn count(&mut self) -> u32 {
+        2
+    }
+
+    fn get(&mut self, index: u32, writer: &mut AudioPortConfigWriter) {
+        if let Some((id, name, channel_count, port_type)) = match index {
+            0 => Some((AUDIO_PORTS_CONFIG_MONO, "Mono", 1, AudioPortType::MONO)),
+            1 => Some((
+                AUDIO_PORTS_CONFIG_STEREO,
+                "Stereo",
+                CHANNEL_NR as u32,
+                AudioPortType::STEREO,
+            )),
+            _ => None,
+        } {
+            writer.write(&AudioPortsConfiguration {
+                id: ClapId::new(id),
+                name: name.as_bytes(),
+                input_port_count: 0,
+                output_port_count: 1,
+                main_input: None,
+                main_output: Some(MainPortInfo {
+                    channel_count,
+                    port_type: Some(port_type),
+                }),
+            });
+        }
+    }
+
+    /// Only gets called while the plugin is deactivated, so the new channel count gets picked up
+    /// when the audio processor is activated again.
+    fn select(&mut self, config_id: ClapId) -> Result<(), PluginError> {
+        self.output_channels = match config_id.get() {
+            AUDIO_PORTS_CONFIG_MONO => 1,
+            AUDIO_PORTS_CONFIG_STEREO => CHANNEL_NR,
+            _ => return Err(PluginError::Message("Unknown audio ports configuration")),
+        };
+
+        Ok(())
+    }
+}
+
+/// Names a key after its note in 12-TET. In other temperaments, there's no standard name for most
+/// notes, so keys are named by how many steps of the temperament they are above the closest A below
+/// them instead. The frequency the key plays at is included either way.
+fn note_name(note: usize, n_tet: f32, concert_pitch: f32) -> String {
+    const NOTE_NAMES: [&str; 12] = [
+        "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+    ];
+
+    let frequency = NoteData::frequency(note as f32, n_tet, concert_pitch);
+    if n_tet == 12.0 {
+        return format!(
+            "{}{} ({:.2} Hz)",
+            NOTE_NAMES[note % 12],
+            note as isize / 12 - 1,
+            frequency
+        );
+    }
+
+    let steps = note as isize - REFERENCE_NOTE as isize;
+    let n_tet = n_tet as isize;
+    let octave = 4 + steps.div_euclid(n_tet);
+
+    match steps.rem_euclid(n_tet) {
+        0 => format!("A{} ({:.2} Hz)", octave, frequency),
+        degree => format!("A{}+{}\\{} ({:.2} Hz)", octave, degree, n_tet, frequency),
+    }
+}
+
+impl PluginContextMenuImpl for Fox3oscMainThread<'_> {
+    /// The same actions are offered on the plugin and on every parameter, since they all act on the
+    /// whole patch.
+    fn populate(
+        &mut self,
+        _target: ContextMenuTarget,
+        builder: &mut ContextMenuBuilder,
+    ) -> Result<(), PluginError> {
+        builder.add_item(ContextMenuItem::Entry(ContextMenuEntry {
+            label: c"Init Patch",
+            is_enabled: true,
+            action_id: CONTEXT_MENU_INIT,
+        }))?;
+        builder.add_item(ContextMenuItem::Entry(ContextMenuEntry {
+            label: c"Randomize",
+            is_enabled: true,
+            action_id: CONTEXT_MENU_RANDOMIZE,
+        }))?;
+
+        for from in 0..OSC_NR {
+            for to in (0..OSC_NR).filter(|&to| to != from) {
+                let label = CString::new(format!("Copy Osc {} to Osc {}", from + 1, to + 1))
+                    .or(Err(PluginError::Message("Invalid context menu label")))?;
+
+                builder.add_item(ContextMenuItem::Entry(ContextMenuEntry {
+                    label: &label,
+                    is_enabled: true,
+                    action_id: CONTEXT_MENU_COPY_OSC + (from * OSC_NR + to) as u32,
+                }))?;
+            }
+        }
+
+        for (index, preset) in FACTORY_PRESETS.iter().enumerate() {
+            let label = CString::new(format!("Load {}", preset.name))
+                .or(Err(PluginError::Message("Invalid context menu label")))?;
+
+            builder.add_item(ContextMenuItem::Entry(ContextMenuEntry {
+                label: &label,
+                is_enabled: true,
+                action_id: CONTEXT_MENU_LOAD_PRESET + index as u32,
+            }))?;
+        }
+
+        Ok(())
+    }
+
+    fn perform(&mut self, _target: ContextMenuTarget, action_id: u32) -> Result<(), PluginError> {
+        match action_id {
+            CONTEXT_MENU_INIT => self.shared.reset_params(),
+            CONTEXT_MENU_RANDOMIZE => self.shared.randomize(&mut self.rng),
+            _ if (CONTEXT_MENU_COPY_OSC..CONTEXT_MENU_COPY_OSC + (OSC_NR * OSC_NR) as u32)
+                .contains(&action_id) =>
+            {
+                let pair = (action_id - CONTEXT_MENU_COPY_OSC) as usize;
+                self.shared.copy_oscillator(pair / OSC_NR, pair % OSC_NR);
+            }
+            _ if (CONTEXT_MENU_LOAD_PRESET
+                ..CONTEXT_MENU_LOAD_PRESET + FACTORY_PRESETS.len() as u32)
+                .contains(&action_id) =>
+            {
+                let preset = &FACTORY_PRESETS[(action_id - CONTEXT_MENU_LOAD_PRESET) as usize];
+                self.load_factory_preset(preset.load_key)?;
+                self.shared.queue_all_param_changes();
+            }
+            _ => return Err(PluginError::Message("Unknown context menu action")),
+        }
+
+        self.rescan_param_values();
+        Ok(())
+    }
+}
+
+impl PluginNoteNameImpl for Fox3oscMainThread<'_> {
+    fn count(&mut self) -> u32 {
+        KEYS_NR as u32
+    }
+
+    fn get(&mut self, index: u32, writer: &mut NoteNameWriter) {
+        if (index as usize) < KEYS_NR {
+            let concert_pitch = self.shared.get_concert_pitch();
+            let name = self::note_name(index as usize, self.shared.n_tet(), concert_pitch);
+
+            writer.write(&NoteName {
+                name: name.as_bytes(),
+                port: Match::All,
+                key: Match::Specific(index as u16),
+                channel: Match::All,
+            });
+        }
+    }
+}
+
+impl PluginNotePortsImpl for Fox3oscMainThread<'_> {
+    fn count(&mut self, is_input: bool) -> u32 {
+        if is_input { 1 } else { 0 }
+    }
+
+    fn get(
+        &mut self,
+        index: u32,
+        is_input: bool,
+        writer: &mut clack_extensions::note_ports::NotePortInfoWriter,
+    ) {
+        if is_input && index == 0 {
+            writer.set(&NotePortInfo {
+                id: ClapId::new(1),
+                name: b"main",
+                preferred_dialect: Some(NoteDialect::Midi),
+                supported_dialects: NoteDialects::CLAP | NoteDialects::MIDI,
+            })
+        }
+    }
+}
+
+fn get_info_adsr(param_index: u32, info: &mut ParamInfoWriter) {
+    const NAMES: [[&str; 4]; OSC_NR] = [
+        [
+            "Osc 1 Attack",
+            "Osc 1 Decay",
+            "Osc 1 Sustain",
+            "Osc 1 Release",
+        ],
+        [
+            "Osc 2 Attack",
+            "Osc 2 Decay",
+            "Osc 2 Sustain",
+            "Osc 2 Release",
+        ],
+        [
+            "Osc 3 Attack",
+            "Osc 3 Decay",
+            "Osc 3 Sustain",
+            "Osc 3 Release",
+        ],
+    ];
+
+    if let Some((osc, segment)) = shared::envelope_parameter(param_index) {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE | ParamInfoFlags::IS_MODULATABLE,
+            cookie: Default::default(),
+            name: NAMES[osc][segment].as_bytes(),
+            module: b"",
+            min_value: 0.0,
+            max_value: 1.0,
+            default_value: Envelope::default().segments()[segment] as f64,
+        });
+    }
+}
+
+fn get_info_envelope_curve(param_index: u32, info: &mut ParamInfoWriter) {
+    if let Some((name, default)) = match param_index {
+        PARAMETER_ENV_CURVE => Some(("Envelope Curve", Envelope::default().curve)),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: CLAP_PARAM_IS_ENUM | ParamInfoFlags::IS_STEPPED | ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: EnvelopeCurve::Linear.into(),
+            max_value: EnvelopeCurve::Exponential.into(),
+            default_value: default.into(),
+        });
+    }
+}
+
+fn get_info_waveforms(param_index: u32, info: &mut ParamInfoWriter) {
+    if let Some((name, default)) = match param_index {
+        PARAMETER_WAVEFORM_1 => Some(("Osc 1 Waveform", Waveform::default())),
+        PARAMETER_WAVEFORM_2 => Some(("Osc 2 Waveform", Waveform::default())),
+        PARAMETER_WAVEFORM_3 => Some(("Osc 3 Waveform", Waveform::default())),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: CLAP_PARAM_IS_ENUM | ParamInfoFlags::IS_STEPPED | ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: Waveform::Sine.into(),
+            max_value: Waveform::PinkNoise.into(),
+            default_value: default.into(),
+        });
+    }
+}
+
+fn get_info_levels(param_index: u32, info: &mut ParamInfoWriter) {
+    if let Some((name, default)) = match param_index {
+        PARAMETER_LEVEL_1 => Some(("Osc 1 Level", 1.0)),
+        PARAMETER_LEVEL_2 => Some(("Osc 2 Level", 0.0)),
+        PARAMETER_LEVEL_3 => Some(("Osc 3 Level", 0.0)),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE
+                | ParamInfoFlags::IS_MODULATABLE
+                | ParamInfoFlags::IS_MODULATABLE_PER_NOTE_ID
+                | ParamInfoFlags::IS_MODULATABLE_PER_KEY,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: 0.0,
+            max_value: 1.0,
+            default_value: default,
+        });
+    }
+}
+
+fn get_info_hq(param_index: u32, info: &mut ParamInfoWriter) {
+    if let Some(name) = match param_index {
+        PARAMETER_HQ_1 => Some("Osc 1 HQ"),
+        PARAMETER_HQ_2 => Some("Osc 2 HQ"),
+        PARAMETER_HQ_3 => Some("Osc 3 HQ"),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: CLAP_PARAM_IS_ENUM
+                | ParamInfoFlags::IS_STEPPED
+                | ParamInfoFlags::IS_HIDDEN
+                | ParamInfoFlags::IS_READONLY,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: 0.0,
+            max_value: 1.0,
+            default_value: Quality::default().band_limited() as u8 as f64,
+        });
+    }
+}
+
+fn get_info_quality(param_index: u32, info: &mut ParamInfoWriter) {
+    if param_index == PARAMETER_QUALITY {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: CLAP_PARAM_IS_ENUM | ParamInfoFlags::IS_STEPPED | ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: b"Quality",
+            module: b"",
+            min_value: Quality::Draft.into(),
+            max_value: Quality::High.into(),
+            default_value: Quality::default().into(),
+        });
+    }
+}
+
+fn get_info_blep_width(param_index: u32, info: &mut ParamInfoWriter) {
+    if param_index == PARAMETER_BLEP_WIDTH {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: b"Anti-aliasing Width",
+            module: b"",
+            min_value: 0.5,
+            max_value: 2.0,
+            default_value: 1.0,
+        });
+    }
+}
+
+fn get_info_mute_solo(param_index: u32, info: &mut ParamInfoWriter) {
+    if let Some(name) = match param_index {
+        PARAMETER_MUTE_1 => Some("Osc 1 Mute"),
+        PARAMETER_MUTE_2 => Some("Osc 2 Mute"),
+        PARAMETER_MUTE_3 => Some("Osc 3 Mute"),
+        PARAMETER_SOLO_1 => Some("Osc 1 Solo"),
+        PARAMETER_SOLO_2 => Some("Osc 2 Solo"),
+        PARAMETER_SOLO_3 => Some("Osc 3 Solo"),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: CLAP_PARAM_IS_ENUM | ParamInfoFlags::IS_STEPPED | ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: 0.0,
+            max_value: 1.0,
+            default_value: 0.0,
+        });
+    }
+}
+
+fn get_info_ratio_lock(param_index: u32, info: &mut ParamInfoWriter) {
+    if param_index == PARAMETER_RATIO_LOCK {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: CLAP_PARAM_IS_ENUM | ParamInfoFlags::IS_STEPPED | ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: b"Osc 3 Ratio Lock",
+            module: b"",
+            min_value: 0.0,
+            max_value: 1.0,
+            default_value: 0.0,
+        });
+    }
+}
+
+fn get_info_algorithm(param_index: u32, info: &mut ParamInfoWriter) {
+    if param_index == PARAMETER_ALGORITHM {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: CLAP_PARAM_IS_ENUM | ParamInfoFlags::IS_STEPPED | ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: b"Phase Modulation Algorithm",
+            module: b"",
+            min_value: Algorithm::ThreeToOne.into(),
+            max_value: Algorithm::Parallel.into(),
+            default_value: Algorithm::default().into(),
+        });
+    }
+}
+
+fn get_info_modulation(param_index: u32, info: &mut ParamInfoWriter) {
+    if let Some((name, default)) = match param_index {
+        PARAMETER_MODULATION => Some(("Osc 3 -> Osc 1 Modulation", Modulation::default())),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: CLAP_PARAM_IS_ENUM | ParamInfoFlags::IS_STEPPED | ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: Modulation::None.into(),
+            max_value: Modulation::Frequency.into(),
+            default_value: default.into(),
+        });
+    }
+}
+
+fn get_info_mod_index(param_index: u32, info: &mut ParamInfoWriter) {
+    if let Some((name, default)) = match param_index {
+        PARAMETER_MOD_INDEX => Some(("Osc 3 -> Osc 1 Modulation Index", 1.0)),
+        PARAMETER_EVIL_DEPTH => Some(("Evil Depth", 1.0)),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE | ParamInfoFlags::IS_MODULATABLE,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: 0.0,
+            max_value: 4.0,
+            default_value: default,
+        });
+    }
+}
+
+fn get_info_stereo_spread(param_index: u32, info: &mut ParamInfoWriter) {
+    if param_index == PARAMETER_STEREO_SPREAD {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: b"Stereo Spread",
+            module: b"",
+            min_value: 0.0,
+            max_value: 1.0,
+            default_value: 0.0,
+        });
+    }
+}
+
+fn get_info_pan(param_index: u32, info: &mut ParamInfoWriter) {
+    if let Some(name) = match param_index {
+        PARAMETER_PAN_1 => Some("Osc 1 Pan"),
+        PARAMETER_PAN_2 => Some("Osc 2 Pan"),
+        PARAMETER_PAN_3 => Some("Osc 3 Pan"),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: -1.0,
+            max_value: 1.0,
+            default_value: 0.0,
+        });
+    }
+}
+
+fn get_info_fm_feedback(param_index: u32, info: &mut ParamInfoWriter) {
+    if param_index == PARAMETER_FM_FEEDBACK {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: b"Osc 3 Feedback",
+            module: b"",
+            min_value: 0.0,
+            max_value: 1.0,
+            default_value: 0.0,
+        });
+    }
+}
+
+fn get_info_pitch(param_index: u32, info: &mut ParamInfoWriter, pitch_amount: f64) {
+    if let Some((name, default)) = match param_index {
+        PARAMETER_PITCH_1 => Some(("Osc 1 Coarse", pitch_amount)),
+        PARAMETER_PITCH_2 => Some(("Osc 2 Coarse", pitch_amount)),
+        PARAMETER_PITCH_3 => Some(("Osc 3 Coarse", pitch_amount)),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_STEPPED
+                | ParamInfoFlags::IS_AUTOMATABLE
+                | ParamInfoFlags::IS_MODULATABLE
+                | ParamInfoFlags::IS_MODULATABLE_PER_NOTE_ID
+                | ParamInfoFlags::IS_MODULATABLE_PER_KEY,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: 0.0,
+            max_value: pitch_amount * 2.0,
+            default_value: default,
+        });
+    }
+}
+
+fn get_info_octave(param_index: u32, info: &mut ParamInfoWriter) {
+    if let Some(name) = match param_index {
+        PARAMETER_OCTAVE_1 => Some("Osc 1 Octave"),
+        PARAMETER_OCTAVE_2 => Some("Osc 2 Octave"),
+        PARAMETER_OCTAVE_3 => Some("Osc 3 Octave"),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_STEPPED | ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: -OCTAVE_MAX as f64,
+            max_value: OCTAVE_MAX as f64,
+            default_value: 0.0,
+        });
+    }
+}
+
+fn get_info_fine(param_index: u32, info: &mut ParamInfoWriter) {
+    if let Some((name, default)) = match param_index {
+        PARAMETER_FINE_1 => Some(("Osc 1 Fine", 0.0)),
+        PARAMETER_FINE_2 => Some(("Osc 2 Fine", 0.0)),
+        PARAMETER_FINE_3 => Some(("Osc 3 Fine", 0.0)),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE
+                | ParamInfoFlags::IS_MODULATABLE
+                | ParamInfoFlags::IS_MODULATABLE_PER_NOTE_ID
+                | ParamInfoFlags::IS_MODULATABLE_PER_KEY,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: -100.0,
+            max_value: 100.0,
+            default_value: default,
+        });
+    }
+}
+
+fn get_info_master_tuning(param_index: u32, info: &mut ParamInfoWriter, pitch_amount: f64) {
+    if let Some((name, flags, min, max)) = match param_index {
+        PARAMETER_TRANSPOSE => Some((
+            "Transpose",
+            ParamInfoFlags::IS_STEPPED,
+            -pitch_amount,
+            pitch_amount,
+        )),
+        PARAMETER_MASTER_TUNE => Some(("Master Tune", ParamInfoFlags::empty(), -100.0, 100.0)),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: flags | ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: min,
+            max_value: max,
+            default_value: 0.0,
+        });
+    }
+}
+
+fn get_info_concert_pitch(param_index: u32, info: &mut ParamInfoWriter) {
+    if param_index == PARAMETER_TUNING_HZ {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: b"Concert Pitch",
+            module: b"",
+            min_value: CONCERT_PITCH_MIN as f64,
+            max_value: CONCERT_PITCH_MAX as f64,
+            default_value: REFERENCE_FREQUENCY as f64,
+        });
+    }
+}
+
+fn get_info_pitch_envelope(param_index: u32, info: &mut ParamInfoWriter) {
+    if let Some((name, min, max, default)) = match param_index {
+        PARAMETER_PITCH_ENV_AMOUNT_1 => Some((
+            "Osc 1 Pitch Envelope",
+            -24.0,
+            24.0,
+            PitchEnvelope::default().amount[0],
+        )),
+        PARAMETER_PITCH_ENV_AMOUNT_2 => Some((
+            "Osc 2 Pitch Envelope",
+            -24.0,
+            24.0,
+            PitchEnvelope::default().amount[1],
+        )),
+        PARAMETER_PITCH_ENV_AMOUNT_3 => Some((
+            "Osc 3 Pitch Envelope",
+            -24.0,
+            24.0,
+            PitchEnvelope::default().amount[2],
+        )),
+        PARAMETER_PITCH_ENV_TIME => Some((
+            "Pitch Envelope Time",
+            0.0,
+            1.0,
+            PitchEnvelope::default().time,
+        )),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: min,
+            max_value: max,
+            default_value: default as f64,
+        });
+    }
+}
+
+fn get_info_velocity(param_index: u32, info: &mut ParamInfoWriter) {
+    if let Some((name, default)) = match param_index {
+        PARAMETER_VEL_SENS => Some(("Velocity Sensitivity", 1.0)),
+        PARAMETER_VEL_TO_MOD => Some(("Velocity to Modulation", 0.0)),
+        PARAMETER_RELEASE_VEL_AMOUNT => Some(("Release Velocity", 0.0)),
+        PARAMETER_AFTERTOUCH_AMOUNT => Some(("Aftertouch", 1.0)),
+        PARAMETER_VEL_1 => Some(("Osc 1 Velocity", 1.0)),
+        PARAMETER_VEL_2 => Some(("Osc 2 Velocity", 1.0)),
+        PARAMETER_VEL_3 => Some(("Osc 3 Velocity", 1.0)),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: 0.0,
+            max_value: 1.0,
+            default_value: default,
+        });
+    }
+}
+
+fn get_info_key_track(param_index: u32, info: &mut ParamInfoWriter) {
+    if let Some((name, default)) = match param_index {
+        PARAMETER_KEY_TRACK => Some(("Key Tracking", 0.0)),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: -1.0,
+            max_value: 1.0,
+            default_value: default,
+        });
+    }
+}
+
+fn get_info_noise_color(param_index: u32, info: &mut ParamInfoWriter) {
+    if param_index == PARAMETER_NOISE_COLOR {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: b"Noise Color",
+            module: b"",
+            min_value: -1.0,
+            max_value: 1.0,
+            default_value: 0.0,
+        });
+    }
+}
+
+fn get_info_sub_level(param_index: u32, info: &mut ParamInfoWriter) {
+    if param_index == PARAMETER_SUB_LEVEL {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: b"Sub Level",
+            module: b"",
+            min_value: 0.0,
+            max_value: 1.0,
+            default_value: 0.0,
+        });
+    }
+}
+
+fn get_info_pulse_width(param_index: u32, info: &mut ParamInfoWriter) {
+    if param_index == PARAMETER_PWM {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: b"Pulse Width",
+            module: b"",
+            min_value: 0.0,
+            max_value: 1.0,
+            default_value: 0.5,
+        });
+    }
+}
+
+fn get_info_filter(param_index: u32, info: &mut ParamInfoWriter) {
+    if let Some((name, default)) = match param_index {
+        PARAMETER_CUTOFF => Some(("Filter Cutoff", 1.0)),
+        PARAMETER_RESONANCE => Some(("Filter Resonance", 0.0)),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: 0.0,
+            max_value: 1.0,
+            default_value: default,
+        });
+    }
+}
+
+fn get_info_fold(param_index: u32, info: &mut ParamInfoWriter) {
+    if let Some(name) = match param_index {
+        PARAMETER_FOLD_1 => Some("Osc 1 Fold"),
+        PARAMETER_FOLD_2 => Some("Osc 2 Fold"),
+        PARAMETER_FOLD_3 => Some("Osc 3 Fold"),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: 1.0,
+            max_value: 8.0,
+            default_value: 1.0,
+        });
+    }
+}
+
+fn get_info_partials(param_index: u32, info: &mut ParamInfoWriter) {
+    if param_index == PARAMETER_PARTIALS {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_STEPPED | ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: b"Additive Partials",
+            module: b"",
+            min_value: 1.0,
+            max_value: PARTIALS_MAX as f64,
+            default_value: 8.0,
+        });
+    }
+}
+
+fn get_info_pitch_bend_range(param_index: u32, info: &mut ParamInfoWriter) {
+    if param_index == PARAMETER_PITCH_BEND_RANGE {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_STEPPED | ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: b"Pitch Bend Range",
+            module: b"",
+            min_value: 0.0,
+            max_value: PITCH_BEND_RANGE_MAX as f64,
+            default_value: 2.0,
+        });
+    }
+}
+
+fn get_info_zones(param_index: u32, info: &mut ParamInfoWriter) {
+    if param_index == PARAMETER_SPLIT {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_STEPPED | ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: b"Split Point",
+            module: b"",
+            min_value: 0.0,
+            max_value: (KEYS_NR - 1) as f64,
+            default_value: SPLIT_DEFAULT as f64,
+        });
+    }
+
+    if let Some(name) = match param_index {
+        PARAMETER_ZONE_1 => Some("Osc 1 Zone"),
+        PARAMETER_ZONE_2 => Some("Osc 2 Zone"),
+        PARAMETER_ZONE_3 => Some("Osc 3 Zone"),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: CLAP_PARAM_IS_ENUM | ParamInfoFlags::IS_STEPPED | ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: Zone::Both.into(),
+            max_value: Zone::Upper.into(),
+            default_value: Zone::default().into(),
+        });
+    }
+}
+
+fn get_info_triggers(param_index: u32, info: &mut ParamInfoWriter) {
+    if let Some(name) = match param_index {
+        PARAMETER_RANDOMIZE => Some("Randomize"),
+        PARAMETER_INIT => Some("Init Patch"),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_STEPPED,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: 0.0,
+            max_value: 1.0,
+            default_value: 0.0,
+        });
+    }
+}
+
+fn get_info_voices(param_index: u32, info: &mut ParamInfoWriter) {
+    if param_index == PARAMETER_VOICES {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_READONLY | ParamInfoFlags::IS_STEPPED,
+            cookie: Default::default(),
+            name: b"Active Voices",
+            module: b"",
+            min_value: 0.0,
+            max_value: KEYS_NR as f64,
+            default_value: 0.0,
+        });
+    }
+}
+
+fn get_info_max_voices(param_index: u32, info: &mut ParamInfoWriter) {
+    if param_index == PARAMETER_MAX_VOICES {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_STEPPED | ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: b"Max Voices",
+            module: b"",
+            min_value: 1.0,
+            max_value: KEYS_NR as f64,
+            default_value: KEYS_NR as f64,
+        });
+    }
 }
 
-impl<'a> PluginMainThread<'a, Fox3oscShared> for Fox3oscMainThread<'a> {}
+fn get_info_glide(param_index: u32, info: &mut ParamInfoWriter) {
+    match param_index {
+        PARAMETER_GLIDE_MODE => info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: CLAP_PARAM_IS_ENUM | ParamInfoFlags::IS_STEPPED | ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: b"Glide",
+            module: b"",
+            min_value: 0.0,
+            max_value: GlideMode::Legato.into(),
+            default_value: GlideMode::default().into(),
+        }),
+        PARAMETER_GLIDE_TIME => info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: b"Glide Time",
+            module: b"",
+            min_value: 0.0,
+            max_value: 2.0,
+            default_value: 0.1,
+        }),
+        _ => {}
+    }
+}
 
-impl<'a> Fox3oscMainThread<'a> {
-    pub fn new(shared: &'a Fox3oscShared) -> Self {
-        Self { shared }
+fn get_info_unison(param_index: u32, info: &mut ParamInfoWriter) {
+    let defaults = Unison::default();
+    if let Some((name, flags, min, max, default)) = match param_index {
+        PARAMETER_UNISON_COUNT => Some((
+            "Unison Voices",
+            ParamInfoFlags::IS_STEPPED,
+            1.0,
+            UNISON_MAX as f64,
+            defaults.count as f64,
+        )),
+        PARAMETER_UNISON_DETUNE => Some((
+            "Unison Detune",
+            ParamInfoFlags::empty(),
+            0.0,
+            UNISON_DETUNE_MAX as f64,
+            defaults.detune as f64,
+        )),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: flags | ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: min,
+            max_value: max,
+            default_value: default,
+        });
     }
 }
 
-impl PluginAudioPortsImpl for Fox3oscMainThread<'_> {
-    fn count(&mut self, is_input: bool) -> u32 {
-        if !is_input { 1 } else { 0 }
+fn get_info_chorus(param_index: u32, info: &mut ParamInfoWriter) {
+    let defaults = ChorusSettings::default();
+    if let Some((name, min, max, default)) = match param_index {
+        PARAMETER_CHORUS_RATE => Some(("Chorus Rate", 0.05, 5.0, defaults.rate)),
+        PARAMETER_CHORUS_DEPTH => Some(("Chorus Depth", 0.0, 1.0, defaults.depth)),
+        PARAMETER_CHORUS_MIX => Some(("Chorus Mix", 0.0, 1.0, defaults.mix)),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: min,
+            max_value: max,
+            default_value: default as f64,
+        });
     }
+}
 
-    fn get(
-        &mut self,
-        index: u32,
-        is_input: bool,
-        writer: &mut clack_extensions::audio_ports::AudioPortInfoWriter,
-    ) {
-        if !is_input && index == 0 {
-            writer.set(&AudioPortInfo {
-                id: ClapId::new(1),
-                name: b"main",
-                channel_count: 1,
-                flags: AudioPortFlags::IS_MAIN,
-                port_type: Some(AudioPortType::MONO),
-                in_place_pair: None,
-            });
-        }
+fn get_info_lfo(param_index: u32, info: &mut ParamInfoWriter) {
+    let defaults = LfoSettings::default();
+    if let Some((name, flags, min, max, default)) = match param_index {
+        PARAMETER_LFO_SHAPE => Some((
+            "LFO Shape",
+            CLAP_PARAM_IS_ENUM | ParamInfoFlags::IS_STEPPED,
+            0.0,
+            LfoShape::Square.into(),
+            defaults.shape.into(),
+        )),
+        PARAMETER_LFO_RATE => Some((
+            "LFO Rate",
+            ParamInfoFlags::empty(),
+            LFO_RATE_MIN as f64,
+            LFO_RATE_MAX as f64,
+            defaults.rate as f64,
+        )),
+        PARAMETER_LFO_DEPTH => Some((
+            "LFO Depth",
+            ParamInfoFlags::empty(),
+            0.0,
+            1.0,
+            defaults.depth as f64,
+        )),
+        PARAMETER_LFO_DEST => Some((
+            "LFO Destination",
+            CLAP_PARAM_IS_ENUM | ParamInfoFlags::IS_STEPPED,
+            0.0,
+            LfoDestination::Cutoff.into(),
+            defaults.destination.into(),
+        )),
+        PARAMETER_LFO_SYNC => Some((
+            "LFO Tempo Sync",
+            CLAP_PARAM_IS_ENUM | ParamInfoFlags::IS_STEPPED,
+            0.0,
+            1.0,
+            defaults.sync as u8 as f64,
+        )),
+        PARAMETER_LFO_DIVISION => Some((
+            "LFO Division",
+            CLAP_PARAM_IS_ENUM | ParamInfoFlags::IS_STEPPED,
+            0.0,
+            LfoDivision::Sixteenth.into(),
+            defaults.division.into(),
+        )),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: flags | ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: min,
+            max_value: max,
+            default_value: default,
+        });
     }
 }
 
-impl PluginNotePortsImpl for Fox3oscMainThread<'_> {
-    fn count(&mut self, is_input: bool) -> u32 {
-        if is_input { 1 } else { 0 }
+fn get_info_fm(param_index: u32, info: &mut ParamInfoWriter) {
+    let defaults = FmSettings::default();
+    if let Some((name, flags, max, default)) = match param_index {
+        PARAMETER_FM_RATIO => Some((
+            "FM Ratio",
+            CLAP_PARAM_IS_ENUM | ParamInfoFlags::IS_STEPPED,
+            (MOD_RATIOS.len() - 1) as f64,
+            defaults.ratio as f64,
+        )),
+        PARAMETER_FM_DEPTH => Some((
+            "FM Depth",
+            ParamInfoFlags::empty(),
+            1.0,
+            defaults.depth as f64,
+        )),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: flags | ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: 0.0,
+            max_value: max,
+            default_value: default,
+        });
     }
+}
 
-    fn get(
-        &mut self,
-        index: u32,
-        is_input: bool,
-        writer: &mut clack_extensions::note_ports::NotePortInfoWriter,
-    ) {
-        if is_input && index == 0 {
-            writer.set(&NotePortInfo {
-                id: ClapId::new(1),
-                name: b"main",
-                preferred_dialect: Some(NoteDialect::Midi),
-                supported_dialects: NoteDialects::MIDI,
-            })
-        }
+/// Writes out one of [`MOD_RATIOS`] the way ratios are usually written, like "2:1" or "1:2".
+fn write_ratio(writer: &mut impl std::fmt::Write, ratio: f32) -> std::fmt::Result {
+    if ratio < 1.0 {
+        write!(writer, "1:{}", 1.0 / ratio)
+    } else {
+        write!(writer, "{}:1", ratio)
     }
 }
 
-fn get_info_adsr(param_index: u32, info: &mut ParamInfoWriter) {
-    if let Some((name, default)) = match param_index {
-        PARAMETER_ATTACK => Some(("Attack", Envelope::default().attack)),
-        PARAMETER_DECAY => Some(("Decay", Envelope::default().decay)),
-        PARAMETER_SUSTAIN => Some(("Sustain", Envelope::default().sustain)),
-        PARAMETER_RELEASE => Some(("Release", Envelope::default().release)),
+/// Parses the number a value's text starts with, ignoring any unit after it like " %" or " s", and
+/// multiplies it by `scale`.
+fn parse_number(input: &str, scale: f64) -> Option<f64> {
+    let suffix_idx = input
+        .find(|c: char| !c.is_numeric() && !matches!(c, '.' | ',' | '-' | '+'))
+        .unwrap_or(input.len());
+
+    // Commas are accepted as decimal separators too, for locales which write them that way.
+    input[..suffix_idx]
+        .replace(',', ".")
+        .parse()
+        .map(|v: f64| v * scale)
+        .ok()
+}
+
+fn get_info_delay(param_index: u32, info: &mut ParamInfoWriter) {
+    let defaults = DelaySettings::default();
+    if let Some((name, flags, max, default)) = match param_index {
+        PARAMETER_DELAY_TIME => Some((
+            "Delay Time",
+            ParamInfoFlags::empty(),
+            MAX_DELAY_TIME as f64,
+            defaults.time as f64,
+        )),
+        PARAMETER_DELAY_FEEDBACK => Some((
+            "Delay Feedback",
+            ParamInfoFlags::empty(),
+            0.95,
+            defaults.feedback as f64,
+        )),
+        PARAMETER_DELAY_MIX => Some((
+            "Delay Mix",
+            ParamInfoFlags::empty(),
+            1.0,
+            defaults.mix as f64,
+        )),
+        PARAMETER_DELAY_SYNC => Some((
+            "Delay Tempo Sync",
+            CLAP_PARAM_IS_ENUM | ParamInfoFlags::IS_STEPPED,
+            1.0,
+            defaults.sync as u8 as f64,
+        )),
         _ => None,
     } {
         info.set(&ParamInfo {
             id: param_index.into(),
-            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            flags: flags | ParamInfoFlags::IS_AUTOMATABLE,
             cookie: Default::default(),
             name: name.as_bytes(),
             module: b"",
             min_value: 0.0,
+            max_value: max,
+            default_value: default,
+        });
+    }
+}
+
+fn get_info_limit(param_index: u32, info: &mut ParamInfoWriter) {
+    if param_index == PARAMETER_LIMIT {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: b"Limiter Threshold",
+            module: b"",
+            min_value: -24.0,
+            max_value: 0.0,
+            default_value: 0.0,
+        });
+    }
+}
+
+fn get_info_drive(param_index: u32, info: &mut ParamInfoWriter) {
+    if param_index == PARAMETER_DRIVE {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: b"Drive",
+            module: b"",
+            min_value: 0.0,
             max_value: 1.0,
-            default_value: default as f64,
+            default_value: 0.0,
         });
     }
 }
 
-fn get_info_waveforms(param_index: u32, info: &mut ParamInfoWriter) {
+fn get_info_gain(param_index: u32, info: &mut ParamInfoWriter) {
+    if param_index == PARAMETER_GAIN {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: b"Master Gain",
+            module: b"",
+            min_value: GAIN_MIN as f64,
+            max_value: 0.0,
+            default_value: 0.0,
+        });
+    }
+}
+
+fn get_info_velocity_curve(param_index: u32, info: &mut ParamInfoWriter) {
     if let Some((name, default)) = match param_index {
-        PARAMETER_WAVEFORM_1 => Some(("Osc 1 Waveform", Waveform::default())),
-        PARAMETER_WAVEFORM_2 => Some(("Osc 2 Waveform", Waveform::default())),
-        PARAMETER_WAVEFORM_3 => Some(("Osc 3 Waveform", Waveform::default())),
+        PARAMETER_VEL_CURVE => Some(("Velocity Curve", VelocityCurve::default())),
         _ => None,
     } {
         info.set(&ParamInfo {
@@ -119,38 +1336,34 @@ fn get_info_waveforms(param_index: u32, info: &mut ParamInfoWriter) {
             cookie: Default::default(),
             name: name.as_bytes(),
             module: b"",
-            min_value: Waveform::Sine.into(),
-            max_value: Waveform::Random.into(),
+            min_value: VelocityCurve::Linear.into(),
+            max_value: VelocityCurve::SCurve.into(),
             default_value: default.into(),
         });
     }
 }
 
-fn get_info_levels(param_index: u32, info: &mut ParamInfoWriter) {
+fn get_info_poly_mode(param_index: u32, info: &mut ParamInfoWriter) {
     if let Some((name, default)) = match param_index {
-        PARAMETER_LEVEL_1 => Some(("Osc 1 Level", 1.0)),
-        PARAMETER_LEVEL_2 => Some(("Osc 2 Level", 0.0)),
-        PARAMETER_LEVEL_3 => Some(("Osc 3 Level", 0.0)),
+        PARAMETER_POLY_MODE => Some(("Poly Mode", PolyMode::default())),
         _ => None,
     } {
         info.set(&ParamInfo {
             id: param_index.into(),
-            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            flags: CLAP_PARAM_IS_ENUM | ParamInfoFlags::IS_STEPPED | ParamInfoFlags::IS_AUTOMATABLE,
             cookie: Default::default(),
             name: name.as_bytes(),
             module: b"",
-            min_value: 0.0,
-            max_value: 1.0,
-            default_value: default,
+            min_value: PolyMode::Poly.into(),
+            max_value: PolyMode::Legato.into(),
+            default_value: default.into(),
         });
     }
 }
 
-fn get_info_hq(param_index: u32, info: &mut ParamInfoWriter) {
+fn get_info_phase_mode(param_index: u32, info: &mut ParamInfoWriter) {
     if let Some((name, default)) = match param_index {
-        PARAMETER_HQ_1 => Some(("Osc 1 HQ", true)),
-        PARAMETER_HQ_2 => Some(("Osc 2 HQ", true)),
-        PARAMETER_HQ_3 => Some(("Osc 3 HQ", true)),
+        PARAMETER_PHASE_MODE => Some(("Phase Mode", PhaseMode::default())),
         _ => None,
     } {
         info.set(&ParamInfo {
@@ -159,16 +1372,16 @@ fn get_info_hq(param_index: u32, info: &mut ParamInfoWriter) {
             cookie: Default::default(),
             name: name.as_bytes(),
             module: b"",
-            min_value: 0.0,
-            max_value: 1.0,
-            default_value: default as u8 as f64,
+            min_value: PhaseMode::Retrigger.into(),
+            max_value: PhaseMode::Random.into(),
+            default_value: default.into(),
         });
     }
 }
 
-fn get_info_modulation(param_index: u32, info: &mut ParamInfoWriter) {
-    if let Some((name, default)) = match param_index {
-        PARAMETER_MODULATION => Some(("Osc 3 -> Osc 1 Modulation", Modulation::default())),
+fn get_info_temperament(param_index: u32, info: &mut ParamInfoWriter, default: Temperament) {
+    if let Some(name) = match param_index {
+        PARAMETER_TEMPERAMENT => Some("Temperament"),
         _ => None,
     } {
         info.set(&ParamInfo {
@@ -177,28 +1390,71 @@ fn get_info_modulation(param_index: u32, info: &mut ParamInfoWriter) {
             cookie: Default::default(),
             name: name.as_bytes(),
             module: b"",
-            min_value: Modulation::None.into(),
-            max_value: Modulation::Evil.into(),
+            min_value: Temperament::Tet12.into(),
+            max_value: Temperament::Tet53.into(),
             default_value: default.into(),
         });
     }
 }
 
-fn get_info_pitch(param_index: u32, info: &mut ParamInfoWriter, pitch_amount: f64) {
+fn get_info_oversample(param_index: u32, info: &mut ParamInfoWriter) {
     if let Some((name, default)) = match param_index {
-        PARAMETER_PITCH_1 => Some(("Osc 1 Pitch", pitch_amount)),
-        PARAMETER_PITCH_2 => Some(("Osc 2 Pitch", pitch_amount)),
-        PARAMETER_PITCH_3 => Some(("Osc 3 Pitch", pitch_amount)),
+        PARAMETER_OVERSAMPLE => Some(("Oversampling", Oversample::default())),
         _ => None,
     } {
         info.set(&ParamInfo {
             id: param_index.into(),
-            flags: ParamInfoFlags::IS_AUTOMATABLE,
+            flags: CLAP_PARAM_IS_ENUM | ParamInfoFlags::IS_STEPPED | ParamInfoFlags::IS_AUTOMATABLE,
             cookie: Default::default(),
             name: name.as_bytes(),
             module: b"",
-            min_value: 0.0,
-            max_value: pitch_amount * 2.0,
+            min_value: Oversample::X1.into(),
+            max_value: Oversample::X4.into(),
+            default_value: default.into(),
+        });
+    }
+}
+
+fn get_info_arp(param_index: u32, info: &mut ParamInfoWriter) {
+    if let Some((name, flags, min, max, default)) = match param_index {
+        PARAMETER_ARP_ON => Some((
+            "Arpeggiator",
+            CLAP_PARAM_IS_ENUM,
+            0.0,
+            1.0,
+            ArpSettings::default().on as u8 as f64,
+        )),
+        PARAMETER_ARP_MODE => Some((
+            "Arpeggiator Mode",
+            CLAP_PARAM_IS_ENUM,
+            ArpMode::Up.into(),
+            ArpMode::Random.into(),
+            ArpSettings::default().mode.into(),
+        )),
+        PARAMETER_ARP_RATE => Some((
+            "Arpeggiator Rate",
+            CLAP_PARAM_IS_ENUM,
+            ArpRate::Quarter.into(),
+            ArpRate::ThirtySecond.into(),
+            ArpSettings::default().rate.into(),
+        )),
+        PARAMETER_ARP_OCTAVES => Some((
+            "Arpeggiator Octaves",
+            ParamInfoFlags::empty(),
+            1.0,
+            ARP_OCTAVES_MAX as f64,
+            ArpSettings::default().octaves as f64,
+        )),
+        _ => None,
+    } {
+        info.set(&ParamInfo {
+            id: param_index.into(),
+            flags: flags | ParamInfoFlags::IS_STEPPED | ParamInfoFlags::IS_AUTOMATABLE,
+            cookie: Default::default(),
+            name: name.as_bytes(),
+            module: b"",
+            min_value: min,
+            max_value: max,
             default_value: default,
         });
     }
@@ -215,38 +1471,55 @@ impl PluginMainThreadParams for Fox3oscMainThread<'_> {
         self::get_info_waveforms(param_index, info);
         self::get_info_levels(param_index, info);
         self::get_info_hq(param_index, info);
+        self::get_info_quality(param_index, info);
+        self::get_info_blep_width(param_index, info);
+        self::get_info_mute_solo(param_index, info);
         self::get_info_modulation(param_index, info);
+        self::get_info_algorithm(param_index, info);
+        self::get_info_ratio_lock(param_index, info);
+        self::get_info_mod_index(param_index, info);
+        self::get_info_fm_feedback(param_index, info);
         self::get_info_pitch(param_index, info, self.shared.pitch_amount);
+        self::get_info_octave(param_index, info);
+        self::get_info_fine(param_index, info);
+        self::get_info_stereo_spread(param_index, info);
+        self::get_info_pan(param_index, info);
+        self::get_info_master_tuning(param_index, info, self.shared.pitch_amount);
+        self::get_info_concert_pitch(param_index, info);
+        self::get_info_temperament(param_index, info, self.shared.default_temperament);
+        self::get_info_pitch_envelope(param_index, info);
+        self::get_info_velocity(param_index, info);
+        self::get_info_velocity_curve(param_index, info);
+        self::get_info_phase_mode(param_index, info);
+        self::get_info_envelope_curve(param_index, info);
+        self::get_info_key_track(param_index, info);
+        self::get_info_arp(param_index, info);
+        self::get_info_glide(param_index, info);
+        self::get_info_unison(param_index, info);
+        self::get_info_chorus(param_index, info);
+        self::get_info_lfo(param_index, info);
+        self::get_info_fm(param_index, info);
+        self::get_info_delay(param_index, info);
+        self::get_info_drive(param_index, info);
+        self::get_info_limit(param_index, info);
+        self::get_info_gain(param_index, info);
+        self::get_info_noise_color(param_index, info);
+        self::get_info_sub_level(param_index, info);
+        self::get_info_pulse_width(param_index, info);
+        self::get_info_filter(param_index, info);
+        self::get_info_partials(param_index, info);
+        self::get_info_pitch_bend_range(param_index, info);
+        self::get_info_fold(param_index, info);
+        self::get_info_zones(param_index, info);
+        self::get_info_triggers(param_index, info);
+        self::get_info_voices(param_index, info);
+        self::get_info_max_voices(param_index, info);
+        self::get_info_poly_mode(param_index, info);
+        self::get_info_oversample(param_index, info);
     }
 
     fn get_value(&mut self, param_id: ClapId) -> Option<f64> {
-        let envelope = self.shared.get_envelope().ok()?;
-        let waveform = self.shared.get_waveforms().ok()?;
-        let levels = self.shared.get_levels().ok()?;
-        let hq = self.shared.get_hq().ok()?;
-        let modulation = self.shared.get_modulation().ok()?;
-        let pitch = self.shared.get_pitch().ok()?;
-
-        match param_id.into() {
-            PARAMETER_ATTACK => Some(envelope.attack as f64),
-            PARAMETER_DECAY => Some(envelope.decay as f64),
-            PARAMETER_SUSTAIN => Some(envelope.sustain as f64),
-            PARAMETER_RELEASE => Some(envelope.release as f64),
-            PARAMETER_WAVEFORM_1 => Some((waveform[0]).into()),
-            PARAMETER_WAVEFORM_2 => Some((waveform[1]).into()),
-            PARAMETER_WAVEFORM_3 => Some((waveform[2]).into()),
-            PARAMETER_LEVEL_1 => Some(levels[0] as f64),
-            PARAMETER_LEVEL_2 => Some(levels[1] as f64),
-            PARAMETER_LEVEL_3 => Some(levels[2] as f64),
-            PARAMETER_HQ_1 => Some(hq[0] as u8 as f64),
-            PARAMETER_HQ_2 => Some(hq[1] as u8 as f64),
-            PARAMETER_HQ_3 => Some(hq[2] as u8 as f64),
-            PARAMETER_MODULATION => Some((*modulation).into()),
-            PARAMETER_PITCH_1 => Some(pitch[0]),
-            PARAMETER_PITCH_2 => Some(pitch[1]),
-            PARAMETER_PITCH_3 => Some(pitch[2]),
-            _ => None,
-        }
+        self.shared.get_param_value(param_id.into())
     }
 
     fn value_to_text(
@@ -257,32 +1530,210 @@ impl PluginMainThreadParams for Fox3oscMainThread<'_> {
     ) -> std::fmt::Result {
         use std::fmt::Write;
         match param_id.into() {
-            PARAMETER_ATTACK | PARAMETER_DECAY | PARAMETER_RELEASE => {
+            PARAMETER_ATTACK_1
+            | PARAMETER_DECAY_1
+            | PARAMETER_RELEASE_1
+            | PARAMETER_ATTACK_2
+            | PARAMETER_DECAY_2
+            | PARAMETER_RELEASE_2
+            | PARAMETER_ATTACK_3
+            | PARAMETER_DECAY_3
+            | PARAMETER_RELEASE_3
+            | PARAMETER_PITCH_ENV_TIME
+            | PARAMETER_GLIDE_TIME
+            | PARAMETER_DELAY_TIME => {
                 write!(writer, "{:.2} s", value)
             }
-            PARAMETER_SUSTAIN | PARAMETER_LEVEL_1..=PARAMETER_LEVEL_3 => {
+            PARAMETER_PITCH_ENV_AMOUNT_1..=PARAMETER_PITCH_ENV_AMOUNT_3 => {
+                write!(writer, "{:+.2} semitones", value)
+            }
+            PARAMETER_CHORUS_RATE | PARAMETER_LFO_RATE | PARAMETER_TUNING_HZ => {
+                write!(writer, "{:.2} Hz", value)
+            }
+            PARAMETER_CUTOFF => {
+                write!(writer, "{:.0} Hz", filter::cutoff_frequency(value as f32))
+            }
+            PARAMETER_LIMIT | PARAMETER_GAIN => {
+                write!(writer, "{:.1} dB", value)
+            }
+            PARAMETER_FINE_1..=PARAMETER_FINE_3 | PARAMETER_MASTER_TUNE => {
+                write!(writer, "{:+.1} cents", value)
+            }
+            PARAMETER_UNISON_DETUNE => {
+                write!(writer, "{:.1} cents", value)
+            }
+            PARAMETER_TRANSPOSE => {
+                write!(
+                    writer,
+                    "{:+} {}",
+                    value as isize,
+                    self.shared.get_temperament().step_name()
+                )
+            }
+            PARAMETER_SUSTAIN_1
+            | PARAMETER_SUSTAIN_2
+            | PARAMETER_SUSTAIN_3
+            | PARAMETER_LEVEL_1..=PARAMETER_LEVEL_3
+            | PARAMETER_VEL_SENS
+            | PARAMETER_VEL_TO_MOD
+            | PARAMETER_RELEASE_VEL_AMOUNT
+            | PARAMETER_AFTERTOUCH_AMOUNT
+            | PARAMETER_VEL_1..=PARAMETER_VEL_3
+            | PARAMETER_KEY_TRACK
+            | PARAMETER_NOISE_COLOR
+            | PARAMETER_PWM
+            | PARAMETER_RESONANCE
+            | PARAMETER_FM_FEEDBACK
+            | PARAMETER_STEREO_SPREAD
+            | PARAMETER_CHORUS_DEPTH
+            | PARAMETER_CHORUS_MIX
+            | PARAMETER_LFO_DEPTH
+            | PARAMETER_FM_DEPTH
+            | PARAMETER_SUB_LEVEL
+            | PARAMETER_DRIVE
+            | PARAMETER_DELAY_FEEDBACK
+            | PARAMETER_DELAY_MIX => {
                 write!(writer, "{:.2} %", value * 100f64)
             }
+            PARAMETER_FM_RATIO => self::write_ratio(
+                writer,
+                MOD_RATIOS[(value as usize).min(MOD_RATIOS.len() - 1)],
+            ),
+            PARAMETER_PAN_1..=PARAMETER_PAN_3 => {
+                if value < 0.0 {
+                    write!(writer, "{:.0} % L", -value * 100f64)
+                } else if value > 0.0 {
+                    write!(writer, "{:.0} % R", value * 100f64)
+                } else {
+                    write!(writer, "C")
+                }
+            }
             PARAMETER_WAVEFORM_1..=PARAMETER_WAVEFORM_3 => {
                 write!(writer, "{}", Waveform::from(value).as_str())
             }
-            PARAMETER_HQ_1..=PARAMETER_HQ_3 => {
+            PARAMETER_MOD_INDEX | PARAMETER_EVIL_DEPTH | PARAMETER_FOLD_1..=PARAMETER_FOLD_3 => {
+                write!(writer, "{:.2}", value)
+            }
+            PARAMETER_BLEP_WIDTH => {
+                write!(writer, "{:.2}x", value)
+            }
+            PARAMETER_HQ_1..=PARAMETER_HQ_3
+            | PARAMETER_ARP_ON
+            | PARAMETER_RANDOMIZE
+            | PARAMETER_INIT
+            | PARAMETER_MUTE_1..=PARAMETER_SOLO_3
+            | PARAMETER_RATIO_LOCK
+            | PARAMETER_DELAY_SYNC
+            | PARAMETER_LFO_SYNC => {
                 write!(writer, "{}", value != 0.0)
             }
+            PARAMETER_ARP_MODE => {
+                write!(writer, "{}", ArpMode::from(value).as_str())
+            }
+            PARAMETER_GLIDE_MODE => {
+                write!(writer, "{}", GlideMode::from(value).as_str())
+            }
+            PARAMETER_LFO_SHAPE => {
+                write!(writer, "{}", LfoShape::from(value).as_str())
+            }
+            PARAMETER_LFO_DEST => {
+                write!(writer, "{}", LfoDestination::from(value).as_str())
+            }
+            PARAMETER_LFO_DIVISION => {
+                write!(writer, "{}", LfoDivision::from(value).as_str())
+            }
+            PARAMETER_QUALITY => {
+                write!(writer, "{}", Quality::from(value).as_str())
+            }
+            PARAMETER_ARP_RATE => {
+                write!(writer, "{}", ArpRate::from(value).as_str())
+            }
+            PARAMETER_ALGORITHM => {
+                write!(writer, "{}", Algorithm::from(value).as_str())
+            }
+            PARAMETER_ARP_OCTAVES | PARAMETER_UNISON_COUNT => {
+                write!(writer, "{}", value as u32)
+            }
+            PARAMETER_PARTIALS => {
+                write!(writer, "{} partials", value as u32)
+            }
+            PARAMETER_OCTAVE_1..=PARAMETER_OCTAVE_3 => {
+                write!(writer, "{:+} octaves", value as i32)
+            }
+            PARAMETER_PITCH_BEND_RANGE => {
+                write!(writer, "{} semitones", value as u32)
+            }
+            PARAMETER_SPLIT => {
+                let concert_pitch = self.shared.get_concert_pitch();
+                write!(
+                    writer,
+                    "{}",
+                    self::note_name(value as usize, self.shared.n_tet(), concert_pitch)
+                )
+            }
+            PARAMETER_ZONE_1..=PARAMETER_ZONE_3 => {
+                write!(writer, "{}", Zone::from(value).as_str())
+            }
+            PARAMETER_VOICES | PARAMETER_MAX_VOICES => {
+                write!(writer, "{} voices", value as u32)
+            }
             PARAMETER_MODULATION => {
                 write!(writer, "{}", Modulation::from(value).as_str())
             }
+            PARAMETER_VEL_CURVE => {
+                write!(writer, "{}", VelocityCurve::from(value).as_str())
+            }
+            PARAMETER_PHASE_MODE => {
+                write!(writer, "{}", PhaseMode::from(value).as_str())
+            }
+            PARAMETER_POLY_MODE => {
+                write!(writer, "{}", PolyMode::from(value).as_str())
+            }
+            PARAMETER_OVERSAMPLE => {
+                write!(writer, "{}", Oversample::from(value).as_str())
+            }
+            PARAMETER_TEMPERAMENT => {
+                write!(writer, "{}", Temperament::from(value).as_str())
+            }
+            PARAMETER_ENV_CURVE => {
+                write!(writer, "{}", EnvelopeCurve::from(value).as_str())
+            }
+            // A locked oscillator 3 follows oscillator 1 at a ratio, so the ratio is shown instead of
+            // a frequency.
+            PARAMETER_PITCH_3 if self.shared.get_ratio_lock() => {
+                let steps = value.floor() - self.shared.pitch_amount;
+                let osc1_pitch = self.shared.get_pitch()[0];
+                let octave_steps = self.shared.octave_steps();
+                write!(
+                    writer,
+                    "{}{} {} (x{} of Osc 1)",
+                    if steps >= 0.0 { "+" } else { "" },
+                    steps as isize,
+                    self.shared.get_temperament().step_name(),
+                    math::locked_ratio(
+                        value.floor() + octave_steps[OSC_MOD]
+                            - osc1_pitch.floor()
+                            - octave_steps[0],
+                        self.shared.n_tet()
+                    ),
+                )
+            }
+            // The frequency shown is that of A4 played on the oscillator, as a point of reference
+            // for how far the oscillator is shifted.
             PARAMETER_PITCH_1..=PARAMETER_PITCH_3 => {
+                let steps = value.floor() - self.shared.pitch_amount;
+                let concert_pitch = self.shared.get_concert_pitch();
                 write!(
                     writer,
-                    "{}{} {}",
-                    if value >= self.shared.pitch_amount {
-                        "+"
-                    } else {
-                        ""
-                    },
-                    (value.floor() - self.shared.pitch_amount) as isize,
-                    self.shared.step_name,
+                    "{}{} {} ({:.2} Hz)",
+                    if steps >= 0.0 { "+" } else { "" },
+                    steps as isize,
+                    self.shared.get_temperament().step_name(),
+                    NoteData::frequency(
+                        REFERENCE_NOTE + steps as f32,
+                        self.shared.n_tet(),
+                        concert_pitch
+                    ),
                 )
             }
             _ => Err(std::fmt::Error),
@@ -293,27 +1744,254 @@ impl PluginMainThreadParams for Fox3oscMainThread<'_> {
         let input = text.to_str().ok()?;
 
         match param_id.get() {
-            param_id @ (PARAMETER_ATTACK..=PARAMETER_RELEASE
-            | PARAMETER_LEVEL_1..=PARAMETER_LEVEL_3) => {
+            param_id @ (PARAMETER_ATTACK_1..=PARAMETER_RELEASE_1
+            | PARAMETER_ATTACK_2..=PARAMETER_RELEASE_3
+            | PARAMETER_LEVEL_1..=PARAMETER_LEVEL_3
+            | PARAMETER_VEL_SENS
+            | PARAMETER_VEL_TO_MOD
+            | PARAMETER_RELEASE_VEL_AMOUNT
+            | PARAMETER_AFTERTOUCH_AMOUNT
+            | PARAMETER_VEL_1..=PARAMETER_VEL_3
+            | PARAMETER_KEY_TRACK
+            | PARAMETER_MOD_INDEX
+            | PARAMETER_EVIL_DEPTH
+            | PARAMETER_BLEP_WIDTH
+            | PARAMETER_PITCH_ENV_AMOUNT_1..=PARAMETER_PITCH_ENV_TIME
+            | PARAMETER_FINE_1..=PARAMETER_FINE_3
+            | PARAMETER_TRANSPOSE
+            | PARAMETER_MASTER_TUNE
+            | PARAMETER_TUNING_HZ
+            | PARAMETER_UNISON_DETUNE
+            | PARAMETER_NOISE_COLOR
+            | PARAMETER_PWM
+            | PARAMETER_RESONANCE
+            | PARAMETER_GLIDE_TIME
+            | PARAMETER_FOLD_1..=PARAMETER_FOLD_3
+            | PARAMETER_FM_FEEDBACK
+            | PARAMETER_STEREO_SPREAD
+            | PARAMETER_CHORUS_RATE..=PARAMETER_CHORUS_MIX
+            | PARAMETER_LFO_RATE
+            | PARAMETER_LFO_DEPTH
+            | PARAMETER_FM_DEPTH
+            | PARAMETER_SUB_LEVEL
+            | PARAMETER_DRIVE
+            | PARAMETER_DELAY_TIME..=PARAMETER_DELAY_MIX
+            | PARAMETER_LIMIT
+            | PARAMETER_GAIN) => {
                 let scale = if matches!(
                     param_id,
-                    PARAMETER_SUSTAIN | PARAMETER_LEVEL_1..=PARAMETER_LEVEL_3
+                    PARAMETER_SUSTAIN_1
+                        | PARAMETER_SUSTAIN_2
+                        | PARAMETER_SUSTAIN_3
+                        | PARAMETER_LEVEL_1
+                        ..=PARAMETER_LEVEL_3
+                            | PARAMETER_VEL_SENS
+                            | PARAMETER_VEL_TO_MOD
+                            | PARAMETER_RELEASE_VEL_AMOUNT
+                            | PARAMETER_AFTERTOUCH_AMOUNT
+                            | PARAMETER_VEL_1
+                                ..=PARAMETER_VEL_3
+                            | PARAMETER_KEY_TRACK
+                            | PARAMETER_NOISE_COLOR
+                            | PARAMETER_PWM
+                            | PARAMETER_RESONANCE
+                            | PARAMETER_FM_FEEDBACK
+                            | PARAMETER_STEREO_SPREAD
+                            | PARAMETER_CHORUS_DEPTH
+                            | PARAMETER_CHORUS_MIX
+                            | PARAMETER_LFO_DEPTH
+                            | PARAMETER_FM_DEPTH
+                            | PARAMETER_SUB_LEVEL
+                            | PARAMETER_DRIVE
+                            | PARAMETER_DELAY_FEEDBACK
+                            | PARAMETER_DELAY_MIX
                 ) {
                     0.01
                 } else {
                     1.0
                 };
 
+                self::parse_number(input, scale)
+            }
+            PARAMETER_CUTOFF => {
                 let suffix_idx = input
-                    .find(|c: char| !c.is_numeric() && c != '.' && c != ',')
+                    .find(|c: char| !c.is_numeric() && !matches!(c, '.' | ','))
                     .unwrap_or(input.len());
+                let frequency: f32 = input[..suffix_idx].replace(',', ".").parse().ok()?;
+                // Kilohertz are accepted too, like "2.5 kHz".
+                let frequency = if input[suffix_idx..].trim_start().starts_with(['k', 'K']) {
+                    frequency * 1000.0
+                } else {
+                    frequency
+                };
 
-                input[..suffix_idx].parse().map(|v: f64| v * scale).ok()
+                Some(filter::cutoff_value(frequency) as f64)
             }
-            PARAMETER_HQ_1..=PARAMETER_HQ_3 => Some(input.parse::<bool>().ok()? as u8 as f64),
-            PARAMETER_PITCH_1..=PARAMETER_PITCH_3 => {
-                Some(input.parse::<f64>().ok()? + self.shared.pitch_amount)
+            PARAMETER_PAN_1..=PARAMETER_PAN_3 => {
+                // Pans are written as a percentage towards a side, like "50 % L", with "C" for the
+                // center. A bare percentage is negative towards the left.
+                let input = input.trim();
+                if input.eq_ignore_ascii_case("c") {
+                    return Some(0.0);
+                }
+
+                let (amount, sign) = if let Some(amount) = input.strip_suffix(['L', 'l']) {
+                    (amount, -1.0)
+                } else if let Some(amount) = input.strip_suffix(['R', 'r']) {
+                    (amount, 1.0)
+                } else {
+                    (input, 1.0)
+                };
+
+                amount
+                    .trim()
+                    .trim_end_matches('%')
+                    .trim()
+                    .replace(',', ".")
+                    .parse()
+                    .map(|v: f64| (v * sign / 100.0).clamp(-1.0, 1.0))
+                    .ok()
+            }
+            PARAMETER_HQ_1..=PARAMETER_HQ_3
+            | PARAMETER_ARP_ON
+            | PARAMETER_RANDOMIZE
+            | PARAMETER_INIT
+            | PARAMETER_MUTE_1..=PARAMETER_SOLO_3
+            | PARAMETER_RATIO_LOCK
+            | PARAMETER_DELAY_SYNC
+            | PARAMETER_LFO_SYNC => Some(input.parse::<bool>().ok()? as u8 as f64),
+            PARAMETER_ARP_MODE => [ArpMode::Up, ArpMode::Down, ArpMode::UpDown, ArpMode::Random]
+                .into_iter()
+                .find(|arp_mode| input == arp_mode.as_str())
+                .map(Into::into),
+            PARAMETER_QUALITY => [Quality::Draft, Quality::Normal, Quality::High]
+                .into_iter()
+                .find(|quality| input == quality.as_str())
+                .map(Into::into),
+            PARAMETER_GLIDE_MODE => [GlideMode::Off, GlideMode::Always, GlideMode::Legato]
+                .into_iter()
+                .find(|glide_mode| input == glide_mode.as_str())
+                .map(Into::into),
+            PARAMETER_LFO_SHAPE => [LfoShape::Sine, LfoShape::Triangle, LfoShape::Square]
+                .into_iter()
+                .find(|lfo_shape| input == lfo_shape.as_str())
+                .map(Into::into),
+            PARAMETER_LFO_DEST => [
+                LfoDestination::Pitch,
+                LfoDestination::Level,
+                LfoDestination::Cutoff,
+            ]
+            .into_iter()
+            .find(|lfo_destination| input == lfo_destination.as_str())
+            .map(Into::into),
+            PARAMETER_LFO_DIVISION => [
+                LfoDivision::Whole,
+                LfoDivision::Half,
+                LfoDivision::Quarter,
+                LfoDivision::Eighth,
+                LfoDivision::Sixteenth,
+            ]
+            .into_iter()
+            .find(|lfo_division| input == lfo_division.as_str())
+            .map(Into::into),
+            PARAMETER_FM_RATIO => MOD_RATIOS
+                .into_iter()
+                .position(|ratio| {
+                    let mut text = String::new();
+                    self::write_ratio(&mut text, ratio).is_ok() && input == text
+                })
+                .map(|index| index as f64),
+            PARAMETER_ALGORITHM => [
+                Algorithm::ThreeToOne,
+                Algorithm::Series,
+                Algorithm::Stack,
+                Algorithm::Branch,
+                Algorithm::Parallel,
+            ]
+            .into_iter()
+            .find(|algorithm| input == algorithm.as_str())
+            .map(Into::into),
+            PARAMETER_ARP_RATE => [
+                ArpRate::Quarter,
+                ArpRate::Eighth,
+                ArpRate::Sixteenth,
+                ArpRate::ThirtySecond,
+            ]
+            .into_iter()
+            .find(|arp_rate| input == arp_rate.as_str())
+            .map(Into::into),
+            PARAMETER_ARP_OCTAVES | PARAMETER_UNISON_COUNT => {
+                Some(input.parse::<u32>().ok()? as f64)
+            }
+            PARAMETER_PARTIALS | PARAMETER_PITCH_BEND_RANGE => {
+                Some(input.split_whitespace().next()?.parse::<u32>().ok()? as f64)
+            }
+            PARAMETER_VOICES | PARAMETER_MAX_VOICES => {
+                Some(input.split_whitespace().next()?.parse::<u32>().ok()? as f64)
+            }
+            PARAMETER_OCTAVE_1..=PARAMETER_OCTAVE_3 => {
+                Some(input.split_whitespace().next()?.parse::<i32>().ok()? as f64)
             }
+            // Either the name of the key, or its number.
+            PARAMETER_SPLIT => {
+                let name = input.split_whitespace().next()?;
+                let concert_pitch = self.shared.get_concert_pitch();
+                (0..KEYS_NR)
+                    .find(|&note| {
+                        self::note_name(note, self.shared.n_tet(), concert_pitch)
+                            .split_whitespace()
+                            .next()
+                            == Some(name)
+                    })
+                    .or_else(|| name.parse::<usize>().ok().filter(|&note| note < KEYS_NR))
+                    .map(|note| note as f64)
+            }
+            PARAMETER_ZONE_1..=PARAMETER_ZONE_3 => [Zone::Both, Zone::Lower, Zone::Upper]
+                .into_iter()
+                .find(|zone| input == zone.as_str())
+                .map(Into::into),
+            PARAMETER_PITCH_1..=PARAMETER_PITCH_3 => Some(
+                input.split_whitespace().next()?.parse::<f64>().ok()? + self.shared.pitch_amount,
+            ),
+            PARAMETER_VEL_CURVE => [
+                VelocityCurve::Linear,
+                VelocityCurve::Exponential,
+                VelocityCurve::Logarithmic,
+                VelocityCurve::SCurve,
+            ]
+            .into_iter()
+            .find(|velocity_curve| input == velocity_curve.as_str())
+            .map(Into::into),
+            PARAMETER_POLY_MODE => [PolyMode::Poly, PolyMode::Mono, PolyMode::Legato]
+                .into_iter()
+                .find(|poly_mode| input == poly_mode.as_str())
+                .map(Into::into),
+            PARAMETER_PHASE_MODE => [PhaseMode::Retrigger, PhaseMode::Free, PhaseMode::Random]
+                .into_iter()
+                .find(|phase_mode| input == phase_mode.as_str())
+                .map(Into::into),
+            PARAMETER_OVERSAMPLE => [Oversample::X1, Oversample::X2, Oversample::X4]
+                .into_iter()
+                .find(|oversample| input == oversample.as_str())
+                .map(Into::into),
+            PARAMETER_TEMPERAMENT => [
+                Temperament::Tet12,
+                Temperament::Tet15,
+                Temperament::Tet17,
+                Temperament::Tet19,
+                Temperament::Tet22,
+                Temperament::Tet23,
+                Temperament::Tet24,
+                Temperament::Tet31,
+                Temperament::Tet53,
+            ]
+            .into_iter()
+            .find(|temperament| input == temperament.as_str())
+            .map(Into::into),
+            PARAMETER_ENV_CURVE => [EnvelopeCurve::Linear, EnvelopeCurve::Exponential]
+                .into_iter()
+                .find(|envelope_curve| input == envelope_curve.as_str())
+                .map(Into::into),
             _ if input == Waveform::Sine.as_str() => Some(Waveform::Sine.into()),
             _ if input == Waveform::Triangle.as_str() => Some(Waveform::Triangle.into()),
             _ if input == Waveform::Square.as_str() => Some(Waveform::Square.into()),
@@ -322,9 +2000,17 @@ impl PluginMainThreadParams for Fox3oscMainThread<'_> {
             _ if input == Waveform::Sploinky.as_str() => Some(Waveform::Sploinky.into()),
             _ if input == Waveform::Skloinky.as_str() => Some(Waveform::Skloinky.into()),
             _ if input == Waveform::Random.as_str() => Some(Waveform::Random.into()),
+            _ if input == Waveform::Additive.as_str() => Some(Waveform::Additive.into()),
+            _ if input == Waveform::Pulse.as_str() => Some(Waveform::Pulse.into()),
+            _ if input == Waveform::SampleHold.as_str() => Some(Waveform::SampleHold.into()),
+            _ if input == Waveform::PinkNoise.as_str() => Some(Waveform::PinkNoise.into()),
             _ if input == Modulation::None.as_str() => Some(Modulation::None.into()),
             _ if input == Modulation::Phase.as_str() => Some(Modulation::Phase.into()),
             _ if input == Modulation::Evil.as_str() => Some(Modulation::Evil.into()),
+            _ if input == Modulation::Cross.as_str() => Some(Modulation::Cross.into()),
+            _ if input == Modulation::Sync.as_str() => Some(Modulation::Sync.into()),
+            _ if input == Modulation::Ring.as_str() => Some(Modulation::Ring.into()),
+            _ if input == Modulation::Frequency.as_str() => Some(Modulation::Frequency.into()),
             _ => None,
         }
     }
@@ -332,93 +2018,105 @@ impl PluginMainThreadParams for Fox3oscMainThread<'_> {
     fn flush(
         &mut self,
         input_parameter_changes: &InputEvents,
-        _output_parameter_changes: &mut OutputEvents,
+        output_parameter_changes: &mut OutputEvents,
     ) {
+        // Like on the audio thread, a change that fails is skipped rather than panicking into the
+        // host.
         for event in input_parameter_changes {
-            self.shared.process_param_event(event).unwrap();
+            let _ = self.flush_event(event, output_parameter_changes);
         }
     }
 }
 
+impl PluginRenderImpl for Fox3oscMainThread<'_> {
+    fn has_hard_realtime_requirement(&self) -> bool {
+        false
+    }
+
+    fn set(&mut self, mode: RenderMode) -> Result<(), PluginError> {
+        *self.shared.get_offline_mut() = matches!(mode, RenderMode::Offline);
+        Ok(())
+    }
+}
+
 impl PluginStateImpl for Fox3oscMainThread<'_> {
     /// Save the plugin parameter state.
     fn save(&mut self, output: &mut OutputStream) -> Result<(), PluginError> {
-        let envelope = self.shared.get_envelope()?;
-        let waveforms = self.shared.get_waveforms()?;
-        let levels = self.shared.get_levels()?;
-        let hq = self.shared.get_hq()?;
-        let modulation = self.shared.get_modulation()?;
-        let pitch = self.shared.get_pitch()?;
-
-        output.write_all(&envelope.attack.to_le_bytes())?;
-        output.write_all(&envelope.decay.to_le_bytes())?;
-        output.write_all(&envelope.sustain.to_le_bytes())?;
-        output.write_all(&envelope.release.to_le_bytes())?;
-        for &waveform in waveforms.iter() {
-            output.write_all(&f64::from(waveform).to_le_bytes())?;
-        }
-
-        for &level in levels.iter() {
-            output.write_all(&level.to_le_bytes())?;
-        }
-
-        for &hq in hq.iter() {
-            output.write_all(&(hq as u32).to_le_bytes())?;
-        }
-
-        output.write_all(&f64::from(*modulation).to_le_bytes())?;
-        for &pitch in pitch.iter() {
-            output.write_all(&pitch.to_le_bytes())?;
-        }
-
-        Ok(())
+        self.shared.to_patch_state().write(output)
     }
 
     /// Load the plugin parameter state.
     fn load(&mut self, input: &mut InputStream) -> Result<(), PluginError> {
-        let mut envelope = self.shared.get_envelope_mut()?;
-        let mut waveforms = self.shared.get_waveforms_mut()?;
-        let mut levels = self.shared.get_levels_mut()?;
-        let mut hq = self.shared.get_hq_mut()?;
-        let mut modulation = self.shared.get_modulation_mut()?;
-        let mut pitch = self.shared.get_pitch_mut()?;
-
-        let mut buf = [0; 4];
-        input.read_exact(&mut buf)?;
-        envelope.attack = f32::from_le_bytes(buf);
-        input.read_exact(&mut buf)?;
-        envelope.decay = f32::from_le_bytes(buf);
-        input.read_exact(&mut buf)?;
-        envelope.sustain = f32::from_le_bytes(buf);
-        input.read_exact(&mut buf)?;
-        envelope.release = f32::from_le_bytes(buf);
-
-        let mut buf = [0; 8];
-        for waveform in waveforms.iter_mut() {
-            input.read_exact(&mut buf)?;
-            *waveform = f64::from_le_bytes(buf).into();
-        }
+        let state = PatchState::read(
+            input,
+            self.shared.pitch_amount,
+            self.shared.default_temperament,
+        )?;
+        self.shared.apply_patch_state(&state);
+        Ok(())
+    }
+}
 
-        let mut buf = [0; 4];
-        for level in levels.iter_mut() {
-            input.read_exact(&mut buf)?;
-            *level = f32::from_le_bytes(buf);
+impl PluginStateContextImpl for Fox3oscMainThread<'_> {
+    /// Everything in the state is portable so far, so presets, projects and duplicates all get the
+    /// same state. Anything tied to the user's machine should be left out of `ForPreset` saves.
+    fn save(
+        &mut self,
+        output: &mut OutputStream,
+        context_type: StateContextType,
+    ) -> Result<(), PluginError> {
+        match context_type {
+            StateContextType::ForPreset
+            | StateContextType::ForDuplicate
+            | StateContextType::ForProject => PluginStateImpl::save(self, output),
         }
+    }
 
-        for hq in hq.iter_mut() {
-            input.read_exact(&mut buf)?;
-            *hq = u32::from_le_bytes(buf) != 0;
+    fn load(
+        &mut self,
+        input: &mut InputStream,
+        context_type: StateContextType,
+    ) -> Result<(), PluginError> {
+        match context_type {
+            StateContextType::ForPreset
+            | StateContextType::ForDuplicate
+            | StateContextType::ForProject => PluginStateImpl::load(self, input),
         }
+    }
+}
 
-        let mut buf = [0; 8];
-        input.read_exact(&mut buf)?;
-        *modulation = f64::from_le_bytes(buf).into();
+impl PluginPresetLoadImpl for Fox3oscMainThread<'_> {
+    /// Only the factory presets embedded in the plugin can be loaded.
+    fn from_location(
+        &mut self,
+        location: PresetLocation,
+        load_key: Option<&CStr>,
+    ) -> Result<(), PluginError> {
+        let PresetLocation::Plugin = location else {
+            return Err(PluginError::Message(
+                "Presets can only be loaded from the plugin",
+            ));
+        };
 
-        for pitch in pitch.iter_mut() {
-            input.read_exact(&mut buf)?;
-            *pitch = f64::from_le_bytes(buf);
-        }
+        let load_key = load_key
+            .and_then(|load_key| load_key.to_str().ok())
+            .ok_or(PluginError::Message("Missing factory preset load key"))?;
 
-        Ok(())
+        self.load_factory_preset(load_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_parse_with_either_decimal_separator_and_a_unit() {
+        assert_eq!(self::parse_number("0.5", 1.0), Some(0.5));
+        assert_eq!(self::parse_number("0,5", 1.0), Some(0.5));
+        // Percentages are scaled down to the parameter's range.
+        assert_eq!(self::parse_number("50 %", 0.01), Some(0.5));
+        assert_eq!(self::parse_number("-0,25 s", 1.0), Some(-0.25));
+        assert_eq!(self::parse_number("%", 1.0), None);
     }
 }
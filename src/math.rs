@@ -1,3 +1,66 @@
+//! The pure DSP math of the plugin. Nothing in here depends on std, except for the float functions
+//! which aren't in `core`. With the `libm` feature, those go through `libm` instead, so this module
+//! can be used in `no_std` builds.
+
+use core::f32::consts::{SQRT_2, TAU};
+
+use crate::consts::MOD_RATIOS;
+
+/// Magnitude under which recursive state gets flushed to 0.0. It's far below anything audible, but
+/// well above the denormal range, which starts at around 1e-38.
+const DENORMAL_THRESHOLD: f32 = 1e-30;
+
+#[cfg(not(feature = "libm"))]
+mod float {
+    pub fn powf(x: f32, n: f32) -> f32 {
+        x.powf(n)
+    }
+
+    pub fn sin(x: f32) -> f32 {
+        x.sin()
+    }
+
+    pub fn log2(x: f32) -> f32 {
+        x.log2()
+    }
+
+    pub fn rem_euclid(x: f32, rhs: f32) -> f32 {
+        x.rem_euclid(rhs)
+    }
+
+    pub fn tanh(x: f32) -> f32 {
+        x.tanh()
+    }
+}
+
+#[cfg(feature = "libm")]
+mod float {
+    pub fn powf(x: f32, n: f32) -> f32 {
+        libm::powf(x, n)
+    }
+
+    pub fn sin(x: f32) -> f32 {
+        libm::sinf(x)
+    }
+
+    pub fn log2(x: f32) -> f32 {
+        libm::log2f(x)
+    }
+
+    /// Same as `f32::rem_euclid`, built on the truncated remainder of `fmodf`.
+    pub fn rem_euclid(x: f32, rhs: f32) -> f32 {
+        let r = libm::fmodf(x, rhs);
+        if r < 0.0 { r + rhs.abs() } else { r }
+    }
+
+    pub fn tanh(x: f32) -> f32 {
+        libm::tanhf(x)
+    }
+}
+
+/// Integral of a band-limited square wave from the start of its cycle to the phase `p`, with
+/// transitions `transition_size` wide. Stays within 0.0 and 0.5 over a cycle, and comes back to 0.0
+/// at the end of it, which is what makes it usable as a triangle wave.
 pub fn integrate_square_wave(p: f32, transition_size: f32) -> f32 {
     let mut value = 0.0;
     let mut prest = p;
@@ -28,18 +91,158 @@ pub fn integrate_square_wave(p: f32, transition_size: f32) -> f32 {
     value
 }
 
+/// Antiderivative of the polynomial rising edge `2p - p²` of a transition, from 0.0 to `p`. Goes
+/// from 0.0 at `p = 0.0` to 2/3 at `p = 1.0`.
 pub fn integrate_f1(p: f32) -> f32 {
-    -p.powf(3.0) / 3.0 + p.powf(2.0)
+    -float::powf(p, 3.0) / 3.0 + float::powf(p, 2.0)
 }
 
 /// "Polynomial bandlimited step" algorithm. Smooths an aliased waveform at the transition points
 /// using bandlimited polynomials.
+///
+/// `ptrans` is the distance to the transition in units of the transition size. The correction is 0.0
+/// outside of -1.0 to 1.0, and is continuous everywhere except for the step at the transition itself.
 pub fn polyblep(ptrans: f32) -> f32 {
     if ptrans <= -1.0 || ptrans >= 1.0 {
         0.0
     } else if ptrans <= 0.0 {
-        (ptrans + 1.0).powf(2.0)
+        float::powf(ptrans + 1.0, 2.0)
+    } else {
+        -float::powf(ptrans - 1.0, 2.0)
+    }
+}
+
+/// Fills a single-cycle wavetable with a sum of `partials` sine harmonics, each with an amplitude of
+/// 1/n, and normalizes it to a peak of 1.0.
+pub fn additive_wavetable(table: &mut [f32], partials: usize) {
+    let size = table.len();
+    for (i, sample) in table.iter_mut().enumerate() {
+        *sample = (1..=partials)
+            .map(|n| float::sin(TAU * ((n * i) % size) as f32 / size as f32) / n as f32)
+            .sum();
+    }
+
+    let peak = table
+        .iter()
+        .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+    if peak > 0.0 {
+        for sample in table.iter_mut() {
+            *sample /= peak;
+        }
+    }
+}
+
+/// Triangle wavefolder. Folds the parts of a signal which exceed ±1.0 back into range, leaving the
+/// signal untouched inside of it.
+pub fn wavefold(sample: f32) -> f32 {
+    1.0 - (float::rem_euclid(sample + 1.0, 4.0) - 2.0).abs()
+}
+
+/// Soft clipper. Blends the sample into a `tanh` curve by `drive`, which also pushes the sample up
+/// to 10 times harder into the curve. A drive of 0.0 passes the sample through untouched, and higher
+/// drives round off its peaks more and more, never folding them back. At full drive it levels out at
+/// ±1.0. The curve is symmetric, so it doesn't add any DC to a signal that had none.
+pub fn saturate(sample: f32, drive: f32) -> f32 {
+    let drive = drive.clamp(0.0, 1.0);
+    let saturated = float::tanh(sample * (1.0 + 9.0 * drive));
+
+    sample + drive * (saturated - sample)
+}
+
+/// Equal-power crossfade between a dry and a wet signal. Returns the gains of the dry and the wet
+/// signal at `position`, clamped to 0.0 (fully dry) and 1.0 (fully wet). The squares of the gains
+/// always add up to 1.0, so the mix of two unrelated signals keeps its loudness as it's faded.
+pub fn crossfade(position: f32) -> (f32, f32) {
+    let position = position.clamp(0.0, 1.0);
+
+    (
+        float::sin((1.0 - position) * TAU / 4.0),
+        float::sin(position * TAU / 4.0),
+    )
+}
+
+/// Equal-power pan law. Returns the gains of the left and the right channel for a stereo
+/// `position`, clamped to -1.0 (hard left) and 1.0 (hard right). The gains are scaled so both
+/// channels are at unity when centered, and neither channel ever reaches 0.0 in the mono sum.
+pub fn pan(position: f32) -> (f32, f32) {
+    let (left, right) = self::crossfade((position + 1.0) / 2.0);
+    (left * SQRT_2, right * SQRT_2)
+}
+
+/// Flushes `x` to 0.0 when it's tiny enough to be inaudible. State fed back into itself, like in
+/// filters, decays towards 0.0 after the input goes silent, and would otherwise end up in denormals,
+/// which are a lot slower to compute with on most CPUs.
+pub fn flush_denormal(x: f32) -> f32 {
+    if x > -DENORMAL_THRESHOLD && x < DENORMAL_THRESHOLD {
+        0.0
     } else {
-        -(ptrans - 1.0).powf(2.0)
+        x
+    }
+}
+
+/// Wraps a phase into a single cycle, giving exactly what `phase % 1.0` would. Phases almost always
+/// go up by less than a cycle at a time, so the common cases get handled without the much slower
+/// float modulo.
+pub fn wrap_phase(phase: f32) -> f32 {
+    if phase > -1.0 && phase < 1.0 {
+        phase
+    } else if (1.0..2.0).contains(&phase) {
+        phase - 1.0
+    } else {
+        phase % 1.0
+    }
+}
+
+/// Snaps the frequency ratio of a pitch difference, in steps of an n-TET temperament, to the closest
+/// entry of `MOD_RATIOS`.
+pub fn locked_ratio(steps: f64, n_tet: f32) -> f32 {
+    let octaves = steps as f32 / n_tet;
+
+    MOD_RATIOS
+        .into_iter()
+        .min_by(|a, b| {
+            (float::log2(*a) - octaves)
+                .abs()
+                .total_cmp(&(float::log2(*b) - octaves).abs())
+        })
+        .unwrap_or(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::TRANSITION_SIZE_MAX;
+
+    #[test]
+    fn polyblep_is_zero_outside_its_transition() {
+        for ptrans in [-5.0, -1.5, -1.0, 1.0, 1.0001, 3.0] {
+            assert_eq!(polyblep(ptrans), 0.0, "at {ptrans}");
+        }
+    }
+
+    #[test]
+    fn integrate_f1_reaches_two_thirds() {
+        assert_eq!(integrate_f1(0.0), 0.0);
+        assert!((integrate_f1(1.0) - 2.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn integrated_square_wave_stays_in_range_over_a_cycle() {
+        for transition_size in [0.001, 0.01, 0.05, 0.1, TRANSITION_SIZE_MAX] {
+            for step in 0..=1000 {
+                let p = step as f32 / 1000.0;
+                let value = integrate_square_wave(p, transition_size);
+                assert!(
+                    (-1e-5..=0.5 + 1e-5).contains(&value),
+                    "{value} at {p} with transitions {transition_size} wide"
+                );
+            }
+
+            let end = integrate_square_wave(1.0, transition_size);
+            assert!(
+                end.abs() < 1e-5,
+                "{end} with transitions {transition_size} wide"
+            );
+        }
     }
 }
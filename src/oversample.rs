@@ -0,0 +1,109 @@
+use crate::{consts::CHANNEL_NR, key::Frame, shared::Oversample};
+
+/// Length of the half-band filter, in samples at the rate it halves.
+const HALF_BAND_LEN: usize = 31;
+/// Taps of the half-band filter at the odd distances 1, 3, 5 and so on to either side of its
+/// center, from a Kaiser-windowed sinc. Every tap at an even distance is 0.0 apart from the center
+/// one, which is 0.5, so these are the only ones which get multiplied.
+const HALF_BAND_TAPS: [f32; (HALF_BAND_LEN + 1) / 4] = [
+    0.31371957,
+    -0.09308524,
+    0.043986473,
+    -0.02159067,
+    0.009803523,
+    -0.0037722571,
+    0.001064443,
+    -0.00012585413,
+];
+/// Number of times the rate gets halved coming down from the highest oversampling.
+const STAGE_NR: usize = Oversample::X4.factor().ilog2() as usize;
+
+/// A low-pass FIR filter cutting off at a quarter of the rate it runs at, which takes two samples
+/// in for every one it puts out.
+#[derive(Clone, Copy)]
+struct HalfBand {
+    /// The last samples taken in, the latest one last.
+    history: [f32; HALF_BAND_LEN],
+}
+
+impl HalfBand {
+    const fn new() -> Self {
+        Self {
+            history: [0.0; HALF_BAND_LEN],
+        }
+    }
+
+    fn process(&mut self, first: f32, second: f32) -> f32 {
+        self.history.copy_within(2.., 0);
+        self.history[HALF_BAND_LEN - 2] = first;
+        self.history[HALF_BAND_LEN - 1] = second;
+
+        let center = HALF_BAND_LEN / 2;
+        HALF_BAND_TAPS.iter().enumerate().fold(
+            0.5 * self.history[center],
+            |sum, (tap, &coefficient)| {
+                let distance = 2 * tap + 1;
+                sum + coefficient
+                    * (self.history[center - distance] + self.history[center + distance])
+            },
+        )
+    }
+}
+
+/// Brings frames rendered oversampled back down to the sample rate, halving their rate with a
+/// half-band filter as many times as it takes. The filters take out what's above the sample rate's
+/// Nyquist first, so it doesn't fold back down while the rate drops.
+#[derive(Clone, Copy)]
+pub struct Decimator {
+    /// The filters of each channel for every halving, the first halving first.
+    stages: [[HalfBand; CHANNEL_NR]; STAGE_NR],
+}
+
+impl Decimator {
+    pub const fn new() -> Self {
+        Self {
+            stages: [[HalfBand::new(); CHANNEL_NR]; STAGE_NR],
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Decimates `frames`, rendered at `factor` times the sample rate, in place. Returns the part of
+    /// `frames` holding the decimated ones. Without any oversampling they're returned untouched.
+    pub fn process<'a>(&mut self, frames: &'a mut [Frame], factor: usize) -> &'a mut [Frame] {
+        let mut frames = frames;
+        for stage in &mut self.stages[..factor.ilog2() as usize] {
+            let len = frames.len() / 2;
+            // Every frame is written behind the two it's made from, so none get overwritten before
+            // they're read.
+            for frame in 0..len {
+                let (first, second) = (frames[2 * frame], frames[2 * frame + 1]);
+                for (channel, half_band) in stage.iter_mut().enumerate() {
+                    frames[frame][channel] = half_band.process(first[channel], second[channel]);
+                }
+            }
+
+            frames = &mut std::mem::take(&mut frames)[..len];
+        }
+
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_oversampling_leaves_frames_untouched() {
+        let mut frames: Vec<Frame> = (0..64)
+            .map(|frame| [frame as f32 * 0.1 - 3.0, (frame as f32).sin()])
+            .collect();
+        let input = frames.clone();
+
+        let mut decimator = Decimator::new();
+        assert_eq!(decimator.process(&mut frames, 1), input.as_slice());
+    }
+}
@@ -0,0 +1,270 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use arrayvec::ArrayVec;
+
+/// Most words a parameter's value can take up.
+const PARAM_WORDS_MAX: usize = 16;
+
+/// A parameter's value, which gets stored as one word per field in a [`ParamCell`].
+pub trait ParamValue: Copy {
+    /// Number of words the value takes up.
+    const WORDS: usize;
+
+    /// Hands every word of the value to `write`, in order.
+    fn to_words(self, write: &mut impl FnMut(u64));
+
+    /// Builds the value back up from the words [`ParamValue::to_words`] wrote, pulled from `read` in
+    /// the same order.
+    fn from_words(read: &mut impl FnMut() -> u64) -> Self;
+}
+
+impl ParamValue for f32 {
+    const WORDS: usize = 1;
+
+    fn to_words(self, write: &mut impl FnMut(u64)) {
+        write(self.to_bits() as u64);
+    }
+
+    fn from_words(read: &mut impl FnMut() -> u64) -> Self {
+        f32::from_bits(read() as u32)
+    }
+}
+
+impl ParamValue for f64 {
+    const WORDS: usize = 1;
+
+    fn to_words(self, write: &mut impl FnMut(u64)) {
+        write(self.to_bits());
+    }
+
+    fn from_words(read: &mut impl FnMut() -> u64) -> Self {
+        f64::from_bits(read())
+    }
+}
+
+impl ParamValue for u32 {
+    const WORDS: usize = 1;
+
+    fn to_words(self, write: &mut impl FnMut(u64)) {
+        write(self as u64);
+    }
+
+    fn from_words(read: &mut impl FnMut() -> u64) -> Self {
+        read() as u32
+    }
+}
+
+impl ParamValue for i32 {
+    const WORDS: usize = 1;
+
+    fn to_words(self, write: &mut impl FnMut(u64)) {
+        write(self as u32 as u64);
+    }
+
+    fn from_words(read: &mut impl FnMut() -> u64) -> Self {
+        read() as u32 as i32
+    }
+}
+
+impl ParamValue for usize {
+    const WORDS: usize = 1;
+
+    fn to_words(self, write: &mut impl FnMut(u64)) {
+        write(self as u64);
+    }
+
+    fn from_words(read: &mut impl FnMut() -> u64) -> Self {
+        read() as usize
+    }
+}
+
+impl ParamValue for bool {
+    const WORDS: usize = 1;
+
+    fn to_words(self, write: &mut impl FnMut(u64)) {
+        write(self as u64);
+    }
+
+    fn from_words(read: &mut impl FnMut() -> u64) -> Self {
+        read() != 0
+    }
+}
+
+impl<T: ParamValue, const N: usize> ParamValue for [T; N] {
+    const WORDS: usize = T::WORDS * N;
+
+    fn to_words(self, write: &mut impl FnMut(u64)) {
+        for element in self {
+            element.to_words(write);
+        }
+    }
+
+    fn from_words(read: &mut impl FnMut() -> u64) -> Self {
+        std::array::from_fn(|_| T::from_words(read))
+    }
+}
+
+/// Implements [`ParamValue`] for enum parameters, storing them as their parameter values.
+macro_rules! param_value_enum {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl $crate::param::ParamValue for $ty {
+                const WORDS: usize = 1;
+
+                fn to_words(self, write: &mut impl FnMut(u64)) {
+                    write(f64::from(self).to_bits());
+                }
+
+                fn from_words(read: &mut impl FnMut() -> u64) -> Self {
+                    f64::from_bits(read()).into()
+                }
+            }
+        )*
+    };
+}
+
+/// Implements [`ParamValue`] for a struct out of its fields, which get stored one after the other.
+macro_rules! param_value_struct {
+    ($ty:ident { $($field:ident: $field_ty:ty),* $(,)? }) => {
+        impl $crate::param::ParamValue for $ty {
+            const WORDS: usize = 0 $(+ <$field_ty as $crate::param::ParamValue>::WORDS)*;
+
+            fn to_words(self, write: &mut impl FnMut(u64)) {
+                $($crate::param::ParamValue::to_words(self.$field, write);)*
+            }
+
+            fn from_words(read: &mut impl FnMut() -> u64) -> Self {
+                Self {
+                    $($field: <$field_ty as $crate::param::ParamValue>::from_words(read),)*
+                }
+            }
+        }
+    };
+}
+
+pub(crate) use param_value_enum;
+pub(crate) use param_value_struct;
+
+/// Holds a parameter with every word of its value in an atomic of its own, so reading or writing it
+/// never waits on another thread. A reader can catch a write between two words, seeing some fields
+/// as of before it and some as of after, but every field on its own is always whole.
+pub struct ParamCell<T: ParamValue> {
+    words: Box<[AtomicU64]>,
+    _value: std::marker::PhantomData<T>,
+}
+
+impl<T: ParamValue> ParamCell<T> {
+    pub fn new(value: T) -> Self {
+        const { assert!(T::WORDS <= PARAM_WORDS_MAX) };
+
+        let cell = Self {
+            words: (0..T::WORDS).map(|_| AtomicU64::new(0)).collect(),
+            _value: std::marker::PhantomData,
+        };
+        cell.store(value);
+        cell
+    }
+
+    pub fn load(&self) -> T {
+        let mut words = self.words.iter();
+        T::from_words(&mut || words.next().map_or(0, |word| word.load(Ordering::Relaxed)))
+    }
+
+    pub fn store(&self, value: T) {
+        let mut words = self.words.iter();
+        value.to_words(&mut |value| {
+            if let Some(word) = words.next() {
+                word.store(value, Ordering::Relaxed);
+            }
+        });
+    }
+
+    /// Stores the words of `value` which differ from those of `old`, leaving the rest to whatever
+    /// they were changed to since.
+    fn store_changes(&self, old: T, value: T) {
+        let mut old_words = ArrayVec::<u64, PARAM_WORDS_MAX>::new();
+        old.to_words(&mut |word| old_words.push(word));
+
+        let mut words = self.words.iter().zip(old_words);
+        value.to_words(&mut |value| {
+            if let Some((word, old)) = words.next()
+                && value != old
+            {
+                word.store(value, Ordering::Relaxed);
+            }
+        });
+    }
+}
+
+impl<T: ParamValue + Default> Default for ParamCell<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+/// A copy of a parameter which gets stored back into it when dropped, for changing part of the
+/// parameter in place. Only the fields which were changed get stored back, so threads changing
+/// different fields of the same parameter at once don't undo each other's changes.
+pub struct ParamWriteGuard<'a, T: ParamValue> {
+    cell: &'a ParamCell<T>,
+    old: T,
+    value: T,
+}
+
+impl<'a, T: ParamValue> ParamWriteGuard<'a, T> {
+    pub fn new(cell: &'a ParamCell<T>) -> Self {
+        let value = cell.load();
+
+        Self {
+            cell,
+            old: value,
+            value,
+        }
+    }
+}
+
+impl<T: ParamValue> std::ops::Deref for ParamWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: ParamValue> std::ops::DerefMut for ParamWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T: ParamValue> Drop for ParamWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.cell.store_changes(self.old, self.value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guards_changing_different_elements_both_land() {
+        let cell = ParamCell::new([0.0f32; 3]);
+
+        let mut first = ParamWriteGuard::new(&cell);
+        let mut second = ParamWriteGuard::new(&cell);
+        first[0] = 1.0;
+        second[2] = 3.0;
+        drop(first);
+        drop(second);
+
+        assert_eq!(cell.load(), [1.0, 0.0, 3.0]);
+    }
+
+    #[test]
+    fn parameters_round_trip_through_their_words() {
+        assert_eq!(ParamCell::new([-3i32, 7]).load(), [-3, 7]);
+        assert_eq!(ParamCell::new(-0.25f64).load(), -0.25);
+        assert!(ParamCell::new(true).load());
+    }
+}
@@ -0,0 +1,226 @@
+use crate::{
+    consts::{KEYS_NR, OSC_NR, PITCH_AMOUNT, REFERENCE_FREQUENCY, SPLIT_DEFAULT},
+    shared::{
+        Algorithm, ArpMode, ArpRate, ArpSettings, ChorusSettings, DelaySettings, Envelope,
+        EnvelopeCurve, FmSettings, Glide, GlideMode, LfoDestination, LfoDivision, LfoSettings,
+        LfoShape, MasterTuning, Modulation, Oversample, PhaseMode, PitchEnvelope, PolyMode,
+        Quality, Temperament, Unison, VelocityCurve, Waveform, Zone,
+    },
+    state::PatchState,
+};
+
+/// The pitch parameter value of an oscillator shifted by +0 semitones in 12-TET. Factory presets are
+/// written for 12-TET, and get rebased onto the plugin's temperament when loaded.
+pub const FACTORY_PITCH_AMOUNT: f64 = PITCH_AMOUNT;
+
+pub struct FactoryPreset {
+    pub name: &'static str,
+    /// Identifies the preset to the host when loading it.
+    pub load_key: &'static str,
+    /// The preset's patch, with the pitch parameter values of 12-TET.
+    pub state: PatchState,
+}
+
+pub const FACTORY_PRESETS: [FactoryPreset; 4] = [
+    FactoryPreset {
+        name: "Fat Saw Lead",
+        load_key: "fat-saw-lead",
+        state: PatchState {
+            envelope: [Envelope::new(0.005, 0.3, 0.7, 0.15).with_curve(EnvelopeCurve::Exponential);
+                OSC_NR],
+            waveforms: [Waveform::Saw; OSC_NR],
+            levels: [0.35, 0.35, 0.3],
+            pitch: self::semitones([0.0, 0.0, -12.0]),
+            fine: [-8.0, 8.0, 0.0],
+            velocity_sensitivity: 0.5,
+            ..INIT
+        },
+    },
+    FactoryPreset {
+        name: "Soft Sine Pad",
+        load_key: "soft-sine-pad",
+        state: PatchState {
+            envelope: [Envelope {
+                attack: 0.8,
+                decay: 1.0,
+                sustain: 0.8,
+                release: 1.0,
+                curve: EnvelopeCurve::Exponential,
+            }; OSC_NR],
+            waveforms: [Waveform::Sine, Waveform::Triangle, Waveform::Sine],
+            levels: [0.5, 0.3, 0.0],
+            pitch: self::semitones([0.0, 12.0, 0.0]),
+            fine: [0.0, 5.0, 0.0],
+            velocity_curve: VelocityCurve::Logarithmic,
+            ..INIT
+        },
+    },
+    FactoryPreset {
+        name: "Sploinky Bass",
+        load_key: "sploinky-bass",
+        state: PatchState {
+            envelope: [Envelope::new(0.001, 0.25, 0.3, 0.08).with_curve(EnvelopeCurve::Exponential);
+                OSC_NR],
+            waveforms: [Waveform::Sploinky, Waveform::Square, Waveform::Sine],
+            levels: [0.7, 0.3, 0.0],
+            pitch: self::semitones([-12.0, -24.0, 0.0]),
+            pitch_envelope: PitchEnvelope {
+                amount: [12.0, 0.0, 0.0],
+                time: 0.03,
+            },
+            ..INIT
+        },
+    },
+    FactoryPreset {
+        name: "Phase Bell",
+        load_key: "phase-bell",
+        state: PatchState {
+            envelope: [Envelope {
+                attack: 0.001,
+                decay: 1.0,
+                sustain: 0.0,
+                release: 1.0,
+                curve: EnvelopeCurve::Exponential,
+            }; OSC_NR],
+            levels: [0.8, 0.0, 0.5],
+            modulation: Modulation::Phase,
+            pitch: self::semitones([0.0, 0.0, 12.0]),
+            mod_index: 1.5,
+            ..INIT
+        },
+    },
+];
+
+/// The init patch factory presets are built on top of. These are the same as the parameter
+/// defaults in 12-TET.
+pub const INIT: PatchState = PatchState {
+    envelope: [Envelope::new(0.01, 0.1, 0.8, 0.1); OSC_NR],
+    waveforms: [Waveform::Sine; OSC_NR],
+    levels: [1.0, 0.0, 0.0],
+    quality: Quality::Normal,
+    modulation: Modulation::None,
+    pitch: self::semitones([0.0; OSC_NR]),
+    fine: [0.0; OSC_NR],
+    velocity_sensitivity: 1.0,
+    velocity_curve: VelocityCurve::Linear,
+    key_track: 0.0,
+    mod_index: 1.0,
+    pitch_envelope: PitchEnvelope {
+        amount: [0.0; OSC_NR],
+        time: 0.05,
+    },
+    arp_settings: ArpSettings {
+        on: false,
+        mode: ArpMode::Up,
+        rate: ArpRate::Eighth,
+        octaves: 1,
+    },
+    master_tuning: MasterTuning {
+        transpose: 0.0,
+        tune: 0.0,
+    },
+    noise_color: 0.0,
+    mute: [false; OSC_NR],
+    solo: [false; OSC_NR],
+    glide: Glide {
+        mode: GlideMode::Off,
+        time: 0.1,
+    },
+    partials: 8,
+    fold: [1.0; OSC_NR],
+    fm_feedback: 0.0,
+    algorithm: Algorithm::ThreeToOne,
+    ratio_lock: false,
+    stereo_spread: 0.0,
+    chorus: ChorusSettings {
+        rate: 0.5,
+        depth: 0.5,
+        mix: 0.0,
+    },
+    delay: DelaySettings {
+        time: 0.35,
+        feedback: 0.4,
+        mix: 0.0,
+        sync: false,
+    },
+    limit: 0.0,
+    vel_to_mod: 0.0,
+    evil_depth: 1.0,
+    osc_velocity: [1.0; OSC_NR],
+    split: SPLIT_DEFAULT,
+    zones: [Zone::Both; OSC_NR],
+    blep_width: 1.0,
+    pan: [0.0; OSC_NR],
+    pitch_bend_range: 2,
+    pulse_width: 0.5,
+    cutoff: 1.0,
+    resonance: 0.0,
+    lfo: LfoSettings {
+        shape: LfoShape::Sine,
+        rate: 5.0,
+        depth: 0.0,
+        destination: LfoDestination::Pitch,
+        sync: false,
+        division: LfoDivision::Quarter,
+    },
+    gain: 0.0,
+    release_vel_amount: 0.0,
+    fm: FmSettings {
+        ratio: 1,
+        depth: 0.5,
+    },
+    sub_level: 0.0,
+    unison: Unison {
+        count: 1,
+        detune: 15.0,
+    },
+    phase_mode: PhaseMode::Retrigger,
+    max_voices: KEYS_NR as u32,
+    poly_mode: PolyMode::Poly,
+    aftertouch_amount: 1.0,
+    concert_pitch: REFERENCE_FREQUENCY,
+    octave: [0; OSC_NR],
+    drive: 0.0,
+    oversample: Oversample::X1,
+    temperament: Temperament::Tet12,
+};
+
+/// The pitch parameter values of oscillators shifted by `semitones` in 12-TET.
+const fn semitones(semitones: [f64; OSC_NR]) -> [f64; OSC_NR] {
+    let mut pitch = [FACTORY_PITCH_AMOUNT; OSC_NR];
+
+    let mut osc = 0;
+    while osc < OSC_NR {
+        pitch[osc] += semitones[osc];
+        osc += 1;
+    }
+
+    pitch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::Fox3oscShared;
+
+    #[test]
+    fn factory_presets_round_trip_through_load() {
+        for preset in &FACTORY_PRESETS {
+            let shared = Fox3oscShared::new(Temperament::Tet12);
+            let mut saved = Vec::new();
+            preset.state.write(&mut saved).unwrap();
+
+            let state = PatchState::read(
+                &mut saved.as_slice(),
+                shared.pitch_amount,
+                shared.default_temperament,
+            )
+            .unwrap_or_else(|_| panic!("{} doesn't load", preset.name));
+            shared.apply_patch_state(&state);
+
+            let mut loaded = Vec::new();
+            shared.to_patch_state().write(&mut loaded).unwrap();
+            assert!(saved == loaded, "{} changes when loaded", preset.name);
+        }
+    }
+}
@@ -1,19 +1,203 @@
 use std::{
     ffi::c_int,
-    sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+    sync::atomic::{AtomicBool, Ordering},
 };
 
 use clack_plugin::{
-    events::{UnknownEvent, spaces::CoreEventSpace},
+    events::{Match, Pckn, UnknownEvent, event_types::ParamValueEvent, spaces::CoreEventSpace},
     plugin::{PluginError, PluginShared},
+    prelude::{ClapId, OutputEvents},
+    utils::Cookie,
 };
+use rand::{Rng, rngs::SmallRng};
 
 use crate::consts::{
-    KEYS_NR, OSC_NR, PARAMETER_ATTACK, PARAMETER_DECAY, PARAMETER_HQ_1, PARAMETER_HQ_2,
-    PARAMETER_HQ_3, PARAMETER_LEVEL_1, PARAMETER_LEVEL_2, PARAMETER_LEVEL_3, PARAMETER_MODULATION,
-    PARAMETER_PITCH_1, PARAMETER_PITCH_2, PARAMETER_PITCH_3, PARAMETER_RELEASE, PARAMETER_SUSTAIN,
-    PARAMETER_WAVEFORM_1, PARAMETER_WAVEFORM_2, PARAMETER_WAVEFORM_3,
+    ARP_OCTAVES_MAX, GAIN_MIN, KEYS_NR, MAX_NOTES_NR, MOD_RATIOS, OSC_MOD, OSC_NR,
+    PARAMETER_AFTERTOUCH_AMOUNT, PARAMETER_ALGORITHM, PARAMETER_ARP_MODE, PARAMETER_ARP_OCTAVES,
+    PARAMETER_ARP_ON, PARAMETER_ARP_RATE, PARAMETER_ATTACK_1, PARAMETER_ATTACK_2,
+    PARAMETER_BLEP_WIDTH, PARAMETER_CHORUS_DEPTH, PARAMETER_CHORUS_MIX, PARAMETER_CHORUS_RATE,
+    PARAMETER_CUTOFF, PARAMETER_DELAY_FEEDBACK, PARAMETER_DELAY_MIX, PARAMETER_DELAY_SYNC,
+    PARAMETER_DELAY_TIME, PARAMETER_DRIVE, PARAMETER_ENV_CURVE, PARAMETER_ENVELOPES,
+    PARAMETER_EVIL_DEPTH, PARAMETER_FINE_1, PARAMETER_FINE_2, PARAMETER_FINE_3, PARAMETER_FM_DEPTH,
+    PARAMETER_FM_FEEDBACK, PARAMETER_FM_RATIO, PARAMETER_FOLD_1, PARAMETER_FOLD_2,
+    PARAMETER_FOLD_3, PARAMETER_GAIN, PARAMETER_GLIDE_MODE, PARAMETER_GLIDE_TIME, PARAMETER_HQ_1,
+    PARAMETER_HQ_3, PARAMETER_INIT, PARAMETER_KEY_TRACK, PARAMETER_LEVEL_1, PARAMETER_LEVEL_2,
+    PARAMETER_LEVEL_3, PARAMETER_LFO_DEPTH, PARAMETER_LFO_DEST, PARAMETER_LFO_DIVISION,
+    PARAMETER_LFO_RATE, PARAMETER_LFO_SHAPE, PARAMETER_LFO_SYNC, PARAMETER_LIMIT,
+    PARAMETER_MASTER_TUNE, PARAMETER_MAX_VOICES, PARAMETER_MOD_INDEX, PARAMETER_MODULATION,
+    PARAMETER_MUTE_1, PARAMETER_MUTE_2, PARAMETER_MUTE_3, PARAMETER_NOISE_COLOR, PARAMETER_NR,
+    PARAMETER_OCTAVE_1, PARAMETER_OCTAVE_2, PARAMETER_OCTAVE_3, PARAMETER_OVERSAMPLE,
+    PARAMETER_PAN_1, PARAMETER_PAN_2, PARAMETER_PAN_3, PARAMETER_PARTIALS, PARAMETER_PHASE_MODE,
+    PARAMETER_PITCH_1, PARAMETER_PITCH_2, PARAMETER_PITCH_3, PARAMETER_PITCH_BEND_RANGE,
+    PARAMETER_PITCH_ENV_AMOUNT_1, PARAMETER_PITCH_ENV_AMOUNT_2, PARAMETER_PITCH_ENV_AMOUNT_3,
+    PARAMETER_PITCH_ENV_TIME, PARAMETER_POLY_MODE, PARAMETER_PWM, PARAMETER_QUALITY,
+    PARAMETER_RANDOMIZE, PARAMETER_RATIO_LOCK, PARAMETER_RELEASE_1, PARAMETER_RELEASE_3,
+    PARAMETER_RELEASE_VEL_AMOUNT, PARAMETER_RESONANCE, PARAMETER_SOLO_1, PARAMETER_SOLO_2,
+    PARAMETER_SOLO_3, PARAMETER_SPLIT, PARAMETER_STEREO_SPREAD, PARAMETER_SUB_LEVEL,
+    PARAMETER_TEMPERAMENT, PARAMETER_TRANSPOSE, PARAMETER_TUNING_HZ, PARAMETER_UNISON_COUNT,
+    PARAMETER_UNISON_DETUNE, PARAMETER_VEL_1, PARAMETER_VEL_2, PARAMETER_VEL_3,
+    PARAMETER_VEL_CURVE, PARAMETER_VEL_SENS, PARAMETER_VEL_TO_MOD, PARAMETER_VOICES,
+    PARAMETER_WAVEFORM_1, PARAMETER_WAVEFORM_2, PARAMETER_WAVEFORM_3, PARAMETER_ZONE_1,
+    PARAMETER_ZONE_2, PARAMETER_ZONE_3, PARTIALS_MAX, PITCH_AMOUNT, PITCH_BEND_RANGE_MAX,
+    REFERENCE_FREQUENCY, SPLIT_DEFAULT, UNISON_MAX,
 };
+use crate::{
+    param::{ParamCell, ParamWriteGuard, param_value_enum, param_value_struct},
+    state::PatchState,
+};
+
+/// Converts the value of an enum parameter to the discriminant of its variant. The value is rounded
+/// since hosts can hand over values which are slightly off from the step, which would otherwise
+/// truncate to the variant below.
+fn enum_discriminant(clap_value: f64) -> c_int {
+    clap_value.round() as c_int
+}
+
+param_value_enum!(
+    EnvelopeCurve,
+    Waveform,
+    Quality,
+    Modulation,
+    Algorithm,
+    Zone,
+    VelocityCurve,
+    GlideMode,
+    PolyMode,
+    PhaseMode,
+    Oversample,
+    Temperament,
+    LfoShape,
+    LfoDestination,
+    LfoDivision,
+    ArpMode,
+    ArpRate,
+);
+param_value_struct!(PitchEnvelope {
+    amount: [f32; OSC_NR],
+    time: f32
+});
+param_value_struct!(MasterTuning {
+    transpose: f64,
+    tune: f32
+});
+param_value_struct!(Envelope {
+    attack: f32,
+    decay: f32,
+    sustain: f32,
+    release: f32,
+    curve: EnvelopeCurve,
+});
+param_value_struct!(Glide {
+    mode: GlideMode,
+    time: f32
+});
+param_value_struct!(Unison {
+    count: u32,
+    detune: f32
+});
+param_value_struct!(ChorusSettings {
+    rate: f32,
+    depth: f32,
+    mix: f32
+});
+param_value_struct!(DelaySettings {
+    time: f32,
+    feedback: f32,
+    mix: f32,
+    sync: bool,
+});
+param_value_struct!(LfoSettings {
+    shape: LfoShape,
+    rate: f32,
+    depth: f32,
+    destination: LfoDestination,
+    sync: bool,
+    division: LfoDivision,
+});
+param_value_struct!(FmSettings {
+    ratio: usize,
+    depth: f32
+});
+param_value_struct!(ArpSettings {
+    on: bool,
+    mode: ArpMode,
+    rate: ArpRate,
+    octaves: u32,
+});
+
+/// A short sweep applied to the pitch of each oscillator at note-on, going from an offset back to
+/// the oscillator's pitch.
+#[derive(Clone, Copy)]
+pub struct PitchEnvelope {
+    /// The offset in semitones the sweep starts at, per oscillator.
+    pub amount: [f32; OSC_NR],
+    /// How long the sweep takes in seconds.
+    pub time: f32,
+}
+
+impl Default for PitchEnvelope {
+    /// No sweep on any oscillator, and a 50 ms sweep time.
+    fn default() -> Self {
+        Self {
+            amount: [0.0; OSC_NR],
+            time: 0.05,
+        }
+    }
+}
+
+/// Tuning applied to every oscillator of every key.
+#[derive(Default, Clone, Copy)]
+pub struct MasterTuning {
+    /// Shift in steps of the temperament.
+    pub transpose: f64,
+    /// Fine tuning in cents.
+    pub tune: f32,
+}
+
+/// The shape of the attack, decay and release segments of an envelope.
+#[derive(Default, Clone, Copy)]
+#[repr(C)]
+pub enum EnvelopeCurve {
+    #[default]
+    Linear,
+    /// Segments move fast at first and then slowly settle, like an analog envelope.
+    Exponential,
+}
+
+impl EnvelopeCurve {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            EnvelopeCurve::Linear => "Linear",
+            EnvelopeCurve::Exponential => "Exponential",
+        }
+    }
+
+    /// Maps the normalized position (0.0..=1.0) within an envelope segment to how far along the
+    /// segment the envelope's amplitude has moved.
+    pub fn shape(self, position: f32) -> f32 {
+        match self {
+            EnvelopeCurve::Linear => position,
+            EnvelopeCurve::Exponential => 1.0 - (1.0 - position).powf(3.0),
+        }
+    }
+}
+
+impl From<EnvelopeCurve> for f64 {
+    fn from(envelope_curve: EnvelopeCurve) -> Self {
+        envelope_curve as c_int as f64
+    }
+}
+
+impl From<f64> for EnvelopeCurve {
+    fn from(clap_value: f64) -> Self {
+        debug_assert!(enum_discriminant(clap_value) <= EnvelopeCurve::Exponential as c_int);
+
+        // SAFETY:
+        // EnvelopeCurve is #[repr(C)] which guarantees it being the same size and alignement as a
+        // c_int.
+        unsafe { std::mem::transmute::<c_int, Self>(enum_discriminant(clap_value)) }
+    }
+}
 
 #[derive(Clone, Copy)]
 pub struct Envelope {
@@ -21,18 +205,59 @@ pub struct Envelope {
     pub decay: f32,
     pub sustain: f32,
     pub release: f32,
+    pub curve: EnvelopeCurve,
 }
 
 impl Default for Envelope {
-    /// The default envelope shape. A 10 ms attack, 80% sustain and 100 ms decay and release.
+    /// The default envelope shape. A 10 ms attack, 80% sustain and 100 ms decay and release, with
+    /// linear segments.
     fn default() -> Self {
+        Self::new(0.01, 0.1, 0.8, 0.1)
+    }
+}
+
+impl Envelope {
+    /// An envelope with linear segments. The times are in seconds and the sustain is a level, each
+    /// of them clamped into the 0.0 to 1.0 range of their parameters.
+    pub const fn new(attack: f32, decay: f32, sustain: f32, release: f32) -> Self {
         Self {
-            attack: 0.01,
-            decay: 0.1,
-            sustain: 0.8,
-            release: 0.1,
+            attack: attack.clamp(0.0, 1.0),
+            decay: decay.clamp(0.0, 1.0),
+            sustain: sustain.clamp(0.0, 1.0),
+            release: release.clamp(0.0, 1.0),
+            curve: EnvelopeCurve::Linear,
         }
     }
+
+    /// The same envelope with a different curve for its segments.
+    pub const fn with_curve(self, curve: EnvelopeCurve) -> Self {
+        Self { curve, ..self }
+    }
+
+    /// The attack, decay, sustain and release, in the order of their parameters.
+    pub const fn segments(&self) -> [f32; 4] {
+        [self.attack, self.decay, self.sustain, self.release]
+    }
+
+    /// The attack, decay, sustain and release, in the order of their parameters.
+    pub fn segments_mut(&mut self) -> [&mut f32; 4] {
+        [
+            &mut self.attack,
+            &mut self.decay,
+            &mut self.sustain,
+            &mut self.release,
+        ]
+    }
+}
+
+/// The oscillator an envelope parameter belongs to, and which of its envelope's segments it is.
+/// Returns `None` if it isn't an envelope parameter.
+pub fn envelope_parameter(param_id: u32) -> Option<(usize, usize)> {
+    PARAMETER_ENVELOPES
+        .into_iter()
+        .enumerate()
+        .find(|&(_, attack)| (attack..attack + 4).contains(&param_id))
+        .map(|(osc, attack)| (osc, (param_id - attack) as usize))
 }
 
 #[derive(Default, Clone, Copy)]
@@ -50,9 +275,38 @@ pub enum Waveform {
     Skloinky,
     /// Randomly chooses a different waveform for every note pressed.
     Random,
+    /// A sum of sine harmonics with falling amplitudes, built up to the partials parameter.
+    Additive,
+    /// A square waveform whose high part takes up a share of the cycle set by the pulse width
+    /// parameter.
+    Pulse,
+    /// Noise which holds each random value for a cycle of the note.
+    SampleHold,
+    /// Noise which falls off by about 3 dB per octave, softer and darker than white noise.
+    PinkNoise,
 }
 
 impl Waveform {
+    /// Every waveform which plays a sound of its own, so all but [`Waveform::Random`].
+    const CONCRETE: [Waveform; 11] = [
+        Waveform::Sine,
+        Waveform::Triangle,
+        Waveform::Square,
+        Waveform::Saw,
+        Waveform::Noise,
+        Waveform::Sploinky,
+        Waveform::Skloinky,
+        Waveform::Additive,
+        Waveform::Pulse,
+        Waveform::SampleHold,
+        Waveform::PinkNoise,
+    ];
+
+    /// Chooses any waveform other than [`Waveform::Random`].
+    pub fn random(rng: &mut SmallRng) -> Self {
+        Self::CONCRETE[rng.random_range(0..Self::CONCRETE.len())]
+    }
+
     pub const fn as_str(self) -> &'static str {
         match self {
             Waveform::Sine => "Sine",
@@ -63,6 +317,10 @@ impl Waveform {
             Waveform::Sploinky => "Sploinky",
             Waveform::Skloinky => "Skloinky",
             Waveform::Random => "Random",
+            Waveform::Additive => "Additive",
+            Waveform::Pulse => "Pulse",
+            Waveform::SampleHold => "Sample & Hold",
+            Waveform::PinkNoise => "Pink Noise",
         }
     }
 }
@@ -75,11 +333,56 @@ impl From<Waveform> for f64 {
 
 impl From<f64> for Waveform {
     fn from(clap_value: f64) -> Self {
-        debug_assert!(clap_value as c_int <= Waveform::Random as c_int);
+        debug_assert!(enum_discriminant(clap_value) <= Waveform::PinkNoise as c_int);
 
         // SAFETY:
         // Waveform is #[repr(C)] which guarantees it being the same size and alignement as a c_int.
-        unsafe { std::mem::transmute::<c_int, Self>(clap_value as c_int) }
+        unsafe { std::mem::transmute::<c_int, Self>(enum_discriminant(clap_value)) }
+    }
+}
+
+/// The processing quality of the waveforms.
+#[derive(Default, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub enum Quality {
+    /// Naive waveforms. They alias, but they're the cheapest on the CPU.
+    Draft,
+    /// Band-limited (polyblep) triangle, square, saw and pulse waveforms.
+    #[default]
+    Normal,
+    /// Band-limited waveforms, with the oscillators oversampled at least 2x on top of whatever the
+    /// oversampling is set to.
+    High,
+}
+
+impl Quality {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Quality::Draft => "Draft",
+            Quality::Normal => "Normal",
+            Quality::High => "High",
+        }
+    }
+
+    /// Whether the band-limited versions of the waveforms are used.
+    pub const fn band_limited(self) -> bool {
+        !matches!(self, Quality::Draft)
+    }
+}
+
+impl From<Quality> for f64 {
+    fn from(quality: Quality) -> Self {
+        quality as c_int as f64
+    }
+}
+
+impl From<f64> for Quality {
+    fn from(clap_value: f64) -> Self {
+        debug_assert!(enum_discriminant(clap_value) <= Quality::High as c_int);
+
+        // SAFETY:
+        // Quality is #[repr(C)] which guarantees it being the same size and alignement as a c_int.
+        unsafe { std::mem::transmute::<c_int, Self>(enum_discriminant(clap_value)) }
     }
 }
 
@@ -90,6 +393,14 @@ pub enum Modulation {
     None,
     Phase,
     Evil,
+    /// Oscillators 1 and 3 modulate each other's phase.
+    Cross,
+    /// Oscillator 3 hard syncs oscillator 1.
+    Sync,
+    /// Oscillator 1 is multiplied by oscillator 3.
+    Ring,
+    /// Oscillator 3 modulates oscillator 1's frequency.
+    Frequency,
 }
 
 impl Modulation {
@@ -98,6 +409,10 @@ impl Modulation {
             Modulation::None => "None",
             Modulation::Phase => "Phase",
             Modulation::Evil => "Evil",
+            Modulation::Cross => "Cross",
+            Modulation::Sync => "Sync",
+            Modulation::Ring => "Ring",
+            Modulation::Frequency => "FM",
         }
     }
 }
@@ -110,155 +425,2147 @@ impl From<Modulation> for f64 {
 
 impl From<f64> for Modulation {
     fn from(clap_value: f64) -> Self {
-        debug_assert!(clap_value as c_int <= Modulation::Evil as c_int);
+        debug_assert!(enum_discriminant(clap_value) <= Modulation::Frequency as c_int);
 
         // SAFETY:
         // Modulation is #[repr(C)] which guarantees it being the same size and alignement as a c_int.
-        unsafe { std::mem::transmute::<c_int, Self>(clap_value as c_int) }
+        unsafe { std::mem::transmute::<c_int, Self>(enum_discriminant(clap_value)) }
     }
 }
 
-pub struct Fox3oscShared {
-    /* --Temperament Data-- */
-    pub n_tet: f32,
-    pub step_name: &'static str,
-    /// The amount of steps which can be shifted per oscillator either up or down. This is 2 octaves.
-    /// 
-    /// It should be noted that the parameters for shifting pitch range from `0..=pitch_amount*2`, 
-    /// so this value in the context of those parameters correspond to a pitch shift of +0 steps on
-    /// an oscillator.
-    pub pitch_amount: f64,
-    /// Number of notes that can be heard taking into account pitch shifting. This value is the pitch
-    /// amount times 2, plus the number of keys.
-    pub notes_nr: usize,
+/// How the oscillators are connected to each other during phase modulation. Modulators aren't heard
+/// themselves, their level sets how deep they modulate instead.
+#[derive(Default, Clone, Copy)]
+#[repr(C)]
+pub enum Algorithm {
+    /// Oscillator 3 modulates oscillator 1, oscillator 2 is heard as is.
+    #[default]
+    ThreeToOne,
+    /// Oscillator 3 modulates oscillator 2, which modulates oscillator 1.
+    Series,
+    /// Oscillators 2 and 3 both modulate oscillator 1.
+    Stack,
+    /// Oscillator 3 modulates both oscillators 1 and 2.
+    Branch,
+    /// Every oscillator is heard as is, same as having no modulation.
+    Parallel,
+}
 
-    /* --Parameters-- */
-    envelope: RwLock<Envelope>,
-    waveform: RwLock<[Waveform; OSC_NR]>,
-    levels: RwLock<[f32; OSC_NR]>,
-    hq: RwLock<[bool; OSC_NR]>,
-    modulation: RwLock<Modulation>,
-    pitch: RwLock<[f64; OSC_NR]>,
+impl Algorithm {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Algorithm::ThreeToOne => "3 > 1",
+            Algorithm::Series => "3 > 2 > 1",
+            Algorithm::Stack => "2 + 3 > 1",
+            Algorithm::Branch => "3 > 1 + 2",
+            Algorithm::Parallel => "Parallel",
+        }
+    }
+
+    /// The routing table of the algorithm, where `routing[carrier][modulator]` is whether
+    /// `modulator` modulates `carrier`. Modulators always come after the oscillators they modulate.
+    pub const fn routing(self) -> [[bool; OSC_NR]; OSC_NR] {
+        match self {
+            Algorithm::ThreeToOne => [
+                [false, false, true],
+                [false, false, false],
+                [false, false, false],
+            ],
+            Algorithm::Series => [
+                [false, true, false],
+                [false, false, true],
+                [false, false, false],
+            ],
+            Algorithm::Stack => [
+                [false, true, true],
+                [false, false, false],
+                [false, false, false],
+            ],
+            Algorithm::Branch => [
+                [false, false, true],
+                [false, false, true],
+                [false, false, false],
+            ],
+            Algorithm::Parallel => [[false; OSC_NR]; OSC_NR],
+        }
+    }
 }
 
-impl PluginShared<'_> for Fox3oscShared {}
+impl From<Algorithm> for f64 {
+    fn from(algorithm: Algorithm) -> Self {
+        algorithm as c_int as f64
+    }
+}
 
-impl Fox3oscShared {
-    pub fn new(n_tet: f32) -> Self {
-        let pitch_amount = (n_tet as f64) * 2.0;
-        let notes_nr = (pitch_amount as usize) * 2 + KEYS_NR;
-        let step_name = match n_tet {
-            12.0 => "semitones",
-            24.0 => "quarter tones",
-            _ => "steps",
-        };
+impl From<f64> for Algorithm {
+    fn from(clap_value: f64) -> Self {
+        debug_assert!(enum_discriminant(clap_value) <= Algorithm::Parallel as c_int);
 
-        Self {
-            n_tet,
-            pitch_amount,
-            notes_nr,
-            step_name,
-            envelope: Default::default(),
-            waveform: Default::default(),
-            modulation: Default::default(),
-            levels: RwLock::new([1.0, 0.0, 0.0]),
-            hq: RwLock::new([true; OSC_NR]),
-            pitch: RwLock::new([pitch_amount; OSC_NR]),
+        // SAFETY:
+        // Algorithm is #[repr(C)] which guarantees it being the same size and alignement as a c_int.
+        unsafe { std::mem::transmute::<c_int, Self>(enum_discriminant(clap_value)) }
+    }
+}
+
+/// The part of the keyboard an oscillator plays in, relative to the split point.
+#[derive(Default, Clone, Copy)]
+#[repr(C)]
+pub enum Zone {
+    /// The whole keyboard.
+    #[default]
+    Both,
+    /// Only the keys below the split point.
+    Lower,
+    /// Only the split point and the keys above it.
+    Upper,
+}
+
+impl Zone {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Zone::Both => "Both",
+            Zone::Lower => "Lower",
+            Zone::Upper => "Upper",
         }
     }
 
-    const PARAMETER_READ_ERR: PluginError =
-        PluginError::Message("Failed to acquire parameter read lock");
+    /// Whether a key is in the zone when the keyboard is split at `split`.
+    pub const fn contains(self, note: usize, split: usize) -> bool {
+        match self {
+            Zone::Both => true,
+            Zone::Lower => note < split,
+            Zone::Upper => note >= split,
+        }
+    }
+}
 
-    const PARAMETER_WRITE_ERR: PluginError =
-        PluginError::Message("Failed to acquire parameter write lock");
+impl From<Zone> for f64 {
+    fn from(zone: Zone) -> Self {
+        zone as c_int as f64
+    }
+}
 
-    /// Process a potential parameter event. Returns the id of the parameter if it is one. Otherwise
-    /// returns `None`. Returns an error on failure to aquire the parameter read lock.
-    pub fn process_param_event(&self, event: &UnknownEvent) -> Result<Option<u32>, PluginError> {
-        if let Some(CoreEventSpace::ParamValue(event)) = event.as_core_event() {
-            let mut envelope = self.get_envelope_mut()?;
-            let mut waveforms = self.get_waveforms_mut()?;
-            let mut levels = self.get_levels_mut()?;
-            let mut hq = self.get_hq_mut()?;
-            let mut modulation = self.get_modulation_mut()?;
-            let mut pitch = self.get_pitch_mut()?;
-            let param_id: Option<u32> = event.param_id().map(|x| x.into());
+impl From<f64> for Zone {
+    fn from(clap_value: f64) -> Self {
+        debug_assert!(enum_discriminant(clap_value) <= Zone::Upper as c_int);
 
-            match param_id {
-                Some(PARAMETER_ATTACK) => envelope.attack = event.value() as f32,
-                Some(PARAMETER_DECAY) => envelope.decay = event.value() as f32,
-                Some(PARAMETER_SUSTAIN) => envelope.sustain = event.value() as f32,
-                Some(PARAMETER_RELEASE) => envelope.release = event.value() as f32,
-                Some(PARAMETER_WAVEFORM_1) => waveforms[0] = event.value().into(),
-                Some(PARAMETER_WAVEFORM_2) => waveforms[1] = event.value().into(),
-                Some(PARAMETER_WAVEFORM_3) => waveforms[2] = event.value().into(),
-                Some(PARAMETER_LEVEL_1) => levels[0] = event.value() as f32,
-                Some(PARAMETER_LEVEL_2) => levels[1] = event.value() as f32,
-                Some(PARAMETER_LEVEL_3) => levels[2] = event.value() as f32,
-                Some(PARAMETER_HQ_1) => hq[0] = event.value() != 0.0,
-                Some(PARAMETER_HQ_2) => hq[1] = event.value() != 0.0,
-                Some(PARAMETER_HQ_3) => hq[2] = event.value() != 0.0,
-                Some(PARAMETER_MODULATION) => *modulation = event.value().into(),
-                Some(PARAMETER_PITCH_1) => pitch[0] = event.value(),
-                Some(PARAMETER_PITCH_2) => pitch[1] = event.value(),
-                Some(PARAMETER_PITCH_3) => pitch[2] = event.value(),
-                _ => {}
-            }
+        // SAFETY:
+        // Zone is #[repr(C)] which guarantees it being the same size and alignement as a c_int.
+        unsafe { std::mem::transmute::<c_int, Self>(enum_discriminant(clap_value)) }
+    }
+}
 
-            Ok(param_id)
-        } else {
-            Ok(None)
+/// The response curve which maps a MIDI note velocity onto an amplitude.
+#[derive(Default, Clone, Copy)]
+#[repr(C)]
+pub enum VelocityCurve {
+    #[default]
+    Linear,
+    /// Soft notes get quieter, good for controllers that feel too sensitive.
+    Exponential,
+    /// Soft notes get louder, good for controllers that feel too stiff.
+    Logarithmic,
+    /// Eases in and out, compressing both ends of the velocity range.
+    SCurve,
+}
+
+impl VelocityCurve {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            VelocityCurve::Linear => "Linear",
+            VelocityCurve::Exponential => "Exponential",
+            VelocityCurve::Logarithmic => "Logarithmic",
+            VelocityCurve::SCurve => "S-Curve",
+        }
+    }
+
+    /// Maps a normalized velocity (0.0..=1.0) through the curve.
+    pub fn apply(self, velocity: f32) -> f32 {
+        match self {
+            VelocityCurve::Linear => velocity,
+            VelocityCurve::Exponential => velocity * velocity,
+            VelocityCurve::Logarithmic => velocity.sqrt(),
+            VelocityCurve::SCurve => velocity * velocity * (3.0 - 2.0 * velocity),
+        }
+    }
+}
+
+impl From<VelocityCurve> for f64 {
+    fn from(velocity_curve: VelocityCurve) -> Self {
+        velocity_curve as c_int as f64
+    }
+}
+
+impl From<f64> for VelocityCurve {
+    fn from(clap_value: f64) -> Self {
+        debug_assert!(enum_discriminant(clap_value) <= VelocityCurve::SCurve as c_int);
+
+        // SAFETY:
+        // VelocityCurve is #[repr(C)] which guarantees it being the same size and alignement as a
+        // c_int.
+        unsafe { std::mem::transmute::<c_int, Self>(enum_discriminant(clap_value)) }
+    }
+}
+
+/// When keys glide to their pitch from the previously played note.
+#[derive(Default, Clone, Copy)]
+#[repr(C)]
+pub enum GlideMode {
+    #[default]
+    Off,
+    Always,
+    /// Only glide when the new note overlaps a held note. A note played after letting go of every
+    /// key starts right at its pitch, like on a classic mono synth.
+    Legato,
+}
+
+impl GlideMode {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            GlideMode::Off => "Off",
+            GlideMode::Always => "Always",
+            GlideMode::Legato => "Legato",
+        }
+    }
+}
+
+impl From<GlideMode> for f64 {
+    fn from(glide_mode: GlideMode) -> Self {
+        glide_mode as c_int as f64
+    }
+}
+
+impl From<f64> for GlideMode {
+    fn from(clap_value: f64) -> Self {
+        debug_assert!(enum_discriminant(clap_value) <= GlideMode::Legato as c_int);
+
+        // SAFETY:
+        // GlideMode is #[repr(C)] which guarantees it being the same size and alignement as a c_int.
+        unsafe { std::mem::transmute::<c_int, Self>(enum_discriminant(clap_value)) }
+    }
+}
+
+/// How many notes sound at once.
+#[derive(Default, Clone, Copy)]
+#[repr(C)]
+pub enum PolyMode {
+    /// Every note gets its own voice.
+    #[default]
+    Poly,
+    /// A single voice plays the most recently pressed note, retriggering with every note. Letting go
+    /// of it goes back to the note held before it.
+    Mono,
+    /// Same as [`PolyMode::Mono`], except playing over a held note moves the voice over to the new
+    /// note without retriggering it.
+    Legato,
+}
+
+impl PolyMode {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            PolyMode::Poly => "Poly",
+            PolyMode::Mono => "Mono",
+            PolyMode::Legato => "Legato",
+        }
+    }
+}
+
+impl From<PolyMode> for f64 {
+    fn from(poly_mode: PolyMode) -> Self {
+        poly_mode as c_int as f64
+    }
+}
+
+impl From<f64> for PolyMode {
+    fn from(clap_value: f64) -> Self {
+        debug_assert!(enum_discriminant(clap_value) <= PolyMode::Legato as c_int);
+
+        // SAFETY:
+        // PolyMode is #[repr(C)] which guarantees it being the same size and alignement as a c_int.
+        unsafe { std::mem::transmute::<c_int, Self>(enum_discriminant(clap_value)) }
+    }
+}
+
+/// Where the oscillators' phases start when a key starts playing.
+#[derive(Default, Clone, Copy)]
+#[repr(C)]
+pub enum PhaseMode {
+    /// Every note starts at the start of the cycle, so every attack sounds the same.
+    #[default]
+    Retrigger,
+    /// Every note picks up from wherever its key's oscillators left off.
+    Free,
+    /// Every note starts at random phases, for some analog-style variation between notes.
+    Random,
+}
+
+impl PhaseMode {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            PhaseMode::Retrigger => "Retrigger",
+            PhaseMode::Free => "Free",
+            PhaseMode::Random => "Random",
         }
     }
+}
+
+impl From<PhaseMode> for f64 {
+    fn from(phase_mode: PhaseMode) -> Self {
+        phase_mode as c_int as f64
+    }
+}
+
+impl From<f64> for PhaseMode {
+    fn from(clap_value: f64) -> Self {
+        debug_assert!(enum_discriminant(clap_value) <= PhaseMode::Random as c_int);
 
-    pub fn get_envelope(&self) -> Result<RwLockReadGuard<'_, Envelope>, PluginError> {
-        self.envelope.read().or(Err(Self::PARAMETER_READ_ERR))
+        // SAFETY:
+        // PhaseMode is #[repr(C)] which guarantees it being the same size and alignement as a c_int.
+        unsafe { std::mem::transmute::<c_int, Self>(enum_discriminant(clap_value)) }
     }
+}
+
+/// How many times the sample rate the oscillators run at, before they're brought back down to it.
+/// Running them faster leaves room above Nyquist for what the modulation modes fold back.
+#[derive(Default, Clone, Copy)]
+#[repr(C)]
+pub enum Oversample {
+    #[default]
+    X1,
+    X2,
+    X4,
+}
 
-    pub fn get_envelope_mut(&self) -> Result<RwLockWriteGuard<'_, Envelope>, PluginError> {
-        self.envelope.write().or(Err(Self::PARAMETER_WRITE_ERR))
+impl Oversample {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Oversample::X1 => "1x",
+            Oversample::X2 => "2x",
+            Oversample::X4 => "4x",
+        }
     }
 
-    pub fn get_waveforms(&self) -> Result<RwLockReadGuard<'_, [Waveform; OSC_NR]>, PluginError> {
-        self.waveform.read().or(Err(Self::PARAMETER_READ_ERR))
+    /// The number of samples the oscillators run for every sample of the output.
+    pub const fn factor(self) -> usize {
+        match self {
+            Oversample::X1 => 1,
+            Oversample::X2 => 2,
+            Oversample::X4 => 4,
+        }
     }
+}
 
-    pub fn get_waveforms_mut(
-        &self,
-    ) -> Result<RwLockWriteGuard<'_, [Waveform; OSC_NR]>, PluginError> {
-        self.waveform.write().or(Err(Self::PARAMETER_WRITE_ERR))
+impl From<Oversample> for f64 {
+    fn from(oversample: Oversample) -> Self {
+        oversample as c_int as f64
     }
+}
+
+impl From<f64> for Oversample {
+    fn from(clap_value: f64) -> Self {
+        debug_assert!(enum_discriminant(clap_value) <= Oversample::X4 as c_int);
 
-    pub fn get_levels(&self) -> Result<RwLockReadGuard<'_, [f32; OSC_NR]>, PluginError> {
-        self.levels.read().or(Err(Self::PARAMETER_READ_ERR))
+        // SAFETY:
+        // Oversample is #[repr(C)] which guarantees it being the same size and alignement as a c_int.
+        unsafe { std::mem::transmute::<c_int, Self>(enum_discriminant(clap_value)) }
     }
+}
+
+/// How many equal steps every octave is split into. Every key, and every step of the pitch
+/// parameters, is one step of it.
+#[derive(Default, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub enum Temperament {
+    #[default]
+    Tet12,
+    Tet15,
+    Tet17,
+    Tet19,
+    Tet22,
+    Tet23,
+    Tet24,
+    Tet31,
+    Tet53,
+}
 
-    pub fn get_levels_mut(&self) -> Result<RwLockWriteGuard<'_, [f32; OSC_NR]>, PluginError> {
-        self.levels.write().or(Err(Self::PARAMETER_WRITE_ERR))
+impl Temperament {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Temperament::Tet12 => "12-TET",
+            Temperament::Tet15 => "15-TET",
+            Temperament::Tet17 => "17-TET",
+            Temperament::Tet19 => "19-TET",
+            Temperament::Tet22 => "22-TET",
+            Temperament::Tet23 => "23-TET",
+            Temperament::Tet24 => "24-TET",
+            Temperament::Tet31 => "31-TET",
+            Temperament::Tet53 => "53-TET",
+        }
     }
 
-    pub fn get_hq(&self) -> Result<RwLockReadGuard<'_, [bool; OSC_NR]>, PluginError> {
-        self.hq.read().or(Err(Self::PARAMETER_READ_ERR))
+    /// The number of steps in an octave.
+    pub const fn divisions(self) -> f32 {
+        match self {
+            Temperament::Tet12 => 12.0,
+            Temperament::Tet15 => 15.0,
+            Temperament::Tet17 => 17.0,
+            Temperament::Tet19 => 19.0,
+            Temperament::Tet22 => 22.0,
+            Temperament::Tet23 => 23.0,
+            Temperament::Tet24 => 24.0,
+            Temperament::Tet31 => 31.0,
+            Temperament::Tet53 => 53.0,
+        }
     }
 
-    pub fn get_hq_mut(&self) -> Result<RwLockWriteGuard<'_, [bool; OSC_NR]>, PluginError> {
-        self.hq.write().or(Err(Self::PARAMETER_WRITE_ERR))
+    /// What the pitch parameters count in.
+    pub const fn step_name(self) -> &'static str {
+        match self {
+            Temperament::Tet12 => "semitones",
+            Temperament::Tet24 => "quarter tones",
+            _ => "steps",
+        }
     }
+}
 
-    pub fn get_modulation(&self) -> Result<RwLockReadGuard<'_, Modulation>, PluginError> {
-        self.modulation.read().or(Err(Self::PARAMETER_READ_ERR))
+impl From<Temperament> for f64 {
+    fn from(temperament: Temperament) -> Self {
+        temperament as c_int as f64
     }
+}
 
-    pub fn get_modulation_mut(&self) -> Result<RwLockWriteGuard<'_, Modulation>, PluginError> {
-        self.modulation.write().or(Err(Self::PARAMETER_WRITE_ERR))
+impl From<f64> for Temperament {
+    fn from(clap_value: f64) -> Self {
+        debug_assert!(enum_discriminant(clap_value) <= Temperament::Tet53 as c_int);
+
+        // SAFETY:
+        // Temperament is #[repr(C)] which guarantees it being the same size and alignement as a c_int.
+        unsafe { std::mem::transmute::<c_int, Self>(enum_discriminant(clap_value)) }
     }
+}
 
-    pub fn get_pitch(&self) -> Result<RwLockReadGuard<'_, [f64; OSC_NR]>, PluginError> {
-        self.pitch.read().or(Err(Self::PARAMETER_READ_ERR))
+#[derive(Clone, Copy)]
+pub struct Glide {
+    pub mode: GlideMode,
+    /// How long it takes to glide to the new note in seconds.
+    pub time: f32,
+}
+
+impl Default for Glide {
+    /// Glide is off by default, taking 100 ms when turned on.
+    fn default() -> Self {
+        Self {
+            mode: GlideMode::default(),
+            time: 0.1,
+        }
     }
+}
+
+#[derive(Clone, Copy)]
+pub struct Unison {
+    /// How many detuned copies of itself every oscillator plays (1..=UNISON_MAX). A single copy is
+    /// just the oscillator.
+    pub count: u32,
+    /// How far the outermost copies are detuned from the oscillator's pitch in cents, the others
+    /// being spread evenly in between.
+    pub detune: f32,
+}
+
+impl Default for Unison {
+    /// Unison is off by default, with a mild detune when turned on.
+    fn default() -> Self {
+        Self {
+            count: 1,
+            detune: 15.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct ChorusSettings {
+    /// Rate of the LFO sweeping the delay lines in Hz.
+    pub rate: f32,
+    /// How far the LFO sweeps the delay lines (0.0..=1.0).
+    pub depth: f32,
+    /// Mix of the delayed signal into the dry signal (0.0..=1.0). The chorus is bypassed at 0.0.
+    pub mix: f32,
+}
+
+impl Default for ChorusSettings {
+    /// The chorus is off by default, with a slow and moderate sweep when turned on.
+    fn default() -> Self {
+        Self {
+            rate: 0.5,
+            depth: 0.5,
+            mix: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct DelaySettings {
+    /// Time between repeats in seconds.
+    pub time: f32,
+    /// How much of every repeat gets fed back into the delay (0.0..1.0).
+    pub feedback: f32,
+    /// Mix of the repeats into the dry signal (0.0..=1.0).
+    pub mix: f32,
+    /// Whether the time gets rounded to the nearest sixteenth note at the host's tempo.
+    pub sync: bool,
+}
+
+impl Default for DelaySettings {
+    /// The delay is off by default.
+    fn default() -> Self {
+        Self {
+            time: 0.35,
+            feedback: 0.4,
+            mix: 0.0,
+            sync: false,
+        }
+    }
+}
+
+/// The shape of the LFO.
+#[derive(Default, Clone, Copy)]
+#[repr(C)]
+pub enum LfoShape {
+    #[default]
+    Sine,
+    Triangle,
+    Square,
+}
+
+impl LfoShape {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            LfoShape::Sine => "Sine",
+            LfoShape::Triangle => "Triangle",
+            LfoShape::Square => "Square",
+        }
+    }
+}
+
+impl From<LfoShape> for f64 {
+    fn from(lfo_shape: LfoShape) -> Self {
+        lfo_shape as c_int as f64
+    }
+}
+
+impl From<f64> for LfoShape {
+    fn from(clap_value: f64) -> Self {
+        debug_assert!(enum_discriminant(clap_value) <= LfoShape::Square as c_int);
+
+        // SAFETY:
+        // LfoShape is #[repr(C)] which guarantees it being the same size and alignement as a c_int.
+        unsafe { std::mem::transmute::<c_int, Self>(enum_discriminant(clap_value)) }
+    }
+}
+
+/// What the LFO modulates.
+#[derive(Default, Clone, Copy)]
+#[repr(C)]
+pub enum LfoDestination {
+    /// Vibrato on every oscillator.
+    #[default]
+    Pitch,
+    /// Tremolo on every key.
+    Level,
+    /// Sweeps the cutoff of the filter.
+    Cutoff,
+}
+
+impl LfoDestination {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            LfoDestination::Pitch => "Pitch",
+            LfoDestination::Level => "Level",
+            LfoDestination::Cutoff => "Filter Cutoff",
+        }
+    }
+}
+
+impl From<LfoDestination> for f64 {
+    fn from(lfo_destination: LfoDestination) -> Self {
+        lfo_destination as c_int as f64
+    }
+}
+
+impl From<f64> for LfoDestination {
+    fn from(clap_value: f64) -> Self {
+        debug_assert!(enum_discriminant(clap_value) <= LfoDestination::Cutoff as c_int);
+
+        // SAFETY:
+        // LfoDestination is #[repr(C)] which guarantees it being the same size and alignement as a
+        // c_int.
+        unsafe { std::mem::transmute::<c_int, Self>(enum_discriminant(clap_value)) }
+    }
+}
+
+/// Length of a cycle of the LFO while it's synced to the host's tempo.
+#[derive(Default, Clone, Copy)]
+#[repr(C)]
+pub enum LfoDivision {
+    Whole,
+    Half,
+    #[default]
+    Quarter,
+    Eighth,
+    Sixteenth,
+}
+
+impl LfoDivision {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            LfoDivision::Whole => "1/1",
+            LfoDivision::Half => "1/2",
+            LfoDivision::Quarter => "1/4",
+            LfoDivision::Eighth => "1/8",
+            LfoDivision::Sixteenth => "1/16",
+        }
+    }
+
+    /// Length of a cycle in beats (quarter notes).
+    pub const fn beats(self) -> f64 {
+        match self {
+            LfoDivision::Whole => 4.0,
+            LfoDivision::Half => 2.0,
+            LfoDivision::Quarter => 1.0,
+            LfoDivision::Eighth => 0.5,
+            LfoDivision::Sixteenth => 0.25,
+        }
+    }
+}
+
+impl From<LfoDivision> for f64 {
+    fn from(lfo_division: LfoDivision) -> Self {
+        lfo_division as c_int as f64
+    }
+}
+
+impl From<f64> for LfoDivision {
+    fn from(clap_value: f64) -> Self {
+        debug_assert!(enum_discriminant(clap_value) <= LfoDivision::Sixteenth as c_int);
+
+        // SAFETY:
+        // LfoDivision is #[repr(C)] which guarantees it being the same size and alignement as a
+        // c_int.
+        unsafe { std::mem::transmute::<c_int, Self>(enum_discriminant(clap_value)) }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct LfoSettings {
+    pub shape: LfoShape,
+    /// Rate of the LFO in Hz. It's ignored while synced to a tempo the host provides.
+    pub rate: f32,
+    /// How far the LFO swings its destination (0.0..=1.0). The LFO does nothing at 0.0.
+    pub depth: f32,
+    pub destination: LfoDestination,
+    /// Whether the LFO does a cycle every `division` at the host's tempo, instead of going at
+    /// `rate`.
+    pub sync: bool,
+    pub division: LfoDivision,
+}
+
+impl Default for LfoSettings {
+    /// The LFO is off by default, with a moderate vibrato when turned up.
+    fn default() -> Self {
+        Self {
+            shape: LfoShape::default(),
+            rate: 5.0,
+            depth: 0.0,
+            destination: LfoDestination::default(),
+            sync: false,
+            division: LfoDivision::default(),
+        }
+    }
+}
+
+/// Settings of the frequency modulation type, where oscillator 3 follows oscillator 1 at a ratio.
+#[derive(Clone, Copy)]
+pub struct FmSettings {
+    /// Index into [`MOD_RATIOS`] of oscillator 3's frequency ratio to oscillator 1.
+    pub ratio: usize,
+    /// How far oscillator 3 swings oscillator 1's frequency (0.0..=1.0). At 1.0, oscillator 3 at
+    /// full scale swings it all the way from 0 Hz to twice its pitch.
+    pub depth: f32,
+}
+
+impl FmSettings {
+    /// Oscillator 3's frequency ratio to oscillator 1.
+    pub fn ratio(self) -> f32 {
+        MOD_RATIOS[self.ratio]
+    }
+}
+
+impl Default for FmSettings {
+    /// Oscillator 3 follows oscillator 1 at the same pitch by default.
+    fn default() -> Self {
+        Self {
+            ratio: 1,
+            depth: 0.5,
+        }
+    }
+}
+
+/// The order in which the arpeggiator cycles through held notes.
+#[derive(Default, Clone, Copy)]
+#[repr(C)]
+pub enum ArpMode {
+    #[default]
+    Up,
+    Down,
+    UpDown,
+    Random,
+}
+
+impl ArpMode {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            ArpMode::Up => "Up",
+            ArpMode::Down => "Down",
+            ArpMode::UpDown => "Up/Down",
+            ArpMode::Random => "Random",
+        }
+    }
+}
+
+impl From<ArpMode> for f64 {
+    fn from(arp_mode: ArpMode) -> Self {
+        arp_mode as c_int as f64
+    }
+}
+
+impl From<f64> for ArpMode {
+    fn from(clap_value: f64) -> Self {
+        debug_assert!(enum_discriminant(clap_value) <= ArpMode::Random as c_int);
+
+        // SAFETY:
+        // ArpMode is #[repr(C)] which guarantees it being the same size and alignement as a c_int.
+        unsafe { std::mem::transmute::<c_int, Self>(enum_discriminant(clap_value)) }
+    }
+}
+
+/// The length of an arpeggiator step as a note division.
+#[derive(Default, Clone, Copy)]
+#[repr(C)]
+pub enum ArpRate {
+    Quarter,
+    #[default]
+    Eighth,
+    Sixteenth,
+    ThirtySecond,
+}
+
+impl ArpRate {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            ArpRate::Quarter => "1/4",
+            ArpRate::Eighth => "1/8",
+            ArpRate::Sixteenth => "1/16",
+            ArpRate::ThirtySecond => "1/32",
+        }
+    }
+
+    /// Length of a step in beats (quarter notes).
+    pub const fn beats(self) -> f64 {
+        match self {
+            ArpRate::Quarter => 1.0,
+            ArpRate::Eighth => 0.5,
+            ArpRate::Sixteenth => 0.25,
+            ArpRate::ThirtySecond => 0.125,
+        }
+    }
+}
+
+impl From<ArpRate> for f64 {
+    fn from(arp_rate: ArpRate) -> Self {
+        arp_rate as c_int as f64
+    }
+}
+
+impl From<f64> for ArpRate {
+    fn from(clap_value: f64) -> Self {
+        debug_assert!(enum_discriminant(clap_value) <= ArpRate::ThirtySecond as c_int);
+
+        // SAFETY:
+        // ArpRate is #[repr(C)] which guarantees it being the same size and alignement as a c_int.
+        unsafe { std::mem::transmute::<c_int, Self>(enum_discriminant(clap_value)) }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct ArpSettings {
+    pub on: bool,
+    pub mode: ArpMode,
+    pub rate: ArpRate,
+    /// Number of octaves the pattern spans (1..=ARP_OCTAVES_MAX).
+    pub octaves: u32,
+}
+
+impl Default for ArpSettings {
+    /// The arpeggiator is off by default, going up in eighth notes over a single octave.
+    fn default() -> Self {
+        Self {
+            on: false,
+            mode: ArpMode::default(),
+            rate: ArpRate::default(),
+            octaves: 1,
+        }
+    }
+}
+
+pub struct Fox3oscShared {
+    /* --Temperament Data-- */
+    /// The temperament the plugin is built for, which the temperament parameter starts out at.
+    pub default_temperament: Temperament,
+    /// The amount of steps which can be shifted per oscillator either up or down, [`PITCH_AMOUNT`].
+    /// It stays the same whatever temperament is played in, since hosts only pick up the pitch
+    /// parameters' ranges when the plugin restarts.
+    ///
+    /// It should be noted that the parameters for shifting pitch range from `0..=pitch_amount*2`,
+    /// so this value in the context of those parameters correspond to a pitch shift of +0 steps on
+    /// an oscillator.
+    pub pitch_amount: f64,
+    /// Number of notes that can be heard taking into account pitch shifting. This value is the pitch
+    /// amount times 2, plus the number of keys.
+    pub notes_nr: usize,
+
+    /* --Parameters-- */
+    // Every field of every parameter sits in an atomic of its own, so neither the audio thread nor
+    // the main thread ever waits on the other. A parameter read while it's being changed can come
+    // out with some fields changed and some not yet, which at worst lasts a block.
+    envelope: ParamCell<[Envelope; OSC_NR]>,
+    waveform: ParamCell<[Waveform; OSC_NR]>,
+    levels: ParamCell<[f32; OSC_NR]>,
+    quality: ParamCell<Quality>,
+    modulation: ParamCell<Modulation>,
+    pitch: ParamCell<[f64; OSC_NR]>,
+    /// Fine tuning per oscillator in cents, on top of the pitch.
+    fine: ParamCell<[f32; OSC_NR]>,
+    /// How much the note velocity affects the amplitude of a key. At 0.0 every key is played at full
+    /// amplitude, at 1.0 amplitude follows velocity fully.
+    velocity_sensitivity: ParamCell<f32>,
+    velocity_curve: ParamCell<VelocityCurve>,
+    /// How much a key's amplitude follows its distance from [`KEY_TRACK_CENTER`], from -1.0 (higher
+    /// keys get quieter) to 1.0 (higher keys get louder).
+    ///
+    /// [`KEY_TRACK_CENTER`]: crate::consts::KEY_TRACK_CENTER
+    key_track: ParamCell<f32>,
+    /// Scales the depth of oscillator 3's modulation of oscillator 1. At 1.0 the modulation depth is
+    /// capped where aliasing starts getting nasty, anything above that trades aliasing for
+    /// brightness.
+    mod_index: ParamCell<f32>,
+    pitch_envelope: ParamCell<PitchEnvelope>,
+    arp_settings: ParamCell<ArpSettings>,
+    master_tuning: ParamCell<MasterTuning>,
+    /// Tilts the spectrum of the noise waveform, from -1.0 (darker) to 1.0 (brighter).
+    noise_color: ParamCell<f32>,
+    /// Silences oscillators without touching their levels.
+    mute: ParamCell<[bool; OSC_NR]>,
+    /// When any oscillator is soloed, only soloed oscillators are heard. Overrides mute.
+    solo: ParamCell<[bool; OSC_NR]>,
+    glide: ParamCell<Glide>,
+
+    partials: ParamCell<u32>,
+    /// Pre-gain into each oscillator's wavefolder. 1.0 leaves the oscillator untouched.
+    fold: ParamCell<[f32; OSC_NR]>,
+    /// Amount of oscillator 3's output fed back into its own phase during phase modulation.
+    fm_feedback: ParamCell<f32>,
+    algorithm: ParamCell<Algorithm>,
+    /// Whether oscillator 3's pitch snaps to integer ratios of oscillator 1's.
+    ratio_lock: ParamCell<bool>,
+    /// How far the oscillators get panned apart, ordered by their fine tuning.
+    stereo_spread: ParamCell<f32>,
+    chorus: ParamCell<ChorusSettings>,
+    delay: ParamCell<DelaySettings>,
+    /// Threshold of the output limiter in dBFS.
+    limit: ParamCell<f32>,
+    /// How much the velocity of a note scales its modulation depth.
+    vel_to_mod: ParamCell<f32>,
+    /// Scale of the modulation in the evil modulation type, on top of the modulation index.
+    evil_depth: ParamCell<f32>,
+    /// How much the level of each oscillator follows the velocity, on top of the velocity
+    /// sensitivity.
+    osc_velocity: ParamCell<[f32; OSC_NR]>,
+    /// The key the keyboard is split into a lower and an upper zone at.
+    split: ParamCell<usize>,
+    /// The zone each oscillator plays in.
+    zones: ParamCell<[Zone; OSC_NR]>,
+    /// Multiplier of the width of the band-limited waveforms' polyblep correction.
+    blep_width: ParamCell<f32>,
+    /// Where each oscillator sits in the stereo field, from -1.0 (left) to 1.0 (right).
+    pan: ParamCell<[f32; OSC_NR]>,
+    /// How far the pitch wheel bends in either direction, in semitones.
+    pitch_bend_range: ParamCell<u32>,
+    /// Share of the cycle the pulse waveform spends high.
+    pulse_width: ParamCell<f32>,
+    /// The filter's cutoff, from 0.0 to 1.0. The frequency goes up exponentially with it, from 20 Hz
+    /// to 20 kHz.
+    cutoff: ParamCell<f32>,
+    /// How much the filter rings around its cutoff, from 0.0 to 1.0.
+    resonance: ParamCell<f32>,
+    /// The global LFO's shape, rate, depth and destination.
+    lfo: ParamCell<LfoSettings>,
+    /// Master output gain in dB.
+    gain: ParamCell<f32>,
+    /// How much harder note-offs shorten the release.
+    release_vel_amount: ParamCell<f32>,
+
+    fm: ParamCell<FmSettings>,
+    sub_level: ParamCell<f32>,
+    unison: ParamCell<Unison>,
+    phase_mode: ParamCell<PhaseMode>,
+    max_voices: ParamCell<u32>,
+    poly_mode: ParamCell<PolyMode>,
+    aftertouch_amount: ParamCell<f32>,
+    /// Frequency in Hz of A4, which the temperament is tuned around.
+    concert_pitch: ParamCell<f32>,
+    /// Shift of each oscillator in octaves, on top of its pitch.
+    octave: ParamCell<[i32; OSC_NR]>,
+    /// How hard the output gets driven into the soft clipper, from 0.0 (clean) to 1.0.
+    drive: ParamCell<f32>,
+    oversample: ParamCell<Oversample>,
+    temperament: ParamCell<Temperament>,
+
+    /* --Read-only Parameters-- */
+    /// Number of voices sounding as of the last processed block.
+    voice_count: ParamCell<usize>,
+    /* --Host State-- */
+    /// Whether the host is rendering offline, as set through the render extension.
+    offline: ParamCell<bool>,
+    /// Parameters the plugin changed on its own, whose new values haven't been sent to the host
+    /// yet.
+    pending_param_changes: [AtomicBool; PARAMETER_NR as usize],
+    /// The offset the host's modulation puts on each parameter, on top of the value it's set to.
+    param_mod: [ParamCell<f64>; PARAMETER_NR as usize],
+}
+
+impl PluginShared<'_> for Fox3oscShared {}
+
+impl Fox3oscShared {
+    pub fn new(temperament: Temperament) -> Self {
+        Self {
+            default_temperament: temperament,
+            pitch_amount: PITCH_AMOUNT,
+            notes_nr: MAX_NOTES_NR,
+            envelope: Default::default(),
+            waveform: Default::default(),
+            modulation: Default::default(),
+            levels: ParamCell::new([1.0, 0.0, 0.0]),
+            quality: Default::default(),
+            pitch: ParamCell::new([PITCH_AMOUNT; OSC_NR]),
+            fine: Default::default(),
+            velocity_sensitivity: ParamCell::new(1.0),
+            velocity_curve: Default::default(),
+            key_track: Default::default(),
+            mod_index: ParamCell::new(1.0),
+            pitch_envelope: Default::default(),
+            arp_settings: Default::default(),
+            master_tuning: Default::default(),
+            noise_color: Default::default(),
+            mute: Default::default(),
+            solo: Default::default(),
+            glide: Default::default(),
+            partials: ParamCell::new(8),
+            fold: ParamCell::new([1.0; OSC_NR]),
+            fm_feedback: Default::default(),
+            algorithm: Default::default(),
+            ratio_lock: Default::default(),
+            stereo_spread: Default::default(),
+            chorus: Default::default(),
+            delay: Default::default(),
+            limit: Default::default(),
+            vel_to_mod: Default::default(),
+            evil_depth: ParamCell::new(1.0),
+            osc_velocity: ParamCell::new([1.0; OSC_NR]),
+            split: ParamCell::new(SPLIT_DEFAULT),
+            zones: Default::default(),
+            blep_width: ParamCell::new(1.0),
+            pan: ParamCell::new([0.0; OSC_NR]),
+            pitch_bend_range: ParamCell::new(2),
+            pulse_width: ParamCell::new(0.5),
+            cutoff: ParamCell::new(1.0),
+            resonance: Default::default(),
+            lfo: Default::default(),
+            gain: Default::default(),
+            release_vel_amount: Default::default(),
+            fm: Default::default(),
+            sub_level: Default::default(),
+            unison: Default::default(),
+            phase_mode: Default::default(),
+            max_voices: ParamCell::new(KEYS_NR as u32),
+            poly_mode: Default::default(),
+            aftertouch_amount: ParamCell::new(1.0),
+            concert_pitch: ParamCell::new(REFERENCE_FREQUENCY),
+            octave: Default::default(),
+            drive: Default::default(),
+            oversample: Default::default(),
+            temperament: ParamCell::new(temperament),
+            voice_count: Default::default(),
+            offline: Default::default(),
+            pending_param_changes: [const { AtomicBool::new(false) }; PARAMETER_NR as usize],
+            param_mod: std::array::from_fn(|_| ParamCell::new(0.0)),
+        }
+    }
+
+    /// Get the current value of a parameter. Returns `None` if there's no parameter with the id.
+    pub fn get_param_value(&self, param_id: u32) -> Option<f64> {
+        let envelope = self.get_envelope();
+        let waveform = self.get_waveforms();
+        let levels = self.get_levels();
+        let quality = self.get_quality();
+        let modulation = self.get_modulation();
+        let pitch = self.get_pitch();
+        let fine = self.get_fine();
+        let velocity_sensitivity = self.get_velocity_sensitivity();
+        let velocity_curve = self.get_velocity_curve();
+        let key_track = self.get_key_track();
+        let mod_index = self.get_mod_index();
+        let pitch_envelope = self.get_pitch_envelope();
+        let arp_settings = self.get_arp_settings();
+        let master_tuning = self.get_master_tuning();
+        let noise_color = self.get_noise_color();
+        let mute = self.get_mute();
+        let solo = self.get_solo();
+        let glide = self.get_glide();
+        let partials = self.get_partials();
+        let fold = self.get_fold();
+        let fm_feedback = self.get_fm_feedback();
+        let algorithm = self.get_algorithm();
+        let ratio_lock = self.get_ratio_lock();
+        let stereo_spread = self.get_stereo_spread();
+        let chorus = self.get_chorus();
+        let delay = self.get_delay();
+        let limit = self.get_limit();
+        let vel_to_mod = self.get_vel_to_mod();
+        let evil_depth = self.get_evil_depth();
+        let osc_velocity = self.get_osc_velocity();
+        let split = self.get_split();
+        let zones = self.get_zones();
+        let blep_width = self.get_blep_width();
+        let pan = self.get_pan();
+        let pitch_bend_range = self.get_pitch_bend_range();
+        let pulse_width = self.get_pulse_width();
+        let cutoff = self.get_cutoff();
+        let resonance = self.get_resonance();
+        let lfo = self.get_lfo();
+        let gain = self.get_gain();
+        let release_vel_amount = self.get_release_vel_amount();
+        let fm = self.get_fm();
+        let sub_level = self.get_sub_level();
+        let unison = self.get_unison();
+        let phase_mode = self.get_phase_mode();
+        let max_voices = self.get_max_voices();
+        let poly_mode = self.get_poly_mode();
+        let aftertouch_amount = self.get_aftertouch_amount();
+        let concert_pitch = self.get_concert_pitch();
+        let octave = self.get_octave();
+        let drive = self.get_drive();
+        let oversample = self.get_oversample();
+        let temperament = self.get_temperament();
+        let voice_count = self.get_voice_count();
+
+        match param_id {
+            PARAMETER_WAVEFORM_1 => Some((waveform[0]).into()),
+            PARAMETER_WAVEFORM_2 => Some((waveform[1]).into()),
+            PARAMETER_WAVEFORM_3 => Some((waveform[2]).into()),
+            PARAMETER_LEVEL_1 => Some(levels[0] as f64),
+            PARAMETER_LEVEL_2 => Some(levels[1] as f64),
+            PARAMETER_LEVEL_3 => Some(levels[2] as f64),
+            PARAMETER_HQ_1..=PARAMETER_HQ_3 => Some(quality.band_limited() as u8 as f64),
+            PARAMETER_QUALITY => Some(quality.into()),
+            PARAMETER_MODULATION => Some(modulation.into()),
+            PARAMETER_PITCH_1 => Some(pitch[0]),
+            PARAMETER_PITCH_2 => Some(pitch[1]),
+            PARAMETER_PITCH_3 => Some(pitch[2]),
+            PARAMETER_FINE_1 => Some(fine[0] as f64),
+            PARAMETER_FINE_2 => Some(fine[1] as f64),
+            PARAMETER_FINE_3 => Some(fine[2] as f64),
+            PARAMETER_VEL_SENS => Some(velocity_sensitivity as f64),
+            PARAMETER_VEL_CURVE => Some(velocity_curve.into()),
+            PARAMETER_ENV_CURVE => Some(envelope[0].curve.into()),
+            PARAMETER_KEY_TRACK => Some(key_track as f64),
+            PARAMETER_MOD_INDEX => Some(mod_index as f64),
+            PARAMETER_PITCH_ENV_AMOUNT_1 => Some(pitch_envelope.amount[0] as f64),
+            PARAMETER_PITCH_ENV_AMOUNT_2 => Some(pitch_envelope.amount[1] as f64),
+            PARAMETER_PITCH_ENV_AMOUNT_3 => Some(pitch_envelope.amount[2] as f64),
+            PARAMETER_PITCH_ENV_TIME => Some(pitch_envelope.time as f64),
+            PARAMETER_ARP_ON => Some(arp_settings.on as u8 as f64),
+            PARAMETER_ARP_MODE => Some(arp_settings.mode.into()),
+            PARAMETER_ARP_RATE => Some(arp_settings.rate.into()),
+            PARAMETER_ARP_OCTAVES => Some(arp_settings.octaves as f64),
+            PARAMETER_TRANSPOSE => Some(master_tuning.transpose),
+            PARAMETER_MASTER_TUNE => Some(master_tuning.tune as f64),
+            PARAMETER_NOISE_COLOR => Some(noise_color as f64),
+            PARAMETER_MUTE_1 => Some(mute[0] as u8 as f64),
+            PARAMETER_MUTE_2 => Some(mute[1] as u8 as f64),
+            PARAMETER_MUTE_3 => Some(mute[2] as u8 as f64),
+            PARAMETER_SOLO_1 => Some(solo[0] as u8 as f64),
+            PARAMETER_SOLO_2 => Some(solo[1] as u8 as f64),
+            PARAMETER_SOLO_3 => Some(solo[2] as u8 as f64),
+            PARAMETER_GLIDE_MODE => Some(glide.mode.into()),
+            PARAMETER_GLIDE_TIME => Some(glide.time as f64),
+            PARAMETER_PARTIALS => Some(partials as f64),
+            PARAMETER_FOLD_1 => Some(fold[0] as f64),
+            PARAMETER_FOLD_2 => Some(fold[1] as f64),
+            PARAMETER_FOLD_3 => Some(fold[2] as f64),
+            PARAMETER_FM_FEEDBACK => Some(fm_feedback as f64),
+            PARAMETER_ALGORITHM => Some(algorithm.into()),
+            PARAMETER_RATIO_LOCK => Some(ratio_lock as u8 as f64),
+            PARAMETER_STEREO_SPREAD => Some(stereo_spread as f64),
+            PARAMETER_CHORUS_RATE => Some(chorus.rate as f64),
+            PARAMETER_CHORUS_DEPTH => Some(chorus.depth as f64),
+            PARAMETER_CHORUS_MIX => Some(chorus.mix as f64),
+            PARAMETER_DELAY_TIME => Some(delay.time as f64),
+            PARAMETER_DELAY_FEEDBACK => Some(delay.feedback as f64),
+            PARAMETER_DELAY_MIX => Some(delay.mix as f64),
+            PARAMETER_DELAY_SYNC => Some(delay.sync as u8 as f64),
+            PARAMETER_LIMIT => Some(limit as f64),
+            PARAMETER_VEL_TO_MOD => Some(vel_to_mod as f64),
+            PARAMETER_EVIL_DEPTH => Some(evil_depth as f64),
+            PARAMETER_VEL_1 => Some(osc_velocity[0] as f64),
+            PARAMETER_VEL_2 => Some(osc_velocity[1] as f64),
+            PARAMETER_VEL_3 => Some(osc_velocity[2] as f64),
+            PARAMETER_SPLIT => Some(split as f64),
+            PARAMETER_ZONE_1 => Some(zones[0].into()),
+            PARAMETER_ZONE_2 => Some(zones[1].into()),
+            PARAMETER_ZONE_3 => Some(zones[2].into()),
+            PARAMETER_BLEP_WIDTH => Some(blep_width as f64),
+            PARAMETER_PAN_1 => Some(pan[0] as f64),
+            PARAMETER_PAN_2 => Some(pan[1] as f64),
+            PARAMETER_PAN_3 => Some(pan[2] as f64),
+            PARAMETER_PITCH_BEND_RANGE => Some(pitch_bend_range as f64),
+            PARAMETER_PWM => Some(pulse_width as f64),
+            PARAMETER_CUTOFF => Some(cutoff as f64),
+            PARAMETER_RESONANCE => Some(resonance as f64),
+            PARAMETER_LFO_SHAPE => Some(lfo.shape.into()),
+            PARAMETER_LFO_RATE => Some(lfo.rate as f64),
+            PARAMETER_LFO_DEPTH => Some(lfo.depth as f64),
+            PARAMETER_LFO_DEST => Some(lfo.destination.into()),
+            PARAMETER_LFO_SYNC => Some(lfo.sync as u8 as f64),
+            PARAMETER_LFO_DIVISION => Some(lfo.division.into()),
+            PARAMETER_GAIN => Some(gain as f64),
+            PARAMETER_RELEASE_VEL_AMOUNT => Some(release_vel_amount as f64),
+            PARAMETER_FM_RATIO => Some(fm.ratio as f64),
+            PARAMETER_FM_DEPTH => Some(fm.depth as f64),
+            PARAMETER_SUB_LEVEL => Some(sub_level as f64),
+            PARAMETER_UNISON_COUNT => Some(unison.count as f64),
+            PARAMETER_UNISON_DETUNE => Some(unison.detune as f64),
+            PARAMETER_PHASE_MODE => Some(phase_mode.into()),
+            PARAMETER_MAX_VOICES => Some(max_voices as f64),
+            PARAMETER_POLY_MODE => Some(poly_mode.into()),
+            PARAMETER_AFTERTOUCH_AMOUNT => Some(aftertouch_amount as f64),
+            PARAMETER_TUNING_HZ => Some(concert_pitch as f64),
+            PARAMETER_OCTAVE_1 => Some(octave[0] as f64),
+            PARAMETER_OCTAVE_2 => Some(octave[1] as f64),
+            PARAMETER_OCTAVE_3 => Some(octave[2] as f64),
+            PARAMETER_DRIVE => Some(drive as f64),
+            PARAMETER_OVERSAMPLE => Some(oversample.into()),
+            PARAMETER_TEMPERAMENT => Some(temperament.into()),
+            PARAMETER_VOICES => Some(voice_count as f64),
+            PARAMETER_RANDOMIZE | PARAMETER_INIT => Some(0.0),
+            _ => self::envelope_parameter(param_id)
+                .map(|(osc, segment)| envelope[osc].segments()[segment] as f64),
+        }
+    }
+
+    /// Whether an event sets a momentary trigger parameter, like [`PARAMETER_RANDOMIZE`] or
+    /// [`PARAMETER_INIT`], on.
+    pub fn is_triggered(event: &UnknownEvent, param_id: u32) -> bool {
+        matches!(
+            event.as_core_event(),
+            Some(CoreEventSpace::ParamValue(event))
+                if event.param_id().map(u32::from) == Some(param_id) && event.value() >= 0.5
+        )
+    }
+
+    /// Queue a parameter the plugin changed on its own, so its new value gets sent to the host the
+    /// next time the parameter changes are flushed.
+    pub fn queue_param_change(&self, param_id: u32) {
+        self.pending_param_changes[param_id as usize].store(true, Ordering::Relaxed);
+    }
+
+    /// Queue every parameter, for when the plugin changed the whole patch.
+    pub fn queue_all_param_changes(&self) {
+        for pending in &self.pending_param_changes {
+            pending.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Write the current value of every queued parameter as output events and empty the queue, so
+    /// the host picks up changes the plugin made to its own parameters.
+    pub fn flush_param_changes(&self, output: &mut OutputEvents) -> Result<(), PluginError> {
+        for param_id in 0..PARAMETER_NR {
+            if !self.pending_param_changes[param_id as usize].swap(false, Ordering::Relaxed) {
+                continue;
+            }
+
+            if let Some(value) = self.get_param_value(param_id) {
+                Self::write_param_value(output, param_id, value)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write the value of a single parameter as an output event.
+    pub fn write_param_value(
+        output: &mut OutputEvents,
+        param_id: u32,
+        value: f64,
+    ) -> Result<(), PluginError> {
+        output
+            .try_push(ParamValueEvent::new(
+                0,
+                ClapId::new(param_id),
+                Pckn::match_all(),
+                value,
+                Cookie::empty(),
+            ))
+            .or(Err(PluginError::Message("Failed to push parameter event")))
+    }
+
+    /// Assign random values to the parameters which shape the sound of the patch. Envelope times
+    /// are kept short enough to stay playable, and at least one oscillator is always audible.
+    ///
+    /// Parameters which are about how the patch is played rather than how it sounds, like
+    /// velocity, key tracking, tuning, quality and the arpeggiator, are left alone.
+    pub fn randomize(&self, rng: &mut SmallRng) {
+        let mut envelope = self.get_envelope_mut();
+        let mut waveforms = self.get_waveforms_mut();
+        let mut levels = self.get_levels_mut();
+        let mut pitch = self.get_pitch_mut();
+        let mut fine = self.get_fine_mut();
+        let mut pitch_envelope = self.get_pitch_envelope_mut();
+
+        // Squaring biases envelope times towards the short end.
+        let curve = (rng.random_range(0..=EnvelopeCurve::Exponential as c_int) as f64).into();
+        for envelope in envelope.iter_mut() {
+            envelope.attack = rng.random::<f32>().powi(2);
+            envelope.decay = 0.05 + rng.random::<f32>().powi(2) * 0.95;
+            envelope.sustain = rng.random();
+            envelope.release = 0.02 + rng.random::<f32>().powi(2) * 0.98;
+            envelope.curve = curve;
+        }
+
+        for waveform in waveforms.iter_mut() {
+            *waveform = Waveform::random(rng);
+        }
+
+        for level in levels.iter_mut() {
+            *level = rng.random();
+        }
+
+        let loudest = rng.random_range(0..OSC_NR);
+        levels[loudest] = levels[loudest].max(0.5);
+
+        // Keep the oscillators from summing past full scale.
+        let total: f32 = levels.iter().sum();
+        if total > 1.0 {
+            levels.iter_mut().for_each(|level| *level /= total);
+        }
+
+        self.modulation
+            .store((rng.random_range(0..=Modulation::Frequency as c_int) as f64).into());
+        self.algorithm
+            .store((rng.random_range(0..=Algorithm::Parallel as c_int) as f64).into());
+        self.mod_index.store(rng.random_range(0.0..2.0));
+
+        // Oscillators are shifted by whole octaves and detuned slightly, so they stay in tune with
+        // each other. Temperaments finer than the default one can't shift a whole octave.
+        for pitch in pitch.iter_mut() {
+            let octave = rng.random_range(-1..=1) as f64;
+            *pitch = (self.pitch_amount + octave * self.n_tet() as f64)
+                .clamp(0.0, self.pitch_amount * 2.0);
+        }
+
+        for fine in fine.iter_mut() {
+            *fine = rng.random_range(-10.0..10.0);
+        }
+
+        for amount in pitch_envelope.amount.iter_mut() {
+            *amount = if rng.random_bool(0.25) {
+                rng.random_range(-12.0..12.0)
+            } else {
+                0.0
+            };
+        }
+
+        pitch_envelope.time = rng.random_range(0.01..0.3);
+        self.noise_color.store(rng.random_range(-1.0..1.0));
+
+        // The guards only store the patch once they're dropped, which has to happen before it gets
+        // queued for the host.
+        drop((envelope, waveforms, levels, pitch, fine, pitch_envelope));
+        self.queue_all_param_changes();
+    }
+
+    /// Copy the sound of oscillator `from` onto oscillator `to`: its envelope, waveform, level,
+    /// pitch, octave, fine tuning, pitch envelope amount, fold and velocity amount. The mute and solo switches stay where they are.
+    ///
+    /// The modulation routing is never touched. While a modulation is on, the level of
+    /// [`OSC_MOD`] is its modulation depth rather than its volume, so the level is only copied
+    /// between oscillators playing the same role.
+    pub fn copy_oscillator(&self, from: usize, to: usize) {
+        debug_assert!(from < OSC_NR && to < OSC_NR);
+
+        self.get_envelope_mut()[to] = self.get_envelope()[from];
+        self.get_waveforms_mut()[to] = self.get_waveforms()[from];
+        self.get_pitch_mut()[to] = self.get_pitch()[from];
+        self.get_octave_mut()[to] = self.get_octave()[from];
+        self.get_fine_mut()[to] = self.get_fine()[from];
+        self.get_pitch_envelope_mut().amount[to] = self.get_pitch_envelope().amount[from];
+        self.get_fold_mut()[to] = self.get_fold()[from];
+        self.get_osc_velocity_mut()[to] = self.get_osc_velocity()[from];
+
+        let modulation = self.get_modulation();
+        if matches!(modulation, Modulation::None) || (from == OSC_MOD) == (to == OSC_MOD) {
+            self.get_levels_mut()[to] = self.get_levels()[from];
+            self.queue_param_change(PARAMETER_LEVEL_1 + to as u32);
+        }
+
+        for param_id in [
+            PARAMETER_WAVEFORM_1,
+            PARAMETER_PITCH_1,
+            PARAMETER_OCTAVE_1,
+            PARAMETER_FINE_1,
+            PARAMETER_PITCH_ENV_AMOUNT_1,
+            PARAMETER_FOLD_1,
+            PARAMETER_VEL_1,
+        ] {
+            self.queue_param_change(param_id + to as u32);
+        }
+
+        for param_id in PARAMETER_ENVELOPES[to]..PARAMETER_ENVELOPES[to] + 4 {
+            self.queue_param_change(param_id);
+        }
+    }
+
+    /// Whether an oscillator should be heard according to its mute and solo switches.
+    pub fn is_audible(&self, osc: usize) -> bool {
+        let mute = self.get_mute();
+        let solo = self.get_solo();
+
+        if solo.contains(&true) {
+            solo[osc]
+        } else {
+            !mute[osc]
+        }
+    }
+
+    /// Restore every parameter to its default value, giving an init patch.
+    pub fn reset_params(&self) {
+        self.apply_patch_state(&Self::new(self.default_temperament).to_patch_state());
+        self.queue_all_param_changes();
+    }
+
+    /// Take a snapshot of every parameter of the patch, for saving it.
+    pub fn to_patch_state(&self) -> PatchState {
+        PatchState {
+            envelope: self.get_envelope(),
+            waveforms: self.get_waveforms(),
+            levels: self.get_levels(),
+            quality: self.get_quality(),
+            modulation: self.get_modulation(),
+            pitch: self.get_pitch(),
+            fine: self.get_fine(),
+            velocity_sensitivity: self.get_velocity_sensitivity(),
+            velocity_curve: self.get_velocity_curve(),
+            key_track: self.get_key_track(),
+            mod_index: self.get_mod_index(),
+            pitch_envelope: self.get_pitch_envelope(),
+            arp_settings: self.get_arp_settings(),
+            master_tuning: self.get_master_tuning(),
+            noise_color: self.get_noise_color(),
+            mute: self.get_mute(),
+            solo: self.get_solo(),
+            glide: self.get_glide(),
+            partials: self.get_partials(),
+            fold: self.get_fold(),
+            fm_feedback: self.get_fm_feedback(),
+            algorithm: self.get_algorithm(),
+            ratio_lock: self.get_ratio_lock(),
+            stereo_spread: self.get_stereo_spread(),
+            chorus: self.get_chorus(),
+            delay: self.get_delay(),
+            limit: self.get_limit(),
+            vel_to_mod: self.get_vel_to_mod(),
+            evil_depth: self.get_evil_depth(),
+            osc_velocity: self.get_osc_velocity(),
+            split: self.get_split(),
+            zones: self.get_zones(),
+            blep_width: self.get_blep_width(),
+            pan: self.get_pan(),
+            pitch_bend_range: self.get_pitch_bend_range(),
+            pulse_width: self.get_pulse_width(),
+            cutoff: self.get_cutoff(),
+            resonance: self.get_resonance(),
+            lfo: self.get_lfo(),
+            gain: self.get_gain(),
+            release_vel_amount: self.get_release_vel_amount(),
+            fm: self.get_fm(),
+            sub_level: self.get_sub_level(),
+            unison: self.get_unison(),
+            phase_mode: self.get_phase_mode(),
+            max_voices: self.get_max_voices(),
+            poly_mode: self.get_poly_mode(),
+            aftertouch_amount: self.get_aftertouch_amount(),
+            concert_pitch: self.get_concert_pitch(),
+            octave: self.get_octave(),
+            drive: self.get_drive(),
+            oversample: self.get_oversample(),
+            temperament: self.get_temperament(),
+        }
+    }
+
+    /// Set every parameter of the patch at once. A block processed while the patch is being applied
+    /// can still pick up some of the old parameters.
+    pub fn apply_patch_state(&self, state: &PatchState) {
+        self.envelope.store(state.envelope);
+        self.waveform.store(state.waveforms);
+        self.levels.store(state.levels);
+        self.quality.store(state.quality);
+        self.modulation.store(state.modulation);
+        self.pitch.store(state.pitch);
+        self.fine.store(state.fine);
+        self.velocity_sensitivity.store(state.velocity_sensitivity);
+        self.velocity_curve.store(state.velocity_curve);
+        self.key_track.store(state.key_track);
+        self.mod_index.store(state.mod_index);
+        self.pitch_envelope.store(state.pitch_envelope);
+        self.arp_settings.store(state.arp_settings);
+        self.master_tuning.store(state.master_tuning);
+        self.noise_color.store(state.noise_color);
+        self.mute.store(state.mute);
+        self.solo.store(state.solo);
+        self.glide.store(state.glide);
+        self.partials.store(state.partials);
+        self.fold.store(state.fold);
+        self.fm_feedback.store(state.fm_feedback);
+        self.algorithm.store(state.algorithm);
+        self.ratio_lock.store(state.ratio_lock);
+        self.stereo_spread.store(state.stereo_spread);
+        self.chorus.store(state.chorus);
+        self.delay.store(state.delay);
+        self.limit.store(state.limit);
+        self.vel_to_mod.store(state.vel_to_mod);
+        self.evil_depth.store(state.evil_depth);
+        self.osc_velocity.store(state.osc_velocity);
+        self.split.store(state.split);
+        self.zones.store(state.zones);
+        self.blep_width.store(state.blep_width);
+        self.pan.store(state.pan);
+        self.pitch_bend_range.store(state.pitch_bend_range);
+        self.pulse_width.store(state.pulse_width);
+        self.cutoff.store(state.cutoff);
+        self.resonance.store(state.resonance);
+        self.lfo.store(state.lfo);
+        self.gain.store(state.gain);
+        self.release_vel_amount.store(state.release_vel_amount);
+        self.fm.store(state.fm);
+        self.sub_level.store(state.sub_level);
+        self.unison.store(state.unison);
+        self.phase_mode.store(state.phase_mode);
+        self.max_voices.store(state.max_voices);
+        self.poly_mode.store(state.poly_mode);
+        self.aftertouch_amount.store(state.aftertouch_amount);
+        self.concert_pitch.store(state.concert_pitch);
+        self.octave.store(state.octave);
+        self.drive.store(state.drive);
+        self.oversample.store(state.oversample);
+        self.temperament.store(state.temperament);
+    }
+
+    /// Process a potential parameter event. Returns the id of the parameter if it is one. Otherwise
+    /// returns `None`.
+    pub fn process_param_event(&self, event: &UnknownEvent) -> Option<u32> {
+        if let Some(CoreEventSpace::ParamValue(event)) = event.as_core_event() {
+            let param_id: Option<u32> = event.param_id().map(|x| x.into());
+
+            if let Some((osc, segment)) = param_id.and_then(self::envelope_parameter) {
+                *self.get_envelope_mut()[osc].segments_mut()[segment] = event.value() as f32;
+            }
+
+            match param_id {
+                // Every envelope shares the same curve.
+                Some(PARAMETER_ENV_CURVE) => {
+                    let curve = event.value().into();
+                    self.get_envelope_mut()
+                        .iter_mut()
+                        .for_each(|envelope| envelope.curve = curve);
+                }
+                Some(PARAMETER_WAVEFORM_1) => self.get_waveforms_mut()[0] = event.value().into(),
+                Some(PARAMETER_WAVEFORM_2) => self.get_waveforms_mut()[1] = event.value().into(),
+                Some(PARAMETER_WAVEFORM_3) => self.get_waveforms_mut()[2] = event.value().into(),
+                Some(PARAMETER_LEVEL_1) => self.get_levels_mut()[0] = event.value() as f32,
+                Some(PARAMETER_LEVEL_2) => self.get_levels_mut()[1] = event.value() as f32,
+                Some(PARAMETER_LEVEL_3) => self.get_levels_mut()[2] = event.value() as f32,
+                Some(PARAMETER_QUALITY) => self.quality.store(event.value().into()),
+                Some(PARAMETER_MODULATION) => self.modulation.store(event.value().into()),
+                Some(PARAMETER_PITCH_1) => self.get_pitch_mut()[0] = event.value(),
+                Some(PARAMETER_PITCH_2) => self.get_pitch_mut()[1] = event.value(),
+                Some(PARAMETER_PITCH_3) => self.get_pitch_mut()[2] = event.value(),
+                Some(PARAMETER_FINE_1) => self.get_fine_mut()[0] = event.value() as f32,
+                Some(PARAMETER_FINE_2) => self.get_fine_mut()[1] = event.value() as f32,
+                Some(PARAMETER_FINE_3) => self.get_fine_mut()[2] = event.value() as f32,
+                Some(PARAMETER_VEL_SENS) => self.velocity_sensitivity.store(event.value() as f32),
+                Some(PARAMETER_VEL_CURVE) => self.velocity_curve.store(event.value().into()),
+                Some(PARAMETER_KEY_TRACK) => self.key_track.store(event.value() as f32),
+                Some(PARAMETER_MOD_INDEX) => self.mod_index.store(event.value() as f32),
+                Some(PARAMETER_PITCH_ENV_AMOUNT_1) => {
+                    self.get_pitch_envelope_mut().amount[0] = event.value() as f32
+                }
+                Some(PARAMETER_PITCH_ENV_AMOUNT_2) => {
+                    self.get_pitch_envelope_mut().amount[1] = event.value() as f32
+                }
+                Some(PARAMETER_PITCH_ENV_AMOUNT_3) => {
+                    self.get_pitch_envelope_mut().amount[2] = event.value() as f32
+                }
+                Some(PARAMETER_PITCH_ENV_TIME) => {
+                    self.get_pitch_envelope_mut().time = event.value() as f32
+                }
+                Some(PARAMETER_ARP_ON) => self.get_arp_settings_mut().on = event.value() != 0.0,
+                Some(PARAMETER_ARP_MODE) => self.get_arp_settings_mut().mode = event.value().into(),
+                Some(PARAMETER_ARP_RATE) => self.get_arp_settings_mut().rate = event.value().into(),
+                Some(PARAMETER_ARP_OCTAVES) => {
+                    self.get_arp_settings_mut().octaves =
+                        (event.value() as u32).clamp(1, ARP_OCTAVES_MAX)
+                }
+                Some(PARAMETER_TRANSPOSE) => self.get_master_tuning_mut().transpose = event.value(),
+                Some(PARAMETER_MASTER_TUNE) => {
+                    self.get_master_tuning_mut().tune = event.value() as f32
+                }
+                Some(PARAMETER_NOISE_COLOR) => self.noise_color.store(event.value() as f32),
+                Some(PARAMETER_MUTE_1) => self.get_mute_mut()[0] = event.value() != 0.0,
+                Some(PARAMETER_MUTE_2) => self.get_mute_mut()[1] = event.value() != 0.0,
+                Some(PARAMETER_MUTE_3) => self.get_mute_mut()[2] = event.value() != 0.0,
+                Some(PARAMETER_SOLO_1) => self.get_solo_mut()[0] = event.value() != 0.0,
+                Some(PARAMETER_SOLO_2) => self.get_solo_mut()[1] = event.value() != 0.0,
+                Some(PARAMETER_SOLO_3) => self.get_solo_mut()[2] = event.value() != 0.0,
+                Some(PARAMETER_GLIDE_MODE) => self.get_glide_mut().mode = event.value().into(),
+                Some(PARAMETER_GLIDE_TIME) => self.get_glide_mut().time = event.value() as f32,
+                Some(PARAMETER_PARTIALS) => self
+                    .partials
+                    .store((event.value() as u32).clamp(1, PARTIALS_MAX)),
+                Some(PARAMETER_FOLD_1) => self.get_fold_mut()[0] = event.value() as f32,
+                Some(PARAMETER_FOLD_2) => self.get_fold_mut()[1] = event.value() as f32,
+                Some(PARAMETER_FOLD_3) => self.get_fold_mut()[2] = event.value() as f32,
+                Some(PARAMETER_FM_FEEDBACK) => self
+                    .fm_feedback
+                    .store((event.value() as f32).clamp(0.0, 1.0)),
+                Some(PARAMETER_ALGORITHM) => self.algorithm.store(event.value().into()),
+                Some(PARAMETER_RATIO_LOCK) => self.ratio_lock.store(event.value() != 0.0),
+                Some(PARAMETER_STEREO_SPREAD) => self
+                    .stereo_spread
+                    .store((event.value() as f32).clamp(0.0, 1.0)),
+                Some(PARAMETER_CHORUS_RATE) => self.get_chorus_mut().rate = event.value() as f32,
+                Some(PARAMETER_CHORUS_DEPTH) => self.get_chorus_mut().depth = event.value() as f32,
+                Some(PARAMETER_CHORUS_MIX) => self.get_chorus_mut().mix = event.value() as f32,
+                Some(PARAMETER_DELAY_TIME) => self.get_delay_mut().time = event.value() as f32,
+                Some(PARAMETER_DELAY_FEEDBACK) => {
+                    self.get_delay_mut().feedback = event.value() as f32
+                }
+                Some(PARAMETER_DELAY_MIX) => self.get_delay_mut().mix = event.value() as f32,
+                Some(PARAMETER_DELAY_SYNC) => self.get_delay_mut().sync = event.value() != 0.0,
+                Some(PARAMETER_LIMIT) => self.limit.store((event.value() as f32).min(0.0)),
+                Some(PARAMETER_VEL_TO_MOD) => self.vel_to_mod.store(event.value() as f32),
+                Some(PARAMETER_EVIL_DEPTH) => self.evil_depth.store(event.value() as f32),
+                Some(PARAMETER_VEL_1) => self.get_osc_velocity_mut()[0] = event.value() as f32,
+                Some(PARAMETER_VEL_2) => self.get_osc_velocity_mut()[1] = event.value() as f32,
+                Some(PARAMETER_VEL_3) => self.get_osc_velocity_mut()[2] = event.value() as f32,
+                Some(PARAMETER_SPLIT) => {
+                    self.split.store((event.value() as usize).min(KEYS_NR - 1))
+                }
+                Some(PARAMETER_ZONE_1) => self.get_zones_mut()[0] = event.value().into(),
+                Some(PARAMETER_ZONE_2) => self.get_zones_mut()[1] = event.value().into(),
+                Some(PARAMETER_ZONE_3) => self.get_zones_mut()[2] = event.value().into(),
+                Some(PARAMETER_BLEP_WIDTH) => self.blep_width.store(event.value() as f32),
+                Some(PARAMETER_PAN_1) => {
+                    self.get_pan_mut()[0] = (event.value() as f32).clamp(-1.0, 1.0)
+                }
+                Some(PARAMETER_PAN_2) => {
+                    self.get_pan_mut()[1] = (event.value() as f32).clamp(-1.0, 1.0)
+                }
+                Some(PARAMETER_PAN_3) => {
+                    self.get_pan_mut()[2] = (event.value() as f32).clamp(-1.0, 1.0)
+                }
+                Some(PARAMETER_PITCH_BEND_RANGE) => self
+                    .pitch_bend_range
+                    .store((event.value() as u32).min(PITCH_BEND_RANGE_MAX)),
+                Some(PARAMETER_PWM) => self
+                    .pulse_width
+                    .store((event.value() as f32).clamp(0.0, 1.0)),
+                Some(PARAMETER_CUTOFF) => self.cutoff.store((event.value() as f32).clamp(0.0, 1.0)),
+                Some(PARAMETER_RESONANCE) => {
+                    self.resonance.store((event.value() as f32).clamp(0.0, 1.0))
+                }
+                Some(PARAMETER_LFO_SHAPE) => self.get_lfo_mut().shape = event.value().into(),
+                Some(PARAMETER_LFO_RATE) => self.get_lfo_mut().rate = event.value() as f32,
+                Some(PARAMETER_LFO_DEPTH) => self.get_lfo_mut().depth = event.value() as f32,
+                Some(PARAMETER_LFO_DEST) => self.get_lfo_mut().destination = event.value().into(),
+                Some(PARAMETER_LFO_SYNC) => self.get_lfo_mut().sync = event.value() != 0.0,
+                Some(PARAMETER_LFO_DIVISION) => self.get_lfo_mut().division = event.value().into(),
+                Some(PARAMETER_GAIN) => {
+                    self.gain.store((event.value() as f32).clamp(GAIN_MIN, 0.0))
+                }
+                Some(PARAMETER_RELEASE_VEL_AMOUNT) => {
+                    self.release_vel_amount.store(event.value() as f32)
+                }
+                Some(PARAMETER_FM_RATIO) => {
+                    self.get_fm_mut().ratio = (event.value() as usize).min(MOD_RATIOS.len() - 1)
+                }
+                Some(PARAMETER_FM_DEPTH) => self.get_fm_mut().depth = event.value() as f32,
+                Some(PARAMETER_SUB_LEVEL) => self.sub_level.store(event.value() as f32),
+                Some(PARAMETER_UNISON_COUNT) => {
+                    self.get_unison_mut().count = (event.value() as u32).clamp(1, UNISON_MAX as u32)
+                }
+                Some(PARAMETER_UNISON_DETUNE) => {
+                    self.get_unison_mut().detune = event.value() as f32
+                }
+                Some(PARAMETER_PHASE_MODE) => self.phase_mode.store(event.value().into()),
+                Some(PARAMETER_MAX_VOICES) => self
+                    .max_voices
+                    .store((event.value() as u32).clamp(1, KEYS_NR as u32)),
+                Some(PARAMETER_POLY_MODE) => self.poly_mode.store(event.value().into()),
+                Some(PARAMETER_AFTERTOUCH_AMOUNT) => {
+                    self.aftertouch_amount.store(event.value() as f32)
+                }
+                Some(PARAMETER_TUNING_HZ) => self.concert_pitch.store(event.value() as f32),
+                Some(PARAMETER_OCTAVE_1) => self.get_octave_mut()[0] = event.value() as i32,
+                Some(PARAMETER_OCTAVE_2) => self.get_octave_mut()[1] = event.value() as i32,
+                Some(PARAMETER_OCTAVE_3) => self.get_octave_mut()[2] = event.value() as i32,
+                Some(PARAMETER_DRIVE) => self.drive.store(event.value() as f32),
+                Some(PARAMETER_OVERSAMPLE) => self.oversample.store(event.value().into()),
+                Some(PARAMETER_TEMPERAMENT) => self.temperament.store(event.value().into()),
+                _ => {}
+            }
+
+            param_id
+        } else if let Some(CoreEventSpace::ParamMod(event)) = event.as_core_event() {
+            let param_id: Option<u32> = event.param_id().map(|x| x.into());
+
+            // The amount replaces the previous offset rather than adding to it, so an amount of 0.0
+            // brings the parameter back to its value. Modulation aimed at a single note is left to
+            // its key.
+            let per_note = !matches!((event.key(), event.note_id()), (Match::All, Match::All));
+            if !per_note
+                && let Some(param_id) = param_id.filter(|&param_id| Self::is_modulatable(param_id))
+            {
+                self.param_mod[param_id as usize].store(event.amount());
+            }
+
+            param_id
+        } else {
+            None
+        }
+    }
+
+    pub fn get_envelope(&self) -> [Envelope; OSC_NR] {
+        self.envelope.load()
+    }
+
+    pub fn get_envelope_mut(&self) -> ParamWriteGuard<'_, [Envelope; OSC_NR]> {
+        ParamWriteGuard::new(&self.envelope)
+    }
+
+    pub fn get_waveforms(&self) -> [Waveform; OSC_NR] {
+        self.waveform.load()
+    }
+
+    pub fn get_waveforms_mut(&self) -> ParamWriteGuard<'_, [Waveform; OSC_NR]> {
+        ParamWriteGuard::new(&self.waveform)
+    }
+
+    pub fn get_levels(&self) -> [f32; OSC_NR] {
+        self.levels.load()
+    }
+
+    pub fn get_levels_mut(&self) -> ParamWriteGuard<'_, [f32; OSC_NR]> {
+        ParamWriteGuard::new(&self.levels)
+    }
+
+    pub fn get_quality(&self) -> Quality {
+        self.quality.load()
+    }
+
+    pub fn get_modulation(&self) -> Modulation {
+        self.modulation.load()
+    }
+
+    pub fn get_pitch(&self) -> [f64; OSC_NR] {
+        self.pitch.load()
+    }
+
+    pub fn get_pitch_mut(&self) -> ParamWriteGuard<'_, [f64; OSC_NR]> {
+        ParamWriteGuard::new(&self.pitch)
+    }
+
+    pub fn get_fine(&self) -> [f32; OSC_NR] {
+        self.fine.load()
+    }
+
+    pub fn get_fine_mut(&self) -> ParamWriteGuard<'_, [f32; OSC_NR]> {
+        ParamWriteGuard::new(&self.fine)
+    }
+
+    pub fn get_velocity_sensitivity(&self) -> f32 {
+        self.velocity_sensitivity.load()
+    }
+
+    pub fn get_velocity_curve(&self) -> VelocityCurve {
+        self.velocity_curve.load()
+    }
+
+    pub fn get_key_track(&self) -> f32 {
+        self.key_track.load()
+    }
+
+    pub fn get_mod_index(&self) -> f32 {
+        self.mod_index.load()
+    }
+
+    pub fn get_pitch_envelope(&self) -> PitchEnvelope {
+        self.pitch_envelope.load()
+    }
+
+    pub fn get_pitch_envelope_mut(&self) -> ParamWriteGuard<'_, PitchEnvelope> {
+        ParamWriteGuard::new(&self.pitch_envelope)
+    }
+
+    pub fn get_arp_settings(&self) -> ArpSettings {
+        self.arp_settings.load()
+    }
+
+    pub fn get_arp_settings_mut(&self) -> ParamWriteGuard<'_, ArpSettings> {
+        ParamWriteGuard::new(&self.arp_settings)
+    }
+
+    pub fn get_master_tuning(&self) -> MasterTuning {
+        self.master_tuning.load()
+    }
+
+    pub fn get_master_tuning_mut(&self) -> ParamWriteGuard<'_, MasterTuning> {
+        ParamWriteGuard::new(&self.master_tuning)
+    }
+
+    pub fn get_noise_color(&self) -> f32 {
+        self.noise_color.load()
+    }
+
+    pub fn get_mute(&self) -> [bool; OSC_NR] {
+        self.mute.load()
+    }
+
+    pub fn get_mute_mut(&self) -> ParamWriteGuard<'_, [bool; OSC_NR]> {
+        ParamWriteGuard::new(&self.mute)
+    }
+
+    pub fn get_solo(&self) -> [bool; OSC_NR] {
+        self.solo.load()
+    }
+
+    pub fn get_solo_mut(&self) -> ParamWriteGuard<'_, [bool; OSC_NR]> {
+        ParamWriteGuard::new(&self.solo)
+    }
+
+    pub fn get_voice_count(&self) -> usize {
+        self.voice_count.load()
+    }
+
+    pub fn get_voice_count_mut(&self) -> ParamWriteGuard<'_, usize> {
+        ParamWriteGuard::new(&self.voice_count)
+    }
+
+    pub fn get_glide(&self) -> Glide {
+        self.glide.load()
+    }
+
+    pub fn get_glide_mut(&self) -> ParamWriteGuard<'_, Glide> {
+        ParamWriteGuard::new(&self.glide)
+    }
+
+    pub fn get_partials(&self) -> u32 {
+        self.partials.load()
+    }
+
+    pub fn get_fold(&self) -> [f32; OSC_NR] {
+        self.fold.load()
+    }
+
+    pub fn get_fold_mut(&self) -> ParamWriteGuard<'_, [f32; OSC_NR]> {
+        ParamWriteGuard::new(&self.fold)
+    }
+
+    pub fn get_fm_feedback(&self) -> f32 {
+        self.fm_feedback.load()
+    }
+
+    pub fn get_algorithm(&self) -> Algorithm {
+        self.algorithm.load()
+    }
+
+    pub fn get_ratio_lock(&self) -> bool {
+        self.ratio_lock.load()
+    }
+
+    pub fn get_stereo_spread(&self) -> f32 {
+        self.stereo_spread.load()
+    }
+
+    pub fn get_chorus(&self) -> ChorusSettings {
+        self.chorus.load()
+    }
+
+    pub fn get_chorus_mut(&self) -> ParamWriteGuard<'_, ChorusSettings> {
+        ParamWriteGuard::new(&self.chorus)
+    }
+
+    pub fn get_delay(&self) -> DelaySettings {
+        self.delay.load()
+    }
+
+    pub fn get_delay_mut(&self) -> ParamWriteGuard<'_, DelaySettings> {
+        ParamWriteGuard::new(&self.delay)
+    }
+
+    pub fn get_limit(&self) -> f32 {
+        self.limit.load()
+    }
+
+    pub fn get_offline(&self) -> bool {
+        self.offline.load()
+    }
+
+    pub fn get_offline_mut(&self) -> ParamWriteGuard<'_, bool> {
+        ParamWriteGuard::new(&self.offline)
+    }
+
+    /// Whether the host can modulate a parameter. These are the parameters declared with
+    /// `IS_MODULATABLE` in their info.
+    fn is_modulatable(param_id: u32) -> bool {
+        matches!(
+            param_id,
+            PARAMETER_ATTACK_1..=PARAMETER_RELEASE_1
+                | PARAMETER_ATTACK_2..=PARAMETER_RELEASE_3
+                | PARAMETER_LEVEL_1..=PARAMETER_LEVEL_3
+                | PARAMETER_PITCH_1..=PARAMETER_PITCH_3
+                | PARAMETER_FINE_1..=PARAMETER_FINE_3
+                | PARAMETER_MOD_INDEX
+                | PARAMETER_EVIL_DEPTH
+        )
+    }
+
+    /// Adds the host's modulation of a parameter on top of its value. The sum still has to be
+    /// clamped into the parameter's range.
+    fn modulated(&self, param_id: u32, value: f64) -> f64 {
+        value + self.param_mod[param_id as usize].load()
+    }
+
+    /// The envelope of every oscillator keys play with, modulation included.
+    pub fn modulated_envelope(&self) -> [Envelope; OSC_NR] {
+        let mut envelope = self.get_envelope();
+        for (envelope, attack) in envelope.iter_mut().zip(PARAMETER_ENVELOPES) {
+            for (param_id, value) in (attack..).zip(envelope.segments_mut()) {
+                *value = (self.modulated(param_id, *value as f64) as f32).clamp(0.0, 1.0);
+            }
+        }
+
+        envelope
+    }
+
+    /// The oscillator levels keys play at, modulation included.
+    pub fn modulated_levels(&self) -> [f32; OSC_NR] {
+        let mut levels = self.get_levels();
+        for (osc, level) in levels.iter_mut().enumerate() {
+            let param_id = PARAMETER_LEVEL_1 + osc as u32;
+            *level = (self.modulated(param_id, *level as f64) as f32).clamp(0.0, 1.0);
+        }
+
+        levels
+    }
+
+    /// The coarse pitch of each oscillator, modulation included.
+    pub fn modulated_pitch(&self) -> [f64; OSC_NR] {
+        let mut pitch = self.get_pitch();
+        for (osc, pitch) in pitch.iter_mut().enumerate() {
+            let param_id = PARAMETER_PITCH_1 + osc as u32;
+            *pitch = self
+                .modulated(param_id, *pitch)
+                .clamp(0.0, self.pitch_amount * 2.0);
+        }
+
+        pitch
+    }
+
+    /// The fine tuning of each oscillator in cents, modulation included.
+    pub fn modulated_fine(&self) -> [f32; OSC_NR] {
+        let mut fine = self.get_fine();
+        for (osc, fine) in fine.iter_mut().enumerate() {
+            let param_id = PARAMETER_FINE_1 + osc as u32;
+            *fine = (self.modulated(param_id, *fine as f64) as f32).clamp(-100.0, 100.0);
+        }
+
+        fine
+    }
+
+    /// The modulation index keys play with, modulation included.
+    pub fn modulated_mod_index(&self) -> f32 {
+        let mod_index = self.get_mod_index() as f64;
+        (self.modulated(PARAMETER_MOD_INDEX, mod_index) as f32).clamp(0.0, 4.0)
+    }
+
+    /// The evil depth keys play with, modulation included.
+    pub fn modulated_evil_depth(&self) -> f32 {
+        let evil_depth = self.get_evil_depth() as f64;
+        (self.modulated(PARAMETER_EVIL_DEPTH, evil_depth) as f32).clamp(0.0, 4.0)
+    }
+
+    /// The quality keys get processed at. Offline renders always go out at the highest quality,
+    /// since they don't have to keep up with realtime.
+    pub fn render_quality(&self) -> Quality {
+        if self.get_offline() {
+            Quality::High
+        } else {
+            self.get_quality()
+        }
+    }
+
+    /// The oversampling keys get processed at. [`Quality::High`] oversamples at least 2x, and
+    /// offline renders always go out at 4x.
+    pub fn render_oversample(&self) -> Oversample {
+        if self.get_offline() {
+            return Oversample::X4;
+        }
+
+        match (self.get_quality(), self.get_oversample()) {
+            (Quality::High, Oversample::X1) => Oversample::X2,
+            (_, oversample) => oversample,
+        }
+    }
+
+    pub fn get_vel_to_mod(&self) -> f32 {
+        self.vel_to_mod.load()
+    }
+
+    pub fn get_evil_depth(&self) -> f32 {
+        self.evil_depth.load()
+    }
+
+    pub fn get_osc_velocity(&self) -> [f32; OSC_NR] {
+        self.osc_velocity.load()
+    }
+
+    pub fn get_osc_velocity_mut(&self) -> ParamWriteGuard<'_, [f32; OSC_NR]> {
+        ParamWriteGuard::new(&self.osc_velocity)
+    }
+
+    pub fn get_split(&self) -> usize {
+        self.split.load()
+    }
+
+    pub fn get_zones(&self) -> [Zone; OSC_NR] {
+        self.zones.load()
+    }
+
+    pub fn get_zones_mut(&self) -> ParamWriteGuard<'_, [Zone; OSC_NR]> {
+        ParamWriteGuard::new(&self.zones)
+    }
+
+    pub fn get_blep_width(&self) -> f32 {
+        self.blep_width.load()
+    }
+
+    pub fn get_pan(&self) -> [f32; OSC_NR] {
+        self.pan.load()
+    }
+
+    pub fn get_pan_mut(&self) -> ParamWriteGuard<'_, [f32; OSC_NR]> {
+        ParamWriteGuard::new(&self.pan)
+    }
+
+    pub fn get_pitch_bend_range(&self) -> u32 {
+        self.pitch_bend_range.load()
+    }
+
+    pub fn get_pulse_width(&self) -> f32 {
+        self.pulse_width.load()
+    }
+
+    pub fn get_cutoff(&self) -> f32 {
+        self.cutoff.load()
+    }
+
+    pub fn get_resonance(&self) -> f32 {
+        self.resonance.load()
+    }
+
+    pub fn get_lfo(&self) -> LfoSettings {
+        self.lfo.load()
+    }
+
+    pub fn get_lfo_mut(&self) -> ParamWriteGuard<'_, LfoSettings> {
+        ParamWriteGuard::new(&self.lfo)
+    }
+
+    pub fn get_gain(&self) -> f32 {
+        self.gain.load()
+    }
+
+    pub fn get_release_vel_amount(&self) -> f32 {
+        self.release_vel_amount.load()
+    }
+
+    pub fn get_fm(&self) -> FmSettings {
+        self.fm.load()
+    }
+
+    pub fn get_fm_mut(&self) -> ParamWriteGuard<'_, FmSettings> {
+        ParamWriteGuard::new(&self.fm)
+    }
+
+    pub fn get_sub_level(&self) -> f32 {
+        self.sub_level.load()
+    }
+
+    pub fn get_unison(&self) -> Unison {
+        self.unison.load()
+    }
+
+    pub fn get_unison_mut(&self) -> ParamWriteGuard<'_, Unison> {
+        ParamWriteGuard::new(&self.unison)
+    }
+
+    pub fn get_phase_mode(&self) -> PhaseMode {
+        self.phase_mode.load()
+    }
+
+    pub fn get_max_voices(&self) -> u32 {
+        self.max_voices.load()
+    }
+
+    pub fn get_poly_mode(&self) -> PolyMode {
+        self.poly_mode.load()
+    }
+
+    pub fn get_aftertouch_amount(&self) -> f32 {
+        self.aftertouch_amount.load()
+    }
+
+    pub fn get_concert_pitch(&self) -> f32 {
+        self.concert_pitch.load()
+    }
+
+    pub fn get_octave(&self) -> [i32; OSC_NR] {
+        self.octave.load()
+    }
+
+    pub fn get_octave_mut(&self) -> ParamWriteGuard<'_, [i32; OSC_NR]> {
+        ParamWriteGuard::new(&self.octave)
+    }
+
+    /// The octave shift of each oscillator, in steps of the temperament.
+    pub fn octave_steps(&self) -> [f64; OSC_NR] {
+        self.get_octave()
+            .map(|octave| octave as f64 * self.n_tet() as f64)
+    }
+
+    pub fn get_drive(&self) -> f32 {
+        self.drive.load()
+    }
+
+    pub fn get_oversample(&self) -> Oversample {
+        self.oversample.load()
+    }
+
+    pub fn get_temperament(&self) -> Temperament {
+        self.temperament.load()
+    }
+
+    /// The number of steps in an octave of the temperament being played in.
+    pub fn n_tet(&self) -> f32 {
+        self.get_temperament().divisions()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn random_waveforms_are_every_concrete_one() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut chosen = [false; Waveform::PinkNoise as usize + 1];
+        for _ in 0..1000 {
+            chosen[Waveform::random(&mut rng) as usize] = true;
+        }
+
+        for (waveform, &chosen) in chosen.iter().enumerate() {
+            let waveform = Waveform::from(waveform as f64);
+            assert_eq!(
+                chosen,
+                !matches!(waveform, Waveform::Random),
+                "{}",
+                waveform.as_str()
+            );
+        }
+    }
+
+    #[test]
+    fn randomized_envelopes_stay_in_range() {
+        let shared = Fox3oscShared::new(Temperament::Tet12);
+        let mut rng = SmallRng::seed_from_u64(0);
+        for _ in 0..1000 {
+            shared.randomize(&mut rng);
+            for envelope in shared.get_envelope() {
+                // The range every envelope parameter is declared with.
+                assert!(
+                    envelope
+                        .segments()
+                        .iter()
+                        .all(|segment| (0.0..=1.0).contains(segment)),
+                    "{:?}",
+                    envelope.segments()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn velocity_curves_keep_their_ends_and_bend_the_middle() {
+        for curve in [
+            VelocityCurve::Linear,
+            VelocityCurve::Exponential,
+            VelocityCurve::Logarithmic,
+            VelocityCurve::SCurve,
+        ] {
+            assert_eq!(curve.apply(0.0), 0.0, "{}", curve.as_str());
+            assert_eq!(curve.apply(1.0), 1.0, "{}", curve.as_str());
+        }
+
+        assert_eq!(VelocityCurve::Linear.apply(0.5), 0.5);
+        assert_eq!(VelocityCurve::Exponential.apply(0.5), 0.25);
+        assert_eq!(VelocityCurve::Logarithmic.apply(0.5), 0.5f32.sqrt());
+        assert_eq!(VelocityCurve::SCurve.apply(0.5), 0.5);
+
+        // The S-curve goes through the middle like the linear one does, but softer below it and
+        // harder above it.
+        assert!(VelocityCurve::SCurve.apply(0.25) < 0.25);
+        assert!(VelocityCurve::SCurve.apply(0.75) > 0.75);
+    }
+
+    #[test]
+    fn envelope_curves_keep_their_ends_and_bend_the_middle() {
+        for curve in [EnvelopeCurve::Linear, EnvelopeCurve::Exponential] {
+            assert_eq!(curve.shape(0.0), 0.0, "{}", curve.as_str());
+            assert_eq!(curve.shape(1.0), 1.0, "{}", curve.as_str());
+        }
 
-    pub fn get_pitch_mut(&self) -> Result<RwLockWriteGuard<'_, [f64; OSC_NR]>, PluginError> {
-        self.pitch.write().or(Err(Self::PARAMETER_WRITE_ERR))
+        // Halfway through a segment, the exponential curve has already covered most of it.
+        assert_eq!(EnvelopeCurve::Linear.shape(0.5), 0.5);
+        assert_eq!(EnvelopeCurve::Exponential.shape(0.5), 0.875);
     }
 }
@@ -0,0 +1,55 @@
+/// Time constant of a [`Smoother`] in seconds. It gets about two thirds of the way to a new target
+/// in this time.
+const SMOOTHING_TIME: f32 = 0.005;
+/// How close to its target a [`Smoother`] has to get, relative to the target, before it lands right
+/// on it.
+const SETTLE_DISTANCE: f32 = 1e-5;
+
+/// Glides a parameter towards new values with a one-pole low-pass, so changes to it don't step
+/// audibly. It lands exactly on its target once it's close enough, so a parameter which stays put
+/// ends up at precisely its value.
+#[derive(Clone, Copy)]
+pub struct Smoother {
+    value: f32,
+    target: f32,
+    /// Share of the distance to the target covered every sample.
+    coefficient: f32,
+}
+
+impl Smoother {
+    pub fn new(value: f32, sample_rate: f32) -> Self {
+        Self {
+            value,
+            target: value,
+            coefficient: 1.0 - (-1.0 / (SMOOTHING_TIME * sample_rate)).exp(),
+        }
+    }
+
+    /// Starts gliding towards `target`.
+    pub fn set(&mut self, target: f32) {
+        self.target = target;
+    }
+
+    /// Jumps straight to `value`, without gliding.
+    pub fn reset(&mut self, value: f32) {
+        self.value = value;
+        self.target = value;
+    }
+
+    /// Whether the smoother has landed on its target.
+    pub fn is_settled(&self) -> bool {
+        self.value == self.target
+    }
+
+    /// Returns the value of the next sample.
+    pub fn process(&mut self) -> f32 {
+        let distance = self.target - self.value;
+        if distance.abs() <= SETTLE_DISTANCE * self.target.abs().max(1.0) {
+            self.value = self.target;
+        } else {
+            self.value += distance * self.coefficient;
+        }
+
+        self.value
+    }
+}
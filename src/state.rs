@@ -0,0 +1,732 @@
+use std::{
+    io::{Read, Write},
+    ops::RangeInclusive,
+};
+
+use clack_plugin::prelude::*;
+
+use crate::{
+    consts::{
+        ARP_OCTAVES_MAX, CONCERT_PITCH_MAX, CONCERT_PITCH_MIN, GAIN_MIN, KEYS_NR, LFO_RATE_MAX,
+        LFO_RATE_MIN, MAX_DELAY_TIME, MOD_RATIOS, OCTAVE_MAX, OSC_NR, PARTIALS_MAX,
+        PITCH_BEND_RANGE_MAX, REFERENCE_FREQUENCY, UNISON_DETUNE_MAX, UNISON_MAX,
+    },
+    presets::INIT,
+    shared::{
+        Algorithm, ArpMode, ArpRate, ArpSettings, ChorusSettings, DelaySettings, Envelope,
+        EnvelopeCurve, FmSettings, Glide, GlideMode, LfoDestination, LfoDivision, LfoSettings,
+        LfoShape, MasterTuning, Modulation, Oversample, PhaseMode, PitchEnvelope, PolyMode,
+        Quality, Temperament, Unison, VelocityCurve, Waveform, Zone,
+    },
+};
+
+/// Marks the start of a versioned state. As an `f32` it's well above 1.0, so it's never mistaken for
+/// the attack time the unversioned states from before it start with.
+const STATE_MAGIC: [u8; 4] = *b"F3OC";
+
+/// Version of the state layout written by [`PatchState::write`]. New parameters get appended to the
+/// layout along with a version bump, and are only read out of states of that version onwards.
+const STATE_VERSION: u16 = 23;
+
+/// Every parameter of a patch, as it gets saved in the plugin's state.
+#[derive(Clone, Copy)]
+pub struct PatchState {
+    pub envelope: [Envelope; OSC_NR],
+    pub waveforms: [Waveform; OSC_NR],
+    pub levels: [f32; OSC_NR],
+    pub quality: Quality,
+    pub modulation: Modulation,
+    pub pitch: [f64; OSC_NR],
+    pub fine: [f32; OSC_NR],
+    pub velocity_sensitivity: f32,
+    pub velocity_curve: VelocityCurve,
+    pub key_track: f32,
+    pub mod_index: f32,
+    pub pitch_envelope: PitchEnvelope,
+    pub arp_settings: ArpSettings,
+    pub master_tuning: MasterTuning,
+    pub noise_color: f32,
+    pub mute: [bool; OSC_NR],
+    pub solo: [bool; OSC_NR],
+    pub glide: Glide,
+    pub partials: u32,
+    pub fold: [f32; OSC_NR],
+    pub fm_feedback: f32,
+    pub algorithm: Algorithm,
+    pub ratio_lock: bool,
+    pub stereo_spread: f32,
+    pub chorus: ChorusSettings,
+    pub delay: DelaySettings,
+    pub limit: f32,
+    pub vel_to_mod: f32,
+    pub evil_depth: f32,
+    pub osc_velocity: [f32; OSC_NR],
+    pub split: usize,
+    pub zones: [Zone; OSC_NR],
+    pub blep_width: f32,
+    pub pan: [f32; OSC_NR],
+    pub pitch_bend_range: u32,
+    pub pulse_width: f32,
+    pub cutoff: f32,
+    pub resonance: f32,
+    pub lfo: LfoSettings,
+    pub gain: f32,
+    pub release_vel_amount: f32,
+    pub fm: FmSettings,
+    pub sub_level: f32,
+    pub unison: Unison,
+    pub phase_mode: PhaseMode,
+    pub max_voices: u32,
+    pub poly_mode: PolyMode,
+    pub aftertouch_amount: f32,
+    pub concert_pitch: f32,
+    pub octave: [i32; OSC_NR],
+    pub drive: f32,
+    pub oversample: Oversample,
+    pub temperament: Temperament,
+}
+
+impl PatchState {
+    /// Write the patch as a state, headed by the version of its layout.
+    pub fn write(&self, output: &mut impl Write) -> Result<(), PluginError> {
+        output.write_all(&STATE_MAGIC)?;
+        output.write_all(&STATE_VERSION.to_le_bytes())?;
+
+        // Oscillator 1's envelope goes where the only envelope used to be, with the others written
+        // at the end.
+        for segment in self.envelope[0].segments() {
+            output.write_all(&segment.to_le_bytes())?;
+        }
+
+        for &waveform in self.waveforms.iter() {
+            output.write_all(&f64::from(waveform).to_le_bytes())?;
+        }
+
+        for &level in self.levels.iter() {
+            output.write_all(&level.to_le_bytes())?;
+        }
+
+        for _ in 0..OSC_NR {
+            output.write_all(&(self.quality as u32).to_le_bytes())?;
+        }
+
+        output.write_all(&f64::from(self.modulation).to_le_bytes())?;
+        for &pitch in self.pitch.iter() {
+            output.write_all(&pitch.to_le_bytes())?;
+        }
+
+        output.write_all(&self.velocity_sensitivity.to_le_bytes())?;
+        output.write_all(&f64::from(self.velocity_curve).to_le_bytes())?;
+        output.write_all(&f64::from(self.envelope[0].curve).to_le_bytes())?;
+        output.write_all(&self.key_track.to_le_bytes())?;
+        output.write_all(&self.mod_index.to_le_bytes())?;
+        for &amount in self.pitch_envelope.amount.iter() {
+            output.write_all(&amount.to_le_bytes())?;
+        }
+
+        output.write_all(&self.pitch_envelope.time.to_le_bytes())?;
+        output.write_all(&(self.arp_settings.on as u32).to_le_bytes())?;
+        output.write_all(&f64::from(self.arp_settings.mode).to_le_bytes())?;
+        output.write_all(&f64::from(self.arp_settings.rate).to_le_bytes())?;
+        output.write_all(&self.arp_settings.octaves.to_le_bytes())?;
+        for &fine in self.fine.iter() {
+            output.write_all(&fine.to_le_bytes())?;
+        }
+
+        output.write_all(&self.master_tuning.transpose.to_le_bytes())?;
+        output.write_all(&self.master_tuning.tune.to_le_bytes())?;
+        output.write_all(&self.noise_color.to_le_bytes())?;
+        for &mute in self.mute.iter() {
+            output.write_all(&(mute as u32).to_le_bytes())?;
+        }
+
+        for &solo in self.solo.iter() {
+            output.write_all(&(solo as u32).to_le_bytes())?;
+        }
+
+        output.write_all(&f64::from(self.glide.mode).to_le_bytes())?;
+        output.write_all(&self.glide.time.to_le_bytes())?;
+        output.write_all(&self.partials.to_le_bytes())?;
+        for &fold in self.fold.iter() {
+            output.write_all(&fold.to_le_bytes())?;
+        }
+
+        output.write_all(&self.fm_feedback.to_le_bytes())?;
+        output.write_all(&f64::from(self.algorithm).to_le_bytes())?;
+        output.write_all(&(self.ratio_lock as u32).to_le_bytes())?;
+        output.write_all(&self.stereo_spread.to_le_bytes())?;
+        output.write_all(&self.chorus.rate.to_le_bytes())?;
+        output.write_all(&self.chorus.depth.to_le_bytes())?;
+        output.write_all(&self.chorus.mix.to_le_bytes())?;
+        output.write_all(&self.delay.time.to_le_bytes())?;
+        output.write_all(&self.delay.feedback.to_le_bytes())?;
+        output.write_all(&self.delay.mix.to_le_bytes())?;
+        output.write_all(&(self.delay.sync as u32).to_le_bytes())?;
+        output.write_all(&self.limit.to_le_bytes())?;
+        output.write_all(&self.vel_to_mod.to_le_bytes())?;
+        output.write_all(&self.evil_depth.to_le_bytes())?;
+        for &osc_velocity in self.osc_velocity.iter() {
+            output.write_all(&osc_velocity.to_le_bytes())?;
+        }
+
+        output.write_all(&(self.split as u32).to_le_bytes())?;
+        for &zone in self.zones.iter() {
+            output.write_all(&f64::from(zone).to_le_bytes())?;
+        }
+
+        output.write_all(&self.blep_width.to_le_bytes())?;
+        for &pan in self.pan.iter() {
+            output.write_all(&pan.to_le_bytes())?;
+        }
+        output.write_all(&self.pitch_bend_range.to_le_bytes())?;
+        output.write_all(&self.pulse_width.to_le_bytes())?;
+        output.write_all(&self.cutoff.to_le_bytes())?;
+        output.write_all(&self.resonance.to_le_bytes())?;
+        output.write_all(&f64::from(self.lfo.shape).to_le_bytes())?;
+        output.write_all(&self.lfo.rate.to_le_bytes())?;
+        output.write_all(&self.lfo.depth.to_le_bytes())?;
+        output.write_all(&f64::from(self.lfo.destination).to_le_bytes())?;
+        output.write_all(&(self.lfo.sync as u32).to_le_bytes())?;
+        output.write_all(&f64::from(self.lfo.division).to_le_bytes())?;
+        output.write_all(&self.gain.to_le_bytes())?;
+        for envelope in &self.envelope[1..] {
+            for segment in envelope.segments() {
+                output.write_all(&segment.to_le_bytes())?;
+            }
+        }
+        output.write_all(&self.release_vel_amount.to_le_bytes())?;
+
+        output.write_all(&(self.fm.ratio as u32).to_le_bytes())?;
+        output.write_all(&self.fm.depth.to_le_bytes())?;
+        output.write_all(&self.sub_level.to_le_bytes())?;
+        output.write_all(&self.unison.count.to_le_bytes())?;
+        output.write_all(&self.unison.detune.to_le_bytes())?;
+        output.write_all(&f64::from(self.phase_mode).to_le_bytes())?;
+        output.write_all(&self.max_voices.to_le_bytes())?;
+        output.write_all(&f64::from(self.poly_mode).to_le_bytes())?;
+        output.write_all(&self.aftertouch_amount.to_le_bytes())?;
+        output.write_all(&self.concert_pitch.to_le_bytes())?;
+        for &octave in self.octave.iter() {
+            output.write_all(&octave.to_le_bytes())?;
+        }
+        output.write_all(&self.drive.to_le_bytes())?;
+        output.write_all(&f64::from(self.oversample).to_le_bytes())?;
+        output.write_all(&f64::from(self.temperament).to_le_bytes())?;
+
+        Ok(())
+    }
+
+    /// Read a patch out of a state written by [`PatchState::write`], or out of an unversioned
+    /// state, which is read as version 0. Those only hold the envelope, waveforms, levels, HQ
+    /// switches, modulation and pitches, which versioned states still start with. `pitch_amount`
+    /// is the pitch parameter value for no shift, and `temperament` the plugin's default one.
+    pub fn read(
+        input: &mut impl Read,
+        pitch_amount: f64,
+        temperament: Temperament,
+    ) -> Result<Self, PluginError> {
+        let mut buf = [0; 4];
+        input.read_exact(&mut buf)?;
+
+        if buf != STATE_MAGIC {
+            // What was read is the start of the attack time, so it's put back in front.
+            return Self::read_fields(&mut (&buf[..]).chain(input), 0, pitch_amount, temperament);
+        }
+
+        let mut buf = [0; 2];
+        input.read_exact(&mut buf)?;
+        let version = u16::from_le_bytes(buf);
+        if version > STATE_VERSION {
+            return Err(PluginError::Message(
+                "State was saved by a newer version of the plugin",
+            ));
+        }
+
+        Self::read_fields(input, version, pitch_amount, temperament)
+    }
+
+    /// Read every field of the patch, in the order [`PatchState::write`] writes them in. Fields
+    /// newer than `version` are left at their init patch values.
+    fn read_fields(
+        input: &mut impl Read,
+        version: u16,
+        pitch_amount: f64,
+        temperament: Temperament,
+    ) -> Result<Self, PluginError> {
+        // Every field gets read, the init patch is only where the reading starts from.
+        let mut state = INIT;
+
+        self::read_envelope(input, &mut state.envelope[0])?;
+        for waveform in state.waveforms.iter_mut() {
+            *waveform = self::read_enum(input, Waveform::PinkNoise)?;
+        }
+
+        for (osc, level) in state.levels.iter_mut().enumerate() {
+            *level = self::read_f32(input, 0.0..=1.0, if osc == 0 { 1.0 } else { 0.0 })?;
+        }
+
+        // Older states have an HQ switch per oscillator here. The quality gets written in each of
+        // their slots, so both old and new states load.
+        let mut buf = [0; 4];
+        let mut discriminant = 0;
+        for _ in 0..OSC_NR {
+            input.read_exact(&mut buf)?;
+            discriminant = discriminant.max(u32::from_le_bytes(buf));
+        }
+
+        state.quality = (discriminant.min(Quality::High as u32) as f64).into();
+        state.modulation = self::read_enum(input, Modulation::Frequency)?;
+        // Older states have their pitches counted from 2 octaves of the plugin's own temperament
+        // down, rather than from 2 octaves of the finest one, so they're moved over to that.
+        let saved_pitch_amount = if version >= 23 {
+            pitch_amount
+        } else {
+            temperament.divisions() as f64 * 2.0
+        };
+        for pitch in state.pitch.iter_mut() {
+            *pitch = self::read_f64(input, 0.0..=saved_pitch_amount * 2.0, saved_pitch_amount)?
+                - saved_pitch_amount
+                + pitch_amount;
+        }
+
+        // Unversioned states end here, with every parameter after staying at its init patch value.
+        if version >= 1 {
+            state.velocity_sensitivity = self::read_f32(input, 0.0..=1.0, 1.0)?;
+            state.velocity_curve = self::read_enum(input, VelocityCurve::SCurve)?;
+            let curve = self::read_enum(input, EnvelopeCurve::Exponential)?;
+            state
+                .envelope
+                .iter_mut()
+                .for_each(|envelope| envelope.curve = curve);
+            state.key_track = self::read_f32(input, -1.0..=1.0, 0.0)?;
+            state.mod_index = self::read_f32(input, 0.0..=4.0, 1.0)?;
+
+            let pitch_envelope_defaults = PitchEnvelope::default();
+            for (osc, amount) in state.pitch_envelope.amount.iter_mut().enumerate() {
+                *amount = self::read_f32(input, -24.0..=24.0, pitch_envelope_defaults.amount[osc])?;
+            }
+
+            state.pitch_envelope.time =
+                self::read_f32(input, 0.0..=1.0, pitch_envelope_defaults.time)?;
+            input.read_exact(&mut buf)?;
+            state.arp_settings.on = u32::from_le_bytes(buf) != 0;
+            state.arp_settings.mode = self::read_enum(input, ArpMode::Random)?;
+            state.arp_settings.rate = self::read_enum(input, ArpRate::ThirtySecond)?;
+            input.read_exact(&mut buf)?;
+            state.arp_settings.octaves = u32::from_le_bytes(buf).clamp(1, ARP_OCTAVES_MAX);
+            for fine in state.fine.iter_mut() {
+                *fine = self::read_f32(input, -100.0..=100.0, 0.0)?;
+            }
+
+            state.master_tuning.transpose =
+                self::read_f64(input, -pitch_amount..=pitch_amount, 0.0)?;
+            state.master_tuning.tune = self::read_f32(input, -100.0..=100.0, 0.0)?;
+            state.noise_color = self::read_f32(input, -1.0..=1.0, 0.0)?;
+            for mute in state.mute.iter_mut() {
+                input.read_exact(&mut buf)?;
+                *mute = u32::from_le_bytes(buf) != 0;
+            }
+
+            for solo in state.solo.iter_mut() {
+                input.read_exact(&mut buf)?;
+                *solo = u32::from_le_bytes(buf) != 0;
+            }
+
+            state.glide.mode = self::read_enum(input, GlideMode::Legato)?;
+            state.glide.time = self::read_f32(input, 0.0..=2.0, Glide::default().time)?;
+            input.read_exact(&mut buf)?;
+            state.partials = u32::from_le_bytes(buf).clamp(1, PARTIALS_MAX);
+            for fold in state.fold.iter_mut() {
+                *fold = self::read_f32(input, 1.0..=8.0, 1.0)?;
+            }
+
+            state.fm_feedback = self::read_f32(input, 0.0..=1.0, 0.0)?;
+            state.algorithm = self::read_enum(input, Algorithm::Parallel)?;
+            input.read_exact(&mut buf)?;
+            state.ratio_lock = u32::from_le_bytes(buf) != 0;
+            state.stereo_spread = self::read_f32(input, 0.0..=1.0, 0.0)?;
+
+            let chorus_defaults = ChorusSettings::default();
+            state.chorus.rate = self::read_f32(input, 0.05..=5.0, chorus_defaults.rate)?;
+            state.chorus.depth = self::read_f32(input, 0.0..=1.0, chorus_defaults.depth)?;
+            state.chorus.mix = self::read_f32(input, 0.0..=1.0, chorus_defaults.mix)?;
+
+            let delay_defaults = DelaySettings::default();
+            state.delay.time = self::read_f32(input, 0.0..=MAX_DELAY_TIME, delay_defaults.time)?;
+            state.delay.feedback = self::read_f32(input, 0.0..=0.95, delay_defaults.feedback)?;
+            state.delay.mix = self::read_f32(input, 0.0..=1.0, delay_defaults.mix)?;
+            input.read_exact(&mut buf)?;
+            state.delay.sync = u32::from_le_bytes(buf) != 0;
+            state.limit = self::read_f32(input, -24.0..=0.0, 0.0)?;
+            state.vel_to_mod = self::read_f32(input, 0.0..=1.0, 0.0)?;
+            state.evil_depth = self::read_f32(input, 0.0..=4.0, 1.0)?;
+            for osc_velocity in state.osc_velocity.iter_mut() {
+                *osc_velocity = self::read_f32(input, 0.0..=1.0, 1.0)?;
+            }
+
+            input.read_exact(&mut buf)?;
+            state.split = (u32::from_le_bytes(buf) as usize).min(KEYS_NR - 1);
+            for zone in state.zones.iter_mut() {
+                *zone = self::read_enum(input, Zone::Upper)?;
+            }
+
+            state.blep_width = self::read_f32(input, 0.5..=2.0, 1.0)?;
+        }
+
+        if version >= 2 {
+            for pan in state.pan.iter_mut() {
+                *pan = self::read_f32(input, -1.0..=1.0, 0.0)?;
+            }
+        }
+
+        if version >= 3 {
+            input.read_exact(&mut buf)?;
+            state.pitch_bend_range = u32::from_le_bytes(buf).min(PITCH_BEND_RANGE_MAX);
+        }
+
+        if version >= 4 {
+            state.pulse_width = self::read_f32(input, 0.0..=1.0, 0.5)?;
+        }
+
+        if version >= 5 {
+            state.cutoff = self::read_f32(input, 0.0..=1.0, 1.0)?;
+            state.resonance = self::read_f32(input, 0.0..=1.0, 0.0)?;
+        }
+
+        if version >= 6 {
+            let lfo_defaults = LfoSettings::default();
+            state.lfo.shape = self::read_enum(input, LfoShape::Square)?;
+            state.lfo.rate = self::read_f32(input, LFO_RATE_MIN..=LFO_RATE_MAX, lfo_defaults.rate)?;
+            state.lfo.depth = self::read_f32(input, 0.0..=1.0, lfo_defaults.depth)?;
+            state.lfo.destination = self::read_enum(input, LfoDestination::Cutoff)?;
+        }
+
+        if version >= 7 {
+            input.read_exact(&mut buf)?;
+            state.lfo.sync = u32::from_le_bytes(buf) != 0;
+            state.lfo.division = self::read_enum(input, LfoDivision::Sixteenth)?;
+        }
+
+        if version >= 8 {
+            state.gain = self::read_f32(input, GAIN_MIN..=0.0, 0.0)?;
+        }
+
+        if version >= 9 {
+            for envelope in state.envelope[1..].iter_mut() {
+                self::read_envelope(input, envelope)?;
+            }
+        } else {
+            // Older states have a single envelope, which every oscillator plays with.
+            state.envelope = [state.envelope[0]; OSC_NR];
+        }
+
+        if version >= 10 {
+            state.release_vel_amount = self::read_f32(input, 0.0..=1.0, 0.0)?;
+        }
+
+        if version >= 11 {
+            input.read_exact(&mut buf)?;
+            state.fm.ratio = (u32::from_le_bytes(buf) as usize).min(MOD_RATIOS.len() - 1);
+            state.fm.depth = self::read_f32(input, 0.0..=1.0, FmSettings::default().depth)?;
+        }
+
+        if version >= 12 {
+            state.sub_level = self::read_f32(input, 0.0..=1.0, 0.0)?;
+        }
+
+        if version >= 13 {
+            input.read_exact(&mut buf)?;
+            state.unison.count = u32::from_le_bytes(buf).clamp(1, UNISON_MAX as u32);
+            state.unison.detune =
+                self::read_f32(input, 0.0..=UNISON_DETUNE_MAX, Unison::default().detune)?;
+        }
+
+        if version >= 14 {
+            state.phase_mode = self::read_enum(input, PhaseMode::Random)?;
+        }
+
+        if version >= 15 {
+            input.read_exact(&mut buf)?;
+            state.max_voices = u32::from_le_bytes(buf).clamp(1, KEYS_NR as u32);
+        }
+
+        if version >= 16 {
+            state.poly_mode = self::read_enum(input, PolyMode::Legato)?;
+        }
+
+        if version >= 17 {
+            state.aftertouch_amount = self::read_f32(input, 0.0..=1.0, 1.0)?;
+        }
+
+        if version >= 18 {
+            state.concert_pitch = self::read_f32(
+                input,
+                CONCERT_PITCH_MIN..=CONCERT_PITCH_MAX,
+                REFERENCE_FREQUENCY,
+            )?;
+        }
+
+        if version >= 19 {
+            for octave in state.octave.iter_mut() {
+                input.read_exact(&mut buf)?;
+                *octave = i32::from_le_bytes(buf).clamp(-OCTAVE_MAX, OCTAVE_MAX);
+            }
+        }
+
+        if version >= 20 {
+            state.drive = self::read_f32(input, 0.0..=1.0, 0.0)?;
+        }
+
+        if version >= 21 {
+            state.oversample = self::read_enum(input, Oversample::X4)?;
+        }
+
+        // Older states were played in the plugin's own temperament, which the pitch values count
+        // steps of.
+        state.temperament = if version >= 22 {
+            self::read_enum(input, Temperament::Tet53)?
+        } else {
+            temperament
+        };
+
+        Ok(state)
+    }
+}
+
+/// Reads the attack, decay, sustain and release of an envelope out of a state.
+fn read_envelope(input: &mut impl Read, envelope: &mut Envelope) -> Result<(), PluginError> {
+    for (value, default) in envelope
+        .segments_mut()
+        .into_iter()
+        .zip(Envelope::default().segments())
+    {
+        *value = self::read_f32(input, 0.0..=1.0, default)?;
+    }
+
+    Ok(())
+}
+
+/// Reads an `f32` parameter value out of a state, clamped to `range`. Values which aren't finite can
+/// only come from a corrupt state, and are replaced with `default` so they never reach the audio
+/// thread.
+fn read_f32(
+    input: &mut impl Read,
+    range: RangeInclusive<f32>,
+    default: f32,
+) -> Result<f32, PluginError> {
+    let mut buf = [0; 4];
+    input.read_exact(&mut buf)?;
+
+    let value = f32::from_le_bytes(buf);
+    Ok(if value.is_finite() {
+        value.clamp(*range.start(), *range.end())
+    } else {
+        default
+    })
+}
+
+/// Same as [`read_f32`], for the parameters which are stored as `f64`.
+fn read_f64(
+    input: &mut impl Read,
+    range: RangeInclusive<f64>,
+    default: f64,
+) -> Result<f64, PluginError> {
+    let mut buf = [0; 8];
+    input.read_exact(&mut buf)?;
+
+    let value = f64::from_le_bytes(buf);
+    Ok(if value.is_finite() {
+        value.clamp(*range.start(), *range.end())
+    } else {
+        default
+    })
+}
+
+/// Reads an enum parameter out of a state, whose variants go from the first one up to `last`.
+/// Anything else would transmute into an invalid variant.
+fn read_enum<E>(input: &mut impl Read, last: E) -> Result<E, PluginError>
+where
+    E: Default + From<f64> + Into<f64>,
+{
+    Ok(self::read_f64(input, 0.0..=last.into(), E::default().into())?.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{consts::PARAMETER_NR, shared::Fox3oscShared};
+
+    /// Bytes taken up by the magic and the version in front of a versioned state.
+    const HEADER_LEN: usize = STATE_MAGIC.len() + size_of::<u16>();
+
+    /// An unversioned state as saved before states were versioned, with the HQ switches set to
+    /// `hq`. It's laid out by hand, the same way the plugin used to write it.
+    fn unversioned_state(hq: [u32; OSC_NR]) -> Vec<u8> {
+        let mut saved = Vec::new();
+        for segment in [0.2f32, 0.3, 0.6, 0.4] {
+            saved.extend_from_slice(&segment.to_le_bytes());
+        }
+
+        // Saw, Square and Sploinky.
+        for waveform in [3.0f64, 2.0, 5.0] {
+            saved.extend_from_slice(&waveform.to_le_bytes());
+        }
+
+        for level in [0.75f32, 0.5, 0.25] {
+            saved.extend_from_slice(&level.to_le_bytes());
+        }
+
+        for hq in hq {
+            saved.extend_from_slice(&hq.to_le_bytes());
+        }
+
+        // Phase modulation.
+        saved.extend_from_slice(&1.0f64.to_le_bytes());
+        // An octave up, no shift and 5 steps down in a 19-TET plugin, counted from 2 octaves of
+        // 19-TET down like they were back then.
+        for pitch in [57.0f64, 38.0, 33.0] {
+            saved.extend_from_slice(&pitch.to_le_bytes());
+        }
+
+        assert_eq!(saved.len(), 96);
+        saved
+    }
+
+    #[test]
+    fn unversioned_states_load_in_their_original_layout() {
+        let pitch_amount = Fox3oscShared::new(Temperament::Tet12).pitch_amount;
+        let unversioned = self::unversioned_state([0; OSC_NR]);
+
+        // The plugin's own temperament is the one unversioned states were played in.
+        let state = PatchState::read(
+            &mut unversioned.as_slice(),
+            pitch_amount,
+            Temperament::Tet19,
+        )
+        .unwrap();
+        assert!(state.temperament == Temperament::Tet19);
+        // The only envelope there was goes to every oscillator.
+        for envelope in state.envelope {
+            assert_eq!(envelope.segments(), [0.2, 0.3, 0.6, 0.4]);
+        }
+        assert!(matches!(
+            state.waveforms,
+            [Waveform::Saw, Waveform::Square, Waveform::Sploinky]
+        ));
+        assert_eq!(state.levels, [0.75, 0.5, 0.25]);
+        assert!(matches!(state.modulation, Modulation::Phase));
+        assert_eq!(
+            state.pitch,
+            [pitch_amount + 19.0, pitch_amount, pitch_amount - 5.0]
+        );
+
+        // Everything which came after is left at the init patch.
+        assert_eq!(state.fine, INIT.fine);
+        assert_eq!(state.velocity_sensitivity, INIT.velocity_sensitivity);
+        assert_eq!(state.mod_index, INIT.mod_index);
+        assert_eq!(state.pan, INIT.pan);
+        assert_eq!(state.gain, INIT.gain);
+
+        let mut written = Vec::new();
+        state.write(&mut written).unwrap();
+        assert_eq!(&written[..STATE_MAGIC.len()], b"F3OC");
+
+        let mut rewritten = Vec::new();
+        PatchState::read(&mut written.as_slice(), pitch_amount, Temperament::Tet12)
+            .unwrap()
+            .write(&mut rewritten)
+            .unwrap();
+        assert_eq!(written, rewritten);
+    }
+
+    #[test]
+    fn unversioned_hq_switches_load_as_a_quality() {
+        let pitch_amount = Fox3oscShared::new(Temperament::Tet12).pitch_amount;
+        for (hq, quality) in [
+            ([0, 0, 0], Quality::Draft),
+            ([0, 1, 0], Quality::Normal),
+            ([1, 1, 1], Quality::Normal),
+        ] {
+            let unversioned = self::unversioned_state(hq);
+            let state = PatchState::read(
+                &mut unversioned.as_slice(),
+                pitch_amount,
+                Temperament::Tet12,
+            )
+            .unwrap();
+            assert!(state.quality == quality, "{hq:?}");
+        }
+    }
+
+    #[test]
+    fn newer_states_dont_load() {
+        let mut saved = Vec::new();
+        INIT.write(&mut saved).unwrap();
+        saved[STATE_MAGIC.len()..HEADER_LEN].copy_from_slice(&(STATE_VERSION + 1).to_le_bytes());
+
+        assert!(PatchState::read(&mut saved.as_slice(), 24.0, Temperament::Tet12).is_err());
+    }
+
+    #[test]
+    fn states_full_of_nan_and_infinity_load_finite_and_in_range() {
+        let shared = Fox3oscShared::new(Temperament::Tet12);
+
+        for bad in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            // Every field takes a whole number of words, so every `f32` field gets `bad` and every
+            // `f64` one gets a NaN.
+            let mut saved = Vec::new();
+            INIT.write(&mut saved).unwrap();
+            for word in saved[HEADER_LEN..].chunks_exact_mut(4) {
+                word.copy_from_slice(&bad.to_le_bytes());
+            }
+
+            let state = PatchState::read(
+                &mut saved.as_slice(),
+                shared.pitch_amount,
+                shared.default_temperament,
+            )
+            .unwrap();
+            for envelope in state.envelope {
+                assert!(
+                    envelope
+                        .segments()
+                        .iter()
+                        .all(|segment| (0.0..=1.0).contains(segment))
+                );
+            }
+            assert!(state.levels.iter().all(|level| (0.0..=1.0).contains(level)));
+            let pitch_range = 0.0..=shared.pitch_amount * 2.0;
+            assert!(state.pitch.iter().all(|pitch| pitch_range.contains(pitch)));
+            assert!(
+                state
+                    .fine
+                    .iter()
+                    .all(|fine| (-100.0..=100.0).contains(fine))
+            );
+
+            shared.apply_patch_state(&state);
+            for param_id in 0..PARAMETER_NR {
+                if let Some(value) = shared.get_param_value(param_id) {
+                    assert!(
+                        value.is_finite(),
+                        "parameter {param_id} is {value} from {bad}"
+                    );
+                }
+            }
+
+            // Whatever was clamped stays put when it's saved and loaded again.
+            let mut loaded = Vec::new();
+            state.write(&mut loaded).unwrap();
+            let mut reloaded = Vec::new();
+            PatchState::read(
+                &mut loaded.as_slice(),
+                shared.pitch_amount,
+                shared.default_temperament,
+            )
+            .unwrap()
+            .write(&mut reloaded)
+            .unwrap();
+            assert_eq!(loaded, reloaded, "{bad}");
+        }
+    }
+}
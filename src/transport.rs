@@ -0,0 +1,57 @@
+use clack_plugin::events::event_types::{TransportEvent, TransportFlags};
+
+/// Tempo assumed when the host doesn't tell us its tempo.
+const DEFAULT_TEMPO: f64 = 120.0;
+
+/// The host's transport as of the current block. Whatever the host doesn't provide is made up by
+/// free-running: the tempo falls back to [`DEFAULT_TEMPO`], and the song position just keeps
+/// counting up from where the last block left off.
+#[derive(Clone, Copy)]
+pub struct Transport {
+    /// Tempo in beats per minute.
+    pub tempo: f64,
+    /// Whether the host is playing. Always `false` without a transport.
+    pub playing: bool,
+    /// Song position at the start of the block, in beats.
+    pub position: f64,
+    /// Whether the tempo came from the host, rather than being [`DEFAULT_TEMPO`].
+    pub host_tempo: bool,
+    /// Whether the song position came from the host, rather than free-running.
+    pub host_position: bool,
+    /// How many beats the block lasts at the current tempo. The song position is advanced by this
+    /// when free-running.
+    block_beats: f64,
+}
+
+impl Transport {
+    pub fn new() -> Self {
+        Self {
+            tempo: DEFAULT_TEMPO,
+            playing: false,
+            position: 0.0,
+            host_tempo: false,
+            host_position: false,
+            block_beats: 0.0,
+        }
+    }
+
+    /// Reads the transport of a new block from the host, `frames` samples long.
+    pub fn update(&mut self, transport: Option<&TransportEvent>, frames: usize, sample_rate: f32) {
+        let flags = transport.map_or(TransportFlags::empty(), |transport| transport.flags);
+
+        self.host_tempo = flags.contains(TransportFlags::HAS_TEMPO);
+        self.host_position = flags.contains(TransportFlags::HAS_BEATS_TIMELINE);
+        self.tempo = match transport {
+            Some(transport) if flags.contains(TransportFlags::HAS_TEMPO) => transport.tempo,
+            _ => DEFAULT_TEMPO,
+        };
+        self.playing = flags.contains(TransportFlags::IS_PLAYING);
+        self.position = match transport {
+            Some(transport) if flags.contains(TransportFlags::HAS_BEATS_TIMELINE) => {
+                transport.song_pos_beats.to_float()
+            }
+            _ => self.position + self.block_beats,
+        };
+        self.block_beats = frames as f64 * self.tempo / 60.0 / sample_rate as f64;
+    }
+}